@@ -1,6 +1,16 @@
 mod traits;
 pub use self::traits::*;
 
+mod restoration_tracker;
+use self::restoration_tracker::RestorationTracker;
+
+mod target_scorer;
+use self::target_scorer::{ScoringContext, TargetScorer};
+
+mod state_machine;
+pub use self::state_machine::StateTransition;
+use self::state_machine::{MachineState, StateMachine, StatefulBehavior};
+
 mod farming_behavior;
 pub use self::farming_behavior::FarmingBehavior;
 
@@ -1,21 +1,24 @@
 use std::time::{Duration, Instant};
 
+use guard::guard;
 use libscreenshot::shared::Area;
-use rand::prelude::SliceRandom;
+use rand::{prelude::SliceRandom, Rng};
 use slog::Logger;
-use tauri::Window;
 
 use crate::{
     data::{Bounds, MobType, PixelDetection, PixelDetectionKind, Target, TargetType},
     image_analyzer::ImageAnalyzer,
-    ipc::{BotConfig, FarmingConfig, FrontendInfo, SlotType},
-    movement::MovementAccessor,
-    platform::{send_slot_eval, eval_mouse_move, eval_mouse_click_at_point},
-    play,
+    ipc::{BotConfig, FarmingConfig, FarmingStrategy, FrontendInfo, SlotType},
+    movement::{MovementAccessor, MovementHandle, Script, TURN_RATE},
+    platform::{CapturePoint, InputError, InputSink},
+    play, play_async,
     utils::DateTime,
 };
 
-use super::Behavior;
+use super::{
+    Behavior, MachineState, RestorationTracker, ScoringContext, StateMachine, StateTransition,
+    StatefulBehavior, TargetScorer,
+};
 
 #[derive(Debug, Clone, Copy)]
 enum State {
@@ -24,14 +27,38 @@ enum State {
     EnemyFound(Target),
     Attacking(Target),
     AfterEnemyKill(Target),
+    /// Hitting a mob once so it follows the player
+    Tagging(Target),
+    /// Waiting for tagged mobs to gather around the player
+    Gathering,
+    /// Using area skills until gathered mobs are dead
+    Bursting,
+    /// Flying to the next hunting spot
+    Travelling,
 }
 
-pub struct FarmingBehavior<'a> {
+impl MachineState for State {
+    fn name(&self) -> &'static str {
+        match self {
+            State::NoEnemyFound => "NoEnemyFound",
+            State::SearchingForEnemy => "SearchingForEnemy",
+            State::EnemyFound(_) => "EnemyFound",
+            State::Attacking(_) => "Attacking",
+            State::AfterEnemyKill(_) => "AfterEnemyKill",
+            State::Tagging(_) => "Tagging",
+            State::Gathering => "Gathering",
+            State::Bursting => "Bursting",
+            State::Travelling => "Travelling",
+        }
+    }
+}
+
+pub struct FarmingBehavior<'a, I: InputSink> {
     rng: rand::rngs::ThreadRng,
     logger: &'a Logger,
     movement: &'a MovementAccessor,
-    window: &'a Window,
-    state: State,
+    input: &'a I,
+    machine: StateMachine<State>,
     slots_usage_last_time: [[Option<Instant>; 10]; 9],
     last_initial_attack_time: Instant,
     last_kill_time: Instant,
@@ -41,26 +68,45 @@ pub struct FarmingBehavior<'a> {
     kill_count: u32,
     obstacle_avoidance_count: u32,
     missclick_count: u32,
+    missclick_reset_count: u32,
     last_summon_pet_time: Option<Instant>,
     last_killed_type: MobType,
     start_time: Instant,
     already_attack_count: u32,
     last_buff_usage: Instant,
-
+    leash_anchored: bool,
+    is_returning_to_anchor: bool,
+    restorations: RestorationTracker,
+    exhausted_slot_type: Option<SlotType>,
+    tagged_count: u32,
+    /// Mobs gathered around the player when the burst started
+    burst_mob_count: Option<u32>,
+    last_kite_time: Instant,
+    walking_since: Option<Instant>,
+    target_scorer: TargetScorer,
+    spot_index: usize,
+    spot_arrival_time: Instant,
+    flight_remaining: u64,
+    /// Time spent climbing after taking off and the board slot, `None` when on the ground
+    flight: Option<(u64, (usize, usize))>,
+    /// Search movement still being played in the background
+    pending_movement: Option<MovementHandle>,
+    last_hp: u32,
+    search_script: Option<Script>,
 }
 
-impl<'a> Behavior<'a> for FarmingBehavior<'a> {
+impl<'a, I: InputSink> Behavior<'a, I> for FarmingBehavior<'a, I> {
     fn new(
         logger: &'a Logger,
         movement: &'a MovementAccessor,
-        window: &'a Window,
+        input: &'a I,
     ) -> Self {
         Self {
             logger,
             movement,
-            window,
+            input,
             rng: rand::thread_rng(),
-            state: State::SearchingForEnemy,
+            machine: StateMachine::new(State::SearchingForEnemy),
             slots_usage_last_time: [[None; 10]; 9],
             last_initial_attack_time: Instant::now(),
             last_kill_time: Instant::now(),
@@ -70,19 +116,55 @@ impl<'a> Behavior<'a> for FarmingBehavior<'a> {
             kill_count: 0,
             obstacle_avoidance_count: 0,
             missclick_count: 0,
+            missclick_reset_count: 0,
             last_summon_pet_time: None,
             last_killed_type: MobType::Passive,
             start_time: Instant::now(),
             already_attack_count: 0,
             last_buff_usage: Instant::now(),
-
+            leash_anchored: false,
+            is_returning_to_anchor: false,
+            restorations: RestorationTracker::default(),
+            exhausted_slot_type: None,
+            tagged_count: 0,
+            burst_mob_count: None,
+            last_kite_time: Instant::now(),
+            walking_since: None,
+            target_scorer: TargetScorer::default(),
+            spot_index: 0,
+            spot_arrival_time: Instant::now(),
+            flight_remaining: 0,
+            flight: None,
+            pending_movement: None,
+            last_hp: 0,
+            search_script: None,
         }
     }
 
-    fn start(&mut self, _config: &BotConfig) {}
-    fn update(&mut self, _config: &BotConfig) {}
+    fn start(&mut self, config: &BotConfig) {
+        self.spot_arrival_time = Instant::now();
+        self.load_search_script(config.farming_config());
+    }
+    fn update(&mut self, config: &BotConfig) {
+        self.load_search_script(config.farming_config());
+    }
     fn stop(&mut self, _config: &BotConfig) {
         self.slots_usage_last_time = [[None; 10]; 9];
+        self.leash_anchored = false;
+        self.is_returning_to_anchor = false;
+        self.restorations.reset();
+        self.exhausted_slot_type = None;
+        self.tagged_count = 0;
+        self.burst_mob_count = None;
+        self.walking_since = None;
+        self.target_scorer.reset();
+        self.cancel_movement();
+        // Land before forgetting about the flight
+        if let State::Travelling = self.machine.state() {
+            self.transition(State::SearchingForEnemy, "stopped");
+        }
+        self.flight_remaining = 0;
+        self.flight = None;
     }
 
     fn run_iteration(
@@ -99,23 +181,163 @@ impl<'a> Behavior<'a> for FarmingBehavior<'a> {
         // Check whether something should be restored
         self.check_restorations(config, image);
 
+        // Forget search movements the client refused
+        if let Some(err) = self.pending_movement.as_ref().and_then(MovementHandle::error) {
+            slog::warn!(self.logger, "Movement aborted"; "error" => err.to_string());
+            self.pending_movement = None;
+        }
+
+        // Stop searching around as soon as we get hit
+        let hp = image.client_stats.hp.value;
+        if hp < self.last_hp {
+            self.cancel_movement();
+        }
+        self.last_hp = hp;
+
         // Use buffs Yiha
-        self.check_buffs(config);
+        if self.flight.is_none() {
+            self.check_buffs(config);
+        }
+
+        // Remember where we started farming
+        if !self.leash_anchored {
+            self.movement.reset_anchor();
+            self.leash_anchored = true;
+        }
+
+        // Leave states that lasted too long
+        if let Some(fallback) = self
+            .machine
+            .timed_out(|state| Self::state_timeout(config, state))
+        {
+            if let State::Attacking(_) = self.machine.state() {
+                self.abort_attack(config, image);
+            }
+            self.transition(fallback, "timeout");
+        }
+
+        // Move on to the next hunting spot
+        if self.should_travel(config) {
+            self.transition(State::Travelling, "dwell time elapsed");
+        }
 
         // Check state machine
-        self.state = match self.state {
+        let (next, reason) = match self.machine.state() {
             State::NoEnemyFound => self.on_no_enemy_found(config),
             State::SearchingForEnemy => self.on_searching_for_enemy(config, image),
             State::EnemyFound(mob) => self.on_enemy_found(config, mob, image),
             State::Attacking(mob) => self.on_attacking(config, mob, image),
             State::AfterEnemyKill(_) => self.after_enemy_kill(frontend_info, config),
+            State::Tagging(mob) => self.on_tagging(config, mob, image),
+            State::Gathering => self.on_gathering(config, image),
+            State::Bursting => self.on_bursting(frontend_info, config, image),
+            State::Travelling => self.on_travelling(config),
         };
+        self.transition(next, reason);
 
         frontend_info.set_is_attacking(self.is_attacking);
+        frontend_info.set_missclick_reset_count(self.missclick_reset_count);
+        frontend_info.set_exhausted_slot_type(self.exhausted_slot_type);
+    }
+
+    fn target_offset(&self, image: &ImageAnalyzer) -> Option<(i32, i32)> {
+        let mob = match self.machine.state() {
+            State::Attacking(mob) | State::Tagging(mob) => mob,
+            _ => return None,
+        };
+        let (width, height) = image.image()?.dimensions();
+        let coords = mob.get_attack_coords();
+        Some((
+            coords.x as i32 - (width / 2) as i32,
+            coords.y as i32 - (height / 2) as i32,
+        ))
+    }
+
+    fn is_mounted(&self) -> bool {
+        matches!(self.machine.state(), State::Travelling)
+    }
+
+    fn take_transitions(&mut self) -> Vec<StateTransition> {
+        self.machine.take_transitions()
+    }
+}
+
+impl<'a, I: InputSink> StatefulBehavior<State> for FarmingBehavior<'a, I> {
+    fn machine(&mut self) -> &mut StateMachine<State> {
+        &mut self.machine
+    }
+
+    fn on_enter(&mut self, state: State) {
+        match state {
+            // Tagged mobs are fought as a group
+            State::Tagging(_) => self.is_attacking = true,
+            // No need to keep looking around
+            State::EnemyFound(_) => self.cancel_movement(),
+            _ => {}
+        }
+    }
+
+    fn on_exit(&mut self, state: State) {
+        match state {
+            State::Attacking(_) => {
+                self.is_attacking = false;
+                self.cancel_movement();
+            }
+            State::Bursting => {
+                self.is_attacking = false;
+                self.burst_mob_count = None;
+            }
+            // Never engage anything from the board
+            State::Travelling => self.land(),
+            _ => {}
+        }
     }
 }
 
-impl<'a> FarmingBehavior<'_> {
+impl<'a, I: InputSink> FarmingBehavior<'a, I> {
+    /// Maximum time to spend in a state in milliseconds, and the state to fall back to
+    fn state_timeout(config: &FarmingConfig, state: &State) -> Option<(u128, State)> {
+        match state {
+            // Fights are left to the player when fighting is stopped, however long they take
+            State::Attacking(_) if config.attack_timeout() > 0 && !config.is_stop_fighting() => {
+                Some((config.attack_timeout(), State::SearchingForEnemy))
+            }
+            State::Gathering => Some((config.aoe_gather_timeout(), State::Bursting)),
+            _ => None,
+        }
+    }
+
+    /// Whether a movement started in the background is still being played
+    fn is_moving(&self) -> bool {
+        self.pending_movement
+            .as_ref()
+            .map(|handle| !handle.is_done())
+            .unwrap_or(false)
+    }
+
+    fn cancel_movement(&mut self) {
+        if let Some(handle) = self.pending_movement.take() {
+            handle.cancel();
+        }
+    }
+
+    fn load_search_script(&mut self, config: &FarmingConfig) {
+        self.search_script = config.search_script().and_then(|name| {
+            Script::load_named(name)
+                .map_err(|err| {
+                    slog::warn!(self.logger, "Failed to load search script"; "script" => name, "error" => err.to_string());
+                })
+                .ok()
+        });
+    }
+
+    /// Log inputs the client didn't accept
+    fn check_input(&self, result: Result<(), InputError>) {
+        if let Err(err) = result {
+            slog::warn!(self.logger, "Failed to send input"; "error" => err.to_string());
+        }
+    }
+
     fn update_timestamps(&mut self, config: &FarmingConfig) {
         self.update_pickup_pet(config);
 
@@ -145,7 +367,7 @@ impl<'a> FarmingBehavior<'_> {
                         .get_slot_cooldown(pickup_pet_slot_index.0, pickup_pet_slot_index.1)
                         .unwrap_or(3000) as u128
                 {
-                    send_slot_eval(self.window, pickup_pet_slot_index.0, pickup_pet_slot_index.1);
+                    self.check_input(self.input.send_slot(pickup_pet_slot_index.0, pickup_pet_slot_index.1));
                     self.last_summon_pet_time = None;
                 }
             }
@@ -200,7 +422,7 @@ impl<'a> FarmingBehavior<'_> {
 
     fn send_slot(&mut self, slot_index: (usize, usize)) {
         // Send keystroke for first slot mapped to pill
-        send_slot_eval(self.window, slot_index.0 , slot_index.1);
+        self.check_input(self.input.send_slot(slot_index.0 , slot_index.1));
         // Update usage last time
         self.slots_usage_last_time[slot_index.0][slot_index.1] = Some(Instant::now());
     }
@@ -211,7 +433,7 @@ impl<'a> FarmingBehavior<'_> {
         if slot.is_some() {
             let index = slot.unwrap();
             if self.last_summon_pet_time.is_none() {
-                send_slot_eval(self.window, index.0, index.1);
+                self.check_input(self.input.send_slot(index.0, index.1));
                 self.last_summon_pet_time = Some(Instant::now());
             } else {
                 // if pet is already out, just reset it's timer
@@ -222,34 +444,57 @@ impl<'a> FarmingBehavior<'_> {
             if slot.is_some() {
                 let index = slot.unwrap();
                 for _i in 1..7 {
-                    send_slot_eval(self.window, index.0, index.1);
+                    self.check_input(self.input.send_slot(index.0, index.1));
                 }
             }
         }
     }
 
     fn check_restorations(&mut self, config: &FarmingConfig, image: &mut ImageAnalyzer) {
+        self.restorations.observe(&image.client_stats);
+
         // Check HP
         let stat = Some(image.client_stats.hp.value);
         if image.client_stats.hp.value > 0 {
             if self
                 .get_slot_for(config, stat, SlotType::Pill, true)
-                .is_none()
+                .is_some()
+            {
+                self.track_restoration(SlotType::Pill, image);
+            } else if self
+                .get_slot_for(config, stat, SlotType::Food, true)
+                .is_some()
             {
-                self.get_slot_for(config, stat, SlotType::Food, true);
+                self.track_restoration(SlotType::Food, image);
             }
         }
 
         // Check MP
         let stat = Some(image.client_stats.mp.value);
-        if image.client_stats.mp.value > 0 {
-            self.get_slot_for(config, stat, SlotType::MpRestorer, true);
+        if image.client_stats.mp.value > 0
+            && self
+                .get_slot_for(config, stat, SlotType::MpRestorer, true)
+                .is_some()
+        {
+            self.track_restoration(SlotType::MpRestorer, image);
         }
 
         // Check FP
         let stat = Some(image.client_stats.fp.value);
-        if image.client_stats.fp.value > 0 {
-            self.get_slot_for(config, stat, SlotType::FpRestorer, true);
+        if image.client_stats.fp.value > 0
+            && self
+                .get_slot_for(config, stat, SlotType::FpRestorer, true)
+                .is_some()
+        {
+            self.track_restoration(SlotType::FpRestorer, image);
+        }
+    }
+
+    /// Remember restorations that stopped having any effect
+    fn track_restoration(&mut self, slot_type: SlotType, image: &ImageAnalyzer) {
+        if self.restorations.on_use(slot_type, &image.client_stats) {
+            slog::warn!(self.logger, "Restoration seems exhausted"; "slot_type" => slot_type.to_string());
+            self.exhausted_slot_type = Some(slot_type);
         }
     }
 
@@ -260,131 +505,193 @@ impl<'a> FarmingBehavior<'_> {
         }
     }
 
-    fn on_no_enemy_found(&mut self, config: &FarmingConfig) -> State {
+    fn on_no_enemy_found(&mut self, config: &FarmingConfig) -> (State, &'static str) {
         use crate::movement::prelude::*;
 
+        // Don't wander off while tagged mobs are on their way
+        if self.tagged_count > 0 {
+            return (State::Gathering, "waiting for tagged mobs");
+        }
+
         // Try rotating first in order to locate nearby enemies
         if self.rotation_movement_tries < 20 {
-            play!(self.movement => [
+            self.pending_movement = Some(play_async!(self.movement => [
                 // Rotate in random direction for a random duration
                 Rotate(rot::Right, dur::Fixed(100)),
                 // Wait a bit to wait for monsters to enter view
                 Wait(dur::Fixed(200)),
-            ]);
+            ]));
             self.rotation_movement_tries += 1;
 
             // Transition to next state
-            return State::SearchingForEnemy;
+            return (State::SearchingForEnemy, "rotated");
         }
 
         // Check whether bot should stay in area
         let circle_pattern_rotation_duration = config.circle_pattern_rotation_duration();
-        if circle_pattern_rotation_duration > 0 {
+        if config.leash_enabled()
+            && self.movement.anchor_offset().0 > config.leash_radius() as f32 / 2.0
+        {
+            // Don't let the circle pattern carry us out of the leash
+            self.return_to_anchor();
+        } else if let Some(script) = self.search_script.as_ref() {
+            match self.movement.start_script(script, None) {
+                Ok(handle) => {
+                    self.walking_since.get_or_insert_with(Instant::now);
+                    self.pending_movement = Some(handle);
+                }
+                Err(err) => {
+                    slog::warn!(self.logger, "Failed to play search script"; "error" => err.to_string())
+                }
+            }
+        } else if circle_pattern_rotation_duration > 0 {
+            self.walking_since.get_or_insert_with(Instant::now);
             self.move_circle_pattern(circle_pattern_rotation_duration);
         } else {
             self.rotation_movement_tries = 0;
-            return self.state;
+            return (self.machine.state(), "");
         }
         // Transition to next state
-        State::SearchingForEnemy
+        (State::SearchingForEnemy, "moved")
     }
 
-    fn move_circle_pattern(&self, rotation_duration: u64) {
+    fn move_circle_pattern(&mut self, rotation_duration: u64) {
         // low rotation duration means big circle, high means little circle
         use crate::movement::prelude::*;
-        play!(self.movement => [
+        self.pending_movement = Some(play_async!(self.movement => [
             HoldKeys(vec!["W", "Space", "D"]),
             Wait(dur::Fixed(rotation_duration)),
             ReleaseKey("D"),
             Wait(dur::Fixed(20)),
             ReleaseKeys(vec!["Space", "W"]),
             HoldKeyFor("S", dur::Fixed(50)),
+        ]));
+    }
+
+    /// Check whether the character drifted too far from the home anchor
+    fn check_leash(&mut self, config: &FarmingConfig) -> bool {
+        if !config.leash_enabled() {
+            self.is_returning_to_anchor = false;
+            return false;
+        }
+
+        let distance = self.movement.anchor_offset().0;
+        let radius = config.leash_radius() as f32;
+        if self.is_returning_to_anchor {
+            // Keep walking until we're well inside the leash again
+            self.is_returning_to_anchor = distance > radius / 4.0;
+        } else if distance > radius {
+            slog::debug!(self.logger, "Leash radius exceeded"; "distance" => distance, "radius" => radius);
+            self.is_returning_to_anchor = true;
+        }
+        self.is_returning_to_anchor
+    }
+
+    /// Walk back towards the home anchor
+    fn return_to_anchor(&mut self) {
+        use crate::movement::prelude::*;
+
+        let (distance, turn) = self.movement.anchor_offset();
+        let rotation_key = if turn < 0.0 { "A" } else { "D" };
+        let rotation_duration = (turn.abs() / TURN_RATE) as u64;
+
+        // Walk in chunks so restorations are still checked on the way back
+        let walk_duration = (distance as u64).min(2000);
+        self.walking_since.get_or_insert_with(Instant::now);
+
+        play!(self.movement => [
+            HoldKeyFor(rotation_key, dur::Fixed(rotation_duration)),
+            HoldKeyFor("W", dur::Fixed(walk_duration)),
         ]);
     }
 
+    /// Check whether the scene stayed still although we've been walking for a while
+    fn is_stuck(&self, config: &FarmingConfig, image: &ImageAnalyzer) -> bool {
+        let stuck_duration = config.stuck_duration();
+        config.stuck_detection_enabled()
+            && self
+                .walking_since
+                .map(|time| time.elapsed().as_millis() > stuck_duration)
+                .unwrap_or(false)
+            && image.motion.still_duration().as_millis() > stuck_duration
+    }
+
+    /// Get free, then time the walk again from there
+    fn unstick(&mut self, image: &ImageAnalyzer) {
+        slog::debug!(self.logger, "Stuck, trying to get free"; "last_motion" => image.motion.last_motion());
+        self.movement.unstick();
+        self.walking_since = Some(Instant::now());
+    }
+
     fn on_searching_for_enemy(
         &mut self,
         config: &FarmingConfig,
         image: &mut ImageAnalyzer,
-    ) -> State {
+    ) -> (State, &'static str) {
         if config.is_stop_fighting() {
-            return State::Attacking(Target::default());
+            return (State::Attacking(Target::default()), "fighting stopped");
+        }
+        if self.check_leash(config) {
+            if self.is_stuck(config, image) {
+                self.unstick(image);
+            } else {
+                self.return_to_anchor();
+            }
+            return (State::SearchingForEnemy, "returning to anchor");
         }
         let mobs = image.identify_mobs(config);
+        self.target_scorer.observe(&mobs);
         if mobs.is_empty() {
+            // The search pattern walked into a wall
+            if self.is_stuck(config, image) {
+                self.cancel_movement();
+                self.unstick(image);
+                return (State::SearchingForEnemy, "stuck while searching");
+            }
+            // Keep looking while the search movement goes on
+            if self.is_moving() {
+                return (State::SearchingForEnemy, "");
+            }
             // Transition to next state
-            State::NoEnemyFound
+            return (State::NoEnemyFound, "no mobs");
+        }
+
+        // Calculate max distance of mobs
+        let max_distance = match config.circle_pattern_rotation_duration() == 0 {
+            true => 325,
+            false => 1000,
+        };
+
+        // A lone name of the type we just killed is most likely the dying mob
+        let killed_type = TargetType::Mob(self.last_killed_type);
+        let candidates = if self.last_kill_time.elapsed().as_millis() < 5500
+            && mobs.iter().filter(|m| m.target_type == killed_type).count() == 1
+        {
+            mobs.into_iter()
+                .filter(|m| m.target_type != killed_type)
+                .collect::<Vec<_>>()
         } else {
-            // Calculate max distance of mobs
-            let max_distance = match config.circle_pattern_rotation_duration() == 0 {
-                true => 325,
-                false => 1000,
-            };
-
-            // Get aggressive mobs to prioritize them
-            let mut mob_list = mobs
-                .iter()
-                .filter(|m| m.target_type == TargetType::Mob(MobType::Aggressive))
-                .cloned()
-                .collect::<Vec<_>>();
-            let mut mob_type = "aggressive";
-
-            // Check if there's aggressive mobs otherwise collect passive mobs
-            if mob_list.is_empty()
-                || self.last_killed_type == MobType::Aggressive
-                    && mob_list.len() == 1
-                    && self.last_kill_time.elapsed().as_millis() < 5500
-            {
-                if image.client_stats.hp.value >= config.min_hp_attack() {
-                    mob_list = mobs
-                        .iter()
-                        .filter(|m| m.target_type == TargetType::Mob(MobType::Passive))
-                        .cloned()
-                        .collect::<Vec<_>>();
-                    mob_type = "passive";
-                }
-            }
+            mobs
+        };
 
-            // Check again
-            if !mob_list.is_empty() {
-                let killed_type = {
-                    if mob_type == "aggressive" {
-                        MobType::Aggressive
-                    } else {
-                        MobType::Passive
-                    }
-                };
-                //slog::debug!(self.logger, "Found mobs"; "mob_type" => mob_type, "mob_count" => mob_list.len());
-                if let Some(mob) = {
-                    if killed_type == self.last_killed_type
-                        && mob_list.len() == 1
-                        && self.last_kill_time.elapsed().as_millis() < 5500
-                    {
-                        // Transition to next state
-                        return State::NoEnemyFound;
-                    }
-                    // Try avoiding detection of last killed mob
-                    if self.avoided_bounds.len() > 0 {
-                        image.find_closest_mob(
-                            mob_list.as_slice(),
-                            Some(&self.avoided_bounds),
-                            max_distance,
-                            self.logger,
-                        )
-                    } else {
-                        image.find_closest_mob(mob_list.as_slice(), None, max_distance, self.logger)
-                    }
-                } {
-                    // Transition to next state
-                    State::EnemyFound(*mob)
-                } else {
-                    // Transition to next state
-                    State::NoEnemyFound
-                }
-            } else {
-                // Transition to next state
-                State::NoEnemyFound
-            }
+        let context = ScoringContext {
+            max_distance,
+            max_name_width: config.max_mobs_name_width(),
+            hp: image.client_stats.hp.value,
+            min_hp_attack: config.min_hp_attack(),
+            avoided_bounds: &self.avoided_bounds,
+        };
+        let mob = self.target_scorer.select(
+            &candidates,
+            &config.scoring_weights(),
+            &config.target_filters(),
+            &context,
+            |mob| image.distance_to_player(mob.get_attack_coords()),
+        );
+        match mob {
+            // Transition to next state
+            Some(mob) => (State::EnemyFound(*mob), "best scored mob"),
+            None => (State::NoEnemyFound, "no suitable mob"),
         }
     }
 
@@ -393,37 +700,65 @@ impl<'a> FarmingBehavior<'_> {
         config: &FarmingConfig,
         mob: Target,
         image: &mut ImageAnalyzer,
-    ) -> State {
+    ) -> (State, &'static str) {
         self.rotation_movement_tries = 0;
+        self.walking_since = None;
 
-        // Transform attack coords into local window coords
-        let point = mob.get_attack_coords();
+        // Transform attack coords into page coords
+        let point = match image
+            .coordinates()
+            .to_input(CapturePoint(mob.get_attack_coords()))
+        {
+            Ok(point) => point,
+            Err(err) => {
+                self.check_input(Err(err));
+                self.avoided_bounds.push((mob.bounds, Instant::now(), 3000));
+                return (State::SearchingForEnemy, "mob out of reach");
+            }
+        };
 
         // Set cursor position and simulate a click
-        eval_mouse_move(self.window, point);
+        self.check_input(self.input.mouse_move(point));
         std::thread::sleep(Duration::from_millis(100));
         image.capture_window_area(self.logger, config, Area::new(0, 0, 2, 2));
         let cursor_style = PixelDetection::new(PixelDetectionKind::CursorType, Some(image));
         if cursor_style.value {
-            eval_mouse_click_at_point(self.window, point);
+            self.check_input(self.input.click_at(point));
             self.missclick_count = 0;
 
+            // The character walks to the mob, keep the distance to the leash anchor up to date
+            if let Some((width, height)) = image.image().map(|image| image.dimensions()) {
+                let coords = mob.get_attack_coords();
+                self.movement.click_move(
+                    coords.x as f32 - (width / 2) as f32,
+                    coords.y as f32 - (height / 2) as f32,
+                );
+            }
+
             // Wait a few ms before transitioning state
             std::thread::sleep(Duration::from_millis(100));
-            State::Attacking(mob)
+            match config.farming_strategy() {
+                FarmingStrategy::SingleTarget => (State::Attacking(mob), "mob selected"),
+                FarmingStrategy::AoePull => (State::Tagging(mob), "mob selected"),
+            }
         } else {
             self.missclick_count += 1;
             self.avoided_bounds.push((mob.bounds, Instant::now(), 3000));
             if self.missclick_count == 30 {
                 self.missclick_count = 0;
-                State::NoEnemyFound
+                self.missclick_reset_count += 1;
+                (State::NoEnemyFound, "too many missclicks")
             } else {
-                State::SearchingForEnemy
+                (State::SearchingForEnemy, "missclick")
             }
         }
     }
 
-    fn abort_attack(&mut self, config: &FarmingConfig, image: &mut ImageAnalyzer) -> State {
+    fn abort_attack(
+        &mut self,
+        config: &FarmingConfig,
+        image: &mut ImageAnalyzer,
+    ) -> (State, &'static str) {
         use crate::movement::prelude::*;
         self.is_attacking = false;
 
@@ -439,7 +774,7 @@ impl<'a> FarmingBehavior<'_> {
         play!(self.movement => [
             PressKey("Escape"),
         ]);
-        return State::SearchingForEnemy;
+        (State::SearchingForEnemy, "attack aborted")
     }
 
     fn on_attacking(
@@ -447,7 +782,7 @@ impl<'a> FarmingBehavior<'_> {
         config: &FarmingConfig,
         mob: Target,
         image: &mut ImageAnalyzer,
-    ) -> State {
+    ) -> (State, &'static str) {
         // Engagin combat
         let is_npc = PixelDetection::new(PixelDetectionKind::IsNpc, Some(image)).value;
         if !self.is_attacking && !config.is_stop_fighting() {
@@ -456,7 +791,7 @@ impl<'a> FarmingBehavior<'_> {
                 play!(self.movement => [
                     HoldKeyFor("S", dur::Fixed(50)),
                 ]);
-                return State::SearchingForEnemy;
+                return (State::SearchingForEnemy, "target lost");
             }
             if image.client_stats.target_hp.value > 0 {
                 // try to implement something related to party, if mob is less than 100% he was probably attacked by someone else so we can avoid it
@@ -478,17 +813,27 @@ impl<'a> FarmingBehavior<'_> {
                 self.last_initial_attack_time = Instant::now();
                 self.is_attacking = true;
             }
+            let target_hp_stale_for = image
+                .client_stats
+                .target_hp
+                .last_update_time
+                .map(|time| time.elapsed().as_millis());
+
+            // The scene stopped moving and the target takes no damage: we ran into an obstacle
+            let is_wedged = config.stuck_detection_enabled()
+                && self.last_initial_attack_time.elapsed().as_millis() > config.stuck_duration()
+                && target_hp_stale_for
+                    .map(|stale_for| stale_for > config.stuck_duration())
+                    .unwrap_or(false)
+                && image.motion.still_duration().as_millis() > config.stuck_duration();
+
             if !config.is_stop_fighting()
                 && config.obstacle_avoidance_enabled()
-                && image.client_stats.target_hp.last_update_time.is_some()
-                && image
-                    .client_stats
-                    .target_hp
-                    .last_update_time
-                    .unwrap()
-                    .elapsed()
-                    .as_millis()
-                    > config.get_obstacle_avoidance_cooldown()
+                && !self.is_moving()
+                && (target_hp_stale_for
+                    .map(|stale_for| stale_for > config.get_obstacle_avoidance_cooldown())
+                    .unwrap_or(false)
+                    || is_wedged)
             {
                 // Reset timer otherwise it'll trigger every tick
                 image.client_stats.target_hp.reset_last_update_time();
@@ -514,15 +859,23 @@ impl<'a> FarmingBehavior<'_> {
                 use crate::movement::prelude::*;
                 let rotation_key = ["A", "D"].choose(&mut self.rng).unwrap_or(&"A");
 
-                // Move into a random direction while jumping
-                play!(self.movement => [
+                // Move into a random direction while jumping, attacking goes on meanwhile
+                self.pending_movement = Some(play_async!(self.movement => [
                     HoldKeys(vec!["W", "Space"]),
                     HoldKeyFor(*rotation_key, dur::Fixed(200)),
                     Wait(dur::Fixed(800)),
                     ReleaseKeys(vec!["Space", "W"]),
-                ]);
+                ]));
                 self.obstacle_avoidance_count += 1;
             }
+
+            // Keep aggressive mobs at distance
+            if config.kiting_enabled()
+                && self.last_kite_time.elapsed().as_millis() > config.kiting_cooldown()
+            {
+                self.kite(config, image);
+            }
+
             // Try to use attack skill if at least one is selected in slot bar
             self.get_slot_for(config, None, SlotType::AttackSkill, true);
         } else if image.client_stats.target_hp.value == 0
@@ -536,12 +889,229 @@ impl<'a> FarmingBehavior<'_> {
                 TargetType::Mob(MobType::Passive) => self.last_killed_type = MobType::Passive,
                 TargetType::TargetMarker => {}
             }
-            return State::AfterEnemyKill(mob);
+            return (State::AfterEnemyKill(mob), "target killed");
         } else {
             self.is_attacking = false;
-            return State::SearchingForEnemy;
+            return (State::SearchingForEnemy, "target lost");
         }
-        self.state
+        (self.machine.state(), "")
+    }
+
+    fn on_tagging(
+        &mut self,
+        config: &FarmingConfig,
+        mob: Target,
+        image: &mut ImageAnalyzer,
+    ) -> (State, &'static str) {
+        use crate::movement::prelude::*;
+
+        let target_hp = image.client_stats.target_hp.value;
+        let is_tagged = target_hp > 0 && target_hp < 100;
+        if is_tagged || self.machine.time_in_state() > 3000 {
+            if is_tagged {
+                self.tagged_count += 1;
+            }
+
+            // Don't pick the same mob again and let it come to us
+            self.avoided_bounds
+                .push((mob.bounds.grow_by(50), Instant::now(), 5000));
+            play!(self.movement => [
+                PressKey("Escape"),
+            ]);
+
+            if self.tagged_count >= config.aoe_pull_count() {
+                return (State::Gathering, "enough mobs tagged");
+            }
+            return (State::SearchingForEnemy, "mob tagged");
+        }
+
+        // Hit the mob once so it starts chasing us
+        self.get_slot_for(config, None, SlotType::AttackSkill, true);
+        (self.machine.state(), "")
+    }
+
+    fn on_gathering(
+        &mut self,
+        config: &FarmingConfig,
+        image: &mut ImageAnalyzer,
+    ) -> (State, &'static str) {
+        let mobs = image.identify_mobs(config);
+        let gathered = image
+            .mobs_around_player(&mobs, config.aoe_cluster_radius())
+            .len() as u32;
+
+        if gathered >= self.tagged_count {
+            slog::debug!(self.logger, "Bursting gathered mobs"; "gathered" => gathered, "tagged" => self.tagged_count);
+            return (State::Bursting, "mobs gathered");
+        }
+        (self.machine.state(), "")
+    }
+
+    fn on_bursting(
+        &mut self,
+        frontend_info: &mut FrontendInfo,
+        config: &FarmingConfig,
+        image: &mut ImageAnalyzer,
+    ) -> (State, &'static str) {
+        let mobs = image.identify_mobs(config);
+        let remaining = image
+            .mobs_around_player(&mobs, config.aoe_cluster_radius())
+            .len() as u32;
+        let gathered = *self.burst_mob_count.get_or_insert(remaining);
+
+        // Give area skills some time to be cast before checking for survivors
+        let elapsed = self.machine.time_in_state();
+        if (remaining == 0 && elapsed > 1500) || elapsed > 30000 {
+            // Only the mobs gone from around the player were killed, survivors of a timeout
+            // and mobs tagged twice don't count
+            let killed = gathered.saturating_sub(remaining);
+            return self.after_aoe_burst(frontend_info, config, killed);
+        }
+
+        self.get_slot_for(config, None, SlotType::AoeSkill, true);
+        (self.machine.state(), "")
+    }
+
+    fn after_aoe_burst(
+        &mut self,
+        frontend_info: &mut FrontendInfo,
+        config: &FarmingConfig,
+        killed: u32,
+    ) -> (State, &'static str) {
+        self.kill_count += killed;
+        self.tagged_count = 0;
+        frontend_info.set_kill_count(self.kill_count);
+        if killed > 0 {
+            self.last_kill_time = Instant::now();
+        }
+
+        // Pickup items
+        self.pickup_items(config);
+
+        // Transition state
+        (State::SearchingForEnemy, "mobs killed")
+    }
+
+    /// Back off or sidestep when an aggressive mob gets too close
+    fn kite(&mut self, config: &FarmingConfig, image: &mut ImageAnalyzer) {
+        use crate::movement::prelude::*;
+
+        // Not worth moving away from a mob that's about to die
+        if image.client_stats.target_hp.value < 20 {
+            return;
+        }
+
+        let aggressive_mobs = image
+            .identify_mobs(config)
+            .into_iter()
+            .filter(|m| m.target_type == TargetType::Mob(MobType::Aggressive))
+            .collect::<Vec<_>>();
+        if image
+            .mobs_around_player(&aggressive_mobs, config.kiting_min_distance())
+            .is_empty()
+        {
+            return;
+        }
+
+        self.last_kite_time = Instant::now();
+        let retreat_duration = config.kiting_retreat_duration();
+        if self.rng.gen_bool(0.5) {
+            play!(self.movement => [
+                Move(dir::Backward, dur::Fixed(retreat_duration)),
+            ]);
+        } else {
+            // Turn sideways, run and turn back to face the mob
+            let (side_key, back_key) = *[("A", "D"), ("D", "A")]
+                .choose(&mut self.rng)
+                .unwrap_or(&("A", "D"));
+            let quarter_turn = (std::f32::consts::FRAC_PI_2 / TURN_RATE) as u64;
+            play!(self.movement => [
+                HoldKeyFor(side_key, dur::Fixed(quarter_turn)),
+                Move(dir::Forward, dur::Fixed(retreat_duration)),
+                HoldKeyFor(back_key, dur::Fixed(quarter_turn)),
+            ]);
+        }
+
+        // Moving around doesn't mean we're stuck
+        image.client_stats.target_hp.reset_last_update_time();
+    }
+
+    /// Check whether the current hunting spot was farmed long enough
+    fn should_travel(&self, config: &FarmingConfig) -> bool {
+        config.flying_enabled()
+            && !config.hunting_spots().is_empty()
+            && config.get_slot_index(SlotType::Flying).is_some()
+            && matches!(
+                self.machine.state(),
+                State::SearchingForEnemy | State::NoEnemyFound
+            )
+            && self.tagged_count == 0
+            && self.spot_arrival_time.elapsed().as_millis() > config.spot_dwell_time()
+    }
+
+    /// Descend and get off the board
+    fn land(&mut self) {
+        use crate::movement::prelude::*;
+
+        guard!(let Some((altitude, board_slot)) = self.flight.take() else {
+            return;
+        });
+        play!(self.movement => [
+            HoldKeyFor("C", dur::Fixed(altitude + 500)),
+            Wait(dur::Fixed(300)),
+        ]);
+        self.check_input(self.input.send_slot(board_slot.0, board_slot.1));
+        std::thread::sleep(Duration::from_millis(1000));
+        self.flight_remaining = 0;
+    }
+
+    fn on_travelling(&mut self, config: &FarmingConfig) -> (State, &'static str) {
+        use crate::movement::prelude::*;
+
+        let spots = config.hunting_spots();
+        self.spot_index %= spots.len().max(1);
+        guard!(let Some(spot) = spots.get(self.spot_index) else {
+            return (State::SearchingForEnemy, "no hunting spot");
+        });
+        guard!(let Some(board_slot) = config.get_slot_index(SlotType::Flying) else {
+            return (State::SearchingForEnemy, "no board");
+        });
+
+        // Take off, climb and face the next hunting spot
+        if self.flight.is_none() {
+            slog::info!(self.logger, "Flying to hunting spot"; "spot" => spot.name(), "index" => self.spot_index);
+            self.check_input(self.input.send_slot(board_slot.0, board_slot.1));
+            let altitude = config.flying_altitude();
+            // Spot headings are relative to the direction faced on arrival at the previous spot,
+            // whatever way we turned while farming there
+            let turn = self.movement.turn_to((spot.turn() as f32).to_radians());
+            let rotation_key = if turn < 0.0 { "A" } else { "D" };
+            play!(self.movement => [
+                Wait(dur::Fixed(1500)),
+                HoldKeyFor("Space", dur::Fixed(altitude)),
+                HoldKeyFor(rotation_key, dur::Fixed((turn.abs() / TURN_RATE) as u64)),
+            ]);
+            self.flight = Some((altitude, board_slot));
+            self.flight_remaining = spot.flight_duration();
+            return (State::Travelling, "");
+        }
+
+        // Fly in chunks so restorations are still checked on the way
+        if self.flight_remaining > 0 {
+            let chunk = self.flight_remaining.min(2000);
+            play!(self.movement => [
+                HoldKeyFor("W", dur::Fixed(chunk)),
+            ]);
+            self.flight_remaining -= chunk;
+            return (State::Travelling, "");
+        }
+
+        // Arrived, land and farm around here from now on
+        self.land();
+        self.spot_index = (self.spot_index + 1) % spots.len();
+        self.spot_arrival_time = Instant::now();
+        self.movement.reset_anchor();
+        (State::SearchingForEnemy, "arrived at hunting spot")
     }
 
     fn after_enemy_kill_debug(&mut self, frontend_info: &mut FrontendInfo) {
@@ -582,7 +1152,7 @@ impl<'a> FarmingBehavior<'_> {
         &mut self,
         frontend_info: &mut FrontendInfo,
         config: &FarmingConfig,
-    ) -> State {
+    ) -> (State, &'static str) {
         self.kill_count += 1;
         frontend_info.set_kill_count(self.kill_count);
         self.after_enemy_kill_debug(frontend_info);
@@ -593,6 +1163,6 @@ impl<'a> FarmingBehavior<'_> {
         self.pickup_items(config);
 
         // Transition state
-        State::SearchingForEnemy
+        (State::SearchingForEnemy, "items picked up")
     }
 }
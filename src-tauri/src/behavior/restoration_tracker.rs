@@ -0,0 +1,124 @@
+use crate::{data::ClientStats, ipc::SlotType};
+
+/// Amount of uses without any gain after which a restoration is considered exhausted
+const MAX_USES_WITHOUT_GAIN: u32 = 5;
+
+#[derive(Debug, Clone, Copy)]
+struct RestorationUsage {
+    slot_type: SlotType,
+    value_at_use: u32,
+    peak_value: u32,
+    uses_without_gain: u32,
+}
+
+/// Detects restoration slots that don't restore anything anymore, most likely because they ran out.
+#[derive(Debug, Default)]
+pub struct RestorationTracker {
+    usages: Vec<RestorationUsage>,
+}
+
+impl RestorationTracker {
+    fn stat_value(slot_type: SlotType, stats: &ClientStats) -> Option<u32> {
+        match slot_type {
+            SlotType::Food | SlotType::Pill => Some(stats.hp.value),
+            SlotType::HealSkill => Some(stats.target_hp.value),
+            SlotType::MpRestorer => Some(stats.mp.value),
+            SlotType::FpRestorer => Some(stats.fp.value),
+            _ => None,
+        }
+    }
+
+    /// Track the highest stat values reached since each restoration was used
+    pub fn observe(&mut self, stats: &ClientStats) {
+        self.observe_values(|slot_type| Self::stat_value(slot_type, stats));
+    }
+
+    fn observe_values(&mut self, stat_value: impl Fn(SlotType) -> Option<u32>) {
+        for usage in self.usages.iter_mut() {
+            if let Some(value) = stat_value(usage.slot_type) {
+                usage.peak_value = usage.peak_value.max(value);
+            }
+        }
+    }
+
+    /// Register a restoration use, returns whether the slot type just became exhausted
+    pub fn on_use(&mut self, slot_type: SlotType, stats: &ClientStats) -> bool {
+        match Self::stat_value(slot_type, stats) {
+            Some(value) => self.on_use_value(slot_type, value),
+            None => false,
+        }
+    }
+
+    /// Register a restoration use while its stat is at `value`
+    fn on_use_value(&mut self, slot_type: SlotType, value: u32) -> bool {
+        if let Some(usage) = self.usages.iter_mut().find(|u| u.slot_type == slot_type) {
+            if usage.peak_value <= usage.value_at_use {
+                usage.uses_without_gain += 1;
+            } else {
+                usage.uses_without_gain = 0;
+            }
+            usage.value_at_use = value;
+            usage.peak_value = value;
+            usage.uses_without_gain == MAX_USES_WITHOUT_GAIN
+        } else {
+            self.usages.push(RestorationUsage {
+                slot_type,
+                value_at_use: value,
+                peak_value: value,
+                uses_without_gain: 0,
+            });
+            false
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.usages.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RestorationTracker, MAX_USES_WITHOUT_GAIN};
+    use crate::ipc::SlotType;
+
+    #[test]
+    fn test_exhaustion() {
+        let mut tracker = RestorationTracker::default();
+        let hp = |value| move |slot_type| Some(value).filter(|_| slot_type == SlotType::Food);
+
+        // Every use that doesn't raise the stat counts, the limit is reported once
+        assert!(!tracker.on_use_value(SlotType::Food, 40));
+        for _ in 1..MAX_USES_WITHOUT_GAIN {
+            tracker.observe_values(hp(40));
+            assert!(!tracker.on_use_value(SlotType::Food, 40));
+        }
+        tracker.observe_values(hp(40));
+        assert!(tracker.on_use_value(SlotType::Food, 40));
+        tracker.observe_values(hp(40));
+        assert!(!tracker.on_use_value(SlotType::Food, 40));
+    }
+
+    #[test]
+    fn test_gain_resets_exhaustion() {
+        let mut tracker = RestorationTracker::default();
+        let mp = |value| move |slot_type| Some(value).filter(|_| slot_type == SlotType::MpRestorer);
+
+        assert!(!tracker.on_use_value(SlotType::MpRestorer, 20));
+        for _ in 1..MAX_USES_WITHOUT_GAIN {
+            tracker.observe_values(mp(20));
+            assert!(!tracker.on_use_value(SlotType::MpRestorer, 20));
+        }
+        // A gain seen at any time since the last use, even if the stat dropped again since
+        tracker.observe_values(mp(60));
+        tracker.observe_values(mp(10));
+        assert!(!tracker.on_use_value(SlotType::MpRestorer, 10));
+        tracker.observe_values(mp(10));
+        assert!(!tracker.on_use_value(SlotType::MpRestorer, 10));
+
+        // Other slot types are tracked on their own
+        assert!(!tracker.on_use_value(SlotType::Food, 10));
+
+        tracker.reset();
+        assert!(tracker.usages.is_empty());
+    }
+}
@@ -1,61 +1,113 @@
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use guard::guard;
+use chrono::Timelike;
+use rand::{prelude::SliceRandom, Rng};
 use slog::Logger;
-use tauri::Window;
 
 use crate::{
     image_analyzer::ImageAnalyzer,
-    ipc::{BotConfig, FrontendInfo, ShoutConfig},
+    ipc::{BotConfig, FrontendInfo, ShoutConfig, ShoutMessage, ShoutSelection},
     movement::MovementAccessor,
+    platform::InputSink,
     play,
+    utils::{render_template, TemplateContext},
 };
 
 use super::Behavior;
 
+/// Minimum time between two shouts, whatever their schedule
+const MIN_SHOUT_GAP: u128 = 2000;
+
+struct ScheduledMessage {
+    message: ShoutMessage,
+    next_time: Instant,
+}
+
 #[allow(dead_code)]
-pub struct ShoutBehavior<'a> {
+pub struct ShoutBehavior<'a, I: InputSink> {
     rng: rand::rngs::ThreadRng,
     logger: &'a Logger,
     movement: &'a MovementAccessor, /*<'a>*/
-    window: &'a Window,
+    input: &'a I,
+    start_time: Instant,
     last_shout_time: Instant,
-    shown_messages: Vec<String>,
-    shout_interval: u64,
-    message_iter: Option<Box<dyn Iterator<Item = String>>>,
+    messages: Vec<ScheduledMessage>,
+    next_index: usize,
 }
 
-impl<'a> Behavior<'a> for ShoutBehavior<'a> {
+impl<'a, I: InputSink> Behavior<'a, I> for ShoutBehavior<'a, I> {
     fn new(
         logger: &'a Logger,
         movement: &'a MovementAccessor,
-        window: &'a Window
+        input: &'a I,
     ) -> Self {
         Self {
             logger,
             movement,
-            window,
+            input,
             rng: rand::thread_rng(),
+            start_time: Instant::now(),
             last_shout_time: Instant::now(),
-            shown_messages: Vec::new(),
-            shout_interval: 30000,
-            message_iter: None,
+            messages: Vec::new(),
+            next_index: 0,
         }
     }
 
     fn start(&mut self, config: &BotConfig) {
+        self.start_time = Instant::now();
         self.update(config);
     }
 
     fn update(&mut self, config: &BotConfig) {
         let config = config.shout_config();
-        self.shown_messages = config.shout_messages();
-        self.message_iter = Some(Box::new(self.shown_messages.clone().into_iter().cycle()));
-        self.shout_interval = config.shout_interval();
+        let now = Instant::now();
+
+        // Plain messages share the global interval and are shouted one after another
+        let interval = config.shout_interval();
+        let plain_messages = config
+            .shout_messages()
+            .into_iter()
+            .filter(|message| !message.trim().is_empty())
+            .collect::<Vec<_>>();
+        let count = plain_messages.len() as u64;
+        let mut messages = plain_messages
+            .into_iter()
+            .enumerate()
+            .map(|(index, text)| ScheduledMessage {
+                message: ShoutMessage::verbatim(text, interval * count),
+                next_time: now + Duration::from_millis(interval * (index as u64 + 1)),
+            })
+            .collect::<Vec<_>>();
+
+        // Scheduled messages are due right away
+        messages.extend(
+            config
+                .scheduled_messages()
+                .into_iter()
+                .filter(|message| !message.text().trim().is_empty())
+                .map(|message| ScheduledMessage {
+                    message,
+                    next_time: now,
+                }),
+        );
+
+        // Messages that didn't change keep their schedule, so saving the settings doesn't shout
+        // everything at once
+        let mut previous = std::mem::take(&mut self.messages);
+        for scheduled in messages.iter_mut() {
+            if let Some(index) = previous.iter().position(|other| {
+                other.message.text() == scheduled.message.text()
+                    && other.message.interval() == scheduled.message.interval()
+                    && other.message.is_template() == scheduled.message.is_template()
+            }) {
+                scheduled.next_time = previous.remove(index).next_time;
+            }
+        }
+        self.messages = messages;
     }
 
     fn stop(&mut self, _config: &BotConfig) {
-        self.message_iter = None;
+        self.messages.clear();
     }
 
     fn run_iteration(
@@ -69,30 +121,71 @@ impl<'a> Behavior<'a> for ShoutBehavior<'a> {
     }
 }
 
-impl<'a> ShoutBehavior<'_> {
-    fn shout(&mut self, _config: &ShoutConfig) {
+impl<'a, I: InputSink> ShoutBehavior<'a, I> {
+    /// Find the index of the next message to shout
+    fn next_message_index(&mut self, config: &ShoutConfig) -> Option<usize> {
+        let now = Instant::now();
+        let due = (0..self.messages.len())
+            .filter(|index| self.messages[*index].next_time <= now)
+            .collect::<Vec<_>>();
+        if due.is_empty() {
+            return None;
+        }
+
+        match config.shout_selection() {
+            ShoutSelection::RoundRobin => {
+                let count = self.messages.len();
+                let index = (0..count)
+                    .map(|offset| (self.next_index + offset) % count)
+                    .find(|index| due.contains(index))?;
+                self.next_index = index + 1;
+                Some(index)
+            }
+            ShoutSelection::WeightedRandom => due
+                .choose_weighted(&mut self.rng, |index| self.messages[*index].message.weight())
+                .ok()
+                .copied(),
+        }
+    }
+
+    fn shout(&mut self, config: &ShoutConfig) {
         use crate::movement::prelude::*;
 
-        // Return early if time since last shout is less than shout interval
-        if Instant::now()
-            .duration_since(self.last_shout_time)
-            .as_millis()
-            < self.shout_interval as u128
-        {
+        // Return early if the last shout was too recent
+        if self.last_shout_time.elapsed().as_millis() < MIN_SHOUT_GAP {
             return;
         }
 
-        // Find next message to shout
-        guard!(let Some(mut messages) = self.message_iter.as_mut() else { return });
-        guard!(let Some(message) = messages.next() else { return });
-
-        // Avoid sending empty messages
-        if message.trim().is_empty() {
+        // Stay silent during quiet hours
+        if config.is_quiet_hour(chrono::Local::now().hour()) {
             return;
         }
 
+        // Find next message to shout
+        let index = match self.next_message_index(config) {
+            Some(index) => index,
+            None => return,
+        };
+
+        // Schedule next occurrence of the message
+        let message = self.messages[index].message.clone();
+        let jitter = self.rng.gen_range(0..=message.jitter());
+        let interval = message.interval().unwrap_or_else(|| config.shout_interval());
+        self.messages[index].next_time = Instant::now() + Duration::from_millis(interval + jitter);
+
+        // Build message
+        let context = TemplateContext {
+            elapsed: self.start_time.elapsed(),
+        };
+        let text = if message.is_template() {
+            render_template(message.text(), &context, &mut self.rng)
+        } else {
+            message.text().to_string()
+        };
+        let text = format!("{}{}", message.channel().prefix(), text);
+
         // Log message
-        slog::debug!(self.logger, "Shouting"; "message" => &message);
+        slog::debug!(self.logger, "Shouting"; "message" => &text);
 
         // Play movement
         play!(self.movement => [
@@ -101,7 +194,7 @@ impl<'a> ShoutBehavior<'_> {
             Wait(dur::Random(100..250)),
 
             // Type message
-            Type(message.to_string()),
+            Type(text.to_string()),
             Wait(dur::Random(100..200)),
 
             // Send message
@@ -117,3 +210,130 @@ impl<'a> ShoutBehavior<'_> {
         self.last_shout_time = Instant::now();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use super::ShoutBehavior;
+    use crate::{
+        behavior::Behavior,
+        ipc::BotConfig,
+        movement::MovementAccessor,
+        platform::{
+            input_sink::{RecordingSink, SentInput},
+            KeyMode,
+        },
+    };
+
+    #[test]
+    fn test_update_keeps_schedules() {
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let sink = Arc::new(RecordingSink::default());
+        let movement = MovementAccessor::new(sink.clone());
+        let config = |interval: u64| -> BotConfig {
+            serde_json::from_str(&format!(
+                r#"{{
+                    "change_id": 0, "is_running": true, "mode": "AutoShout",
+                    "farming_config": {{}}, "support_config": {{}},
+                    "shout_config": {{
+                        "shout_interval": 60000, "shout_messages": ["hello"],
+                        "scheduled_messages": [{{"text": "sale", "interval": {}}}]
+                    }}
+                }}"#,
+                interval
+            ))
+            .unwrap()
+        };
+
+        let mut behavior = ShoutBehavior::new(&logger, &movement, &*sink);
+        behavior.start(&config(30000));
+        let later = Instant::now() + Duration::from_secs(600);
+        for message in behavior.messages.iter_mut() {
+            message.next_time = later;
+        }
+
+        // Saving the same messages keeps them waiting
+        behavior.update(&config(30000));
+        assert!(behavior
+            .messages
+            .iter()
+            .all(|message| message.next_time == later));
+
+        // A new interval starts a new schedule
+        behavior.update(&config(45000));
+        assert_eq!(behavior.messages[0].next_time, later);
+        assert!(behavior.messages[1].next_time < later);
+    }
+
+    #[test]
+    fn test_shout_inputs() {
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let sink = Arc::new(RecordingSink::default());
+        let movement = MovementAccessor::new(sink.clone());
+        let config: BotConfig = serde_json::from_str(
+            r#"{
+                "change_id": 0, "is_running": true, "mode": "AutoShout",
+                "farming_config": {}, "support_config": {},
+                "shout_config": {"shout_interval": 0, "shout_messages": ["hello"]}
+            }"#,
+        )
+        .unwrap();
+
+        let mut behavior = ShoutBehavior::new(&logger, &movement, &*sink);
+        behavior.start(&config);
+        behavior.last_shout_time = Instant::now() - Duration::from_secs(3);
+        behavior.shout(config.shout_config());
+
+        assert_eq!(
+            sink.take_inputs(),
+            vec![
+                SentInput::Key("Enter".to_string(), KeyMode::Press),
+                SentInput::Message("hello".to_string()),
+                SentInput::Key("Enter".to_string(), KeyMode::Press),
+                SentInput::Key("Escape".to_string(), KeyMode::Press),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plain_messages_are_verbatim() {
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let sink = Arc::new(RecordingSink::default());
+        let movement = MovementAccessor::new(sink.clone());
+        let config: BotConfig = serde_json::from_str(
+            r#"{
+                "change_id": 0, "is_running": true, "mode": "AutoShout",
+                "farming_config": {}, "support_config": {},
+                "shout_config": {
+                    "shout_interval": 0, "shout_messages": ["{WTS|WTB} {elapsed}"],
+                    "scheduled_messages": [{"text": "{WTS|WTS} sword"}]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let mut behavior = ShoutBehavior::new(&logger, &movement, &*sink);
+        behavior.start(&config);
+        let mut shouted = vec![];
+        for _ in 0..2 {
+            behavior.last_shout_time = Instant::now() - Duration::from_secs(3);
+            behavior.shout(config.shout_config());
+            shouted.extend(
+                sink.take_inputs()
+                    .into_iter()
+                    .filter_map(|input| match input {
+                        SentInput::Message(text) => Some(text),
+                        _ => None,
+                    }),
+            );
+        }
+        shouted.sort();
+
+        // Only the scheduled messages are templates
+        assert_eq!(shouted, ["WTS sword", "{WTS|WTB} {elapsed}"]);
+    }
+}
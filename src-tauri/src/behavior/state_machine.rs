@@ -0,0 +1,143 @@
+use std::{collections::VecDeque, fmt, time::Instant};
+
+use serde::Serialize;
+
+/// Keep this many transitions around until they are collected
+const MAX_PENDING_TRANSITIONS: usize = 50;
+
+/// A state of a behavior state machine
+pub trait MachineState: Copy + fmt::Debug {
+    /// Name of the state, regardless of the data it carries
+    fn name(&self) -> &'static str;
+}
+
+/// Record of a state change
+#[derive(Debug, Clone, Serialize)]
+pub struct StateTransition {
+    pub from: &'static str,
+    pub to: &'static str,
+    pub reason: &'static str,
+    /// Unix timestamp in milliseconds
+    pub timestamp: i64,
+}
+
+/// Current state of a behavior, with the time it was entered and the transitions leading to it.
+pub struct StateMachine<S: MachineState> {
+    state: S,
+    entered_at: Instant,
+    transitions: VecDeque<StateTransition>,
+}
+
+impl<S: MachineState> StateMachine<S> {
+    pub fn new(state: S) -> Self {
+        Self {
+            state,
+            entered_at: Instant::now(),
+            transitions: VecDeque::new(),
+        }
+    }
+
+    pub fn state(&self) -> S {
+        self.state
+    }
+
+    /// Time spent in the current state in milliseconds
+    pub fn time_in_state(&self) -> u128 {
+        self.entered_at.elapsed().as_millis()
+    }
+
+    /// Get the fallback state if the current one lasted longer than its timeout.
+    /// `timeout_of` returns the timeout in milliseconds and the fallback state of a state.
+    pub fn timed_out<F>(&self, timeout_of: F) -> Option<S>
+    where
+        F: Fn(&S) -> Option<(u128, S)>,
+    {
+        timeout_of(&self.state)
+            .filter(|(timeout, _)| self.time_in_state() > *timeout)
+            .map(|(_, fallback)| fallback)
+    }
+
+    /// Switch to the next state. Returns the previous state if the state changed.
+    ///
+    /// Staying in a state with different data (e.g. attacking another target) is not a change.
+    pub fn transition(&mut self, next: S, reason: &'static str) -> Option<S> {
+        let previous = self.state;
+        self.state = next;
+        if previous.name() == next.name() {
+            return None;
+        }
+
+        self.entered_at = Instant::now();
+        if self.transitions.len() >= MAX_PENDING_TRANSITIONS {
+            self.transitions.pop_front();
+        }
+        self.transitions.push_back(StateTransition {
+            from: previous.name(),
+            to: next.name(),
+            reason,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        });
+        Some(previous)
+    }
+
+    /// Take the transitions recorded since the last call
+    pub fn take_transitions(&mut self) -> Vec<StateTransition> {
+        self.transitions.drain(..).collect()
+    }
+}
+
+/// Behavior driven by a [`StateMachine`], with hooks running on state changes
+pub trait StatefulBehavior<S: MachineState> {
+    fn machine(&mut self) -> &mut StateMachine<S>;
+
+    /// Runs when a state is entered
+    fn on_enter(&mut self, _state: S) {}
+
+    /// Runs when a state is left
+    fn on_exit(&mut self, _state: S) {}
+
+    /// Move to the next state, running exit and entry hooks if the state changed
+    fn transition(&mut self, next: S, reason: &'static str) {
+        if let Some(previous) = self.machine().transition(next, reason) {
+            self.on_exit(previous);
+            self.on_enter(next);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MachineState, StateMachine};
+
+    #[derive(Debug, Clone, Copy)]
+    enum Light {
+        Off,
+        On(u8),
+    }
+
+    impl MachineState for Light {
+        fn name(&self) -> &'static str {
+            match self {
+                Light::Off => "Off",
+                Light::On(_) => "On",
+            }
+        }
+    }
+
+    #[test]
+    fn test_state_machine_transitions() {
+        let mut machine = StateMachine::new(Light::Off);
+        assert!(machine.transition(Light::On(50), "switched").is_some());
+        assert!(machine.transition(Light::On(100), "dimmed").is_none());
+        assert!(machine.transition(Light::Off, "switched").is_some());
+
+        let transitions = machine.take_transitions();
+        assert_eq!(transitions.len(), 2);
+        assert_eq!((transitions[0].from, transitions[0].to), ("Off", "On"));
+        assert_eq!((transitions[1].from, transitions[1].to), ("On", "Off"));
+        assert!(machine.take_transitions().is_empty());
+
+        assert!(machine.timed_out(|_| Some((60000, Light::On(1)))).is_none());
+        assert!(machine.timed_out(|_| None).is_none());
+    }
+}
@@ -1,39 +1,109 @@
 use std::time::Instant;
 
+use guard::guard;
 use slog::Logger;
-use tauri::Window;
 
 use crate::{
+    bus::BusEvent,
+    chat::send_chat_message,
+    data::{MobType, TargetType},
     image_analyzer::ImageAnalyzer,
-    ipc::{BotConfig, FrontendInfo, SlotType, SupportConfig},
+    ipc::{BotConfig, FarmingConfig, FrontendInfo, SlotType, SupportConfig},
     movement::MovementAccessor,
-    platform::send_slot_eval,
+    platform::{CapturePoint, InputError, InputSink},
     play,
 };
 
-use super::Behavior;
+use super::{
+    Behavior, MachineState, RestorationTracker, StateMachine, StateTransition, StatefulBehavior,
+};
+
+/// Leader stats older than this are ignored
+const LEADER_STATS_TIMEOUT: u128 = 2000;
+
+/// How long after the leader engaged a target we look for it around us
+const LEADER_ENGAGED_TIMEOUT: u128 = 5000;
+
+/// Time the target command gets to select the leader before the attempt counts as failed
+const REACQUIRE_CHECK_DELAY: u128 = 1500;
+
+/// Failed attempts to select the leader after which the target command is considered broken and
+/// tried less often
+const MAX_FAILED_REACQUISITIONS: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AssistState {
+    /// Selecting the leader again
+    Reacquiring,
+    /// Following the selected leader
+    Following,
+    /// Attacking a monster next to the leader
+    Assisting,
+}
 
-pub struct SupportBehavior<'a> {
+impl MachineState for AssistState {
+    fn name(&self) -> &'static str {
+        match self {
+            AssistState::Reacquiring => "Reacquiring",
+            AssistState::Following => "Following",
+            AssistState::Assisting => "Assisting",
+        }
+    }
+}
+
+pub struct SupportBehavior<'a, I: InputSink> {
+    logger: &'a Logger,
     movement: &'a MovementAccessor,
-    window: &'a Window,
+    input: &'a I,
     slots_usage_last_time: [[Option<Instant>; 10]; 9],
     last_buff_usage: Instant,
     last_jump_time: Instant,
+    restorations: RestorationTracker,
+    exhausted_slot_type: Option<SlotType>,
+    machine: StateMachine<AssistState>,
+    last_follow_time: Option<Instant>,
+    last_reacquire_time: Option<Instant>,
+    is_mounted: bool,
+    leader_hp: Option<(u32, Instant)>,
+    leader_engaged_time: Option<Instant>,
+    /// Where the leader's target stands relative to it, when the leader told us
+    leader_target_offset: Option<(i32, i32)>,
+    /// Whether the leader is on its board/mount, when a leader instance told us
+    leader_mounted: Option<bool>,
+    buff_requested: bool,
+    /// Whether the leader was seen selected since the last attempt to select it
+    leader_selected: bool,
+    /// Attempts in a row where the target command didn't select the leader
+    failed_reacquisitions: u32,
     //is_on_flight: bool,
 }
 
-impl<'a> Behavior<'a> for SupportBehavior<'a> {
+impl<'a, I: InputSink> Behavior<'a, I> for SupportBehavior<'a, I> {
     fn new(
-        _logger: &'a Logger,
+        logger: &'a Logger,
         movement: &'a MovementAccessor,
-        window: &'a Window
+        input: &'a I,
     ) -> Self {
         Self {
+            logger,
             movement,
-            window,
+            input,
             slots_usage_last_time: [[None; 10]; 9],
             last_buff_usage: Instant::now(),
             last_jump_time: Instant::now(),
+            restorations: RestorationTracker::default(),
+            exhausted_slot_type: None,
+            machine: StateMachine::new(AssistState::Reacquiring),
+            last_follow_time: None,
+            last_reacquire_time: None,
+            is_mounted: false,
+            leader_hp: None,
+            leader_engaged_time: None,
+            leader_target_offset: None,
+            leader_mounted: None,
+            buff_requested: false,
+            leader_selected: false,
+            failed_reacquisitions: 0,
             //is_on_flight: false,
         }
     }
@@ -42,20 +112,50 @@ impl<'a> Behavior<'a> for SupportBehavior<'a> {
     fn update(&mut self, _config: &BotConfig) {}
     fn stop(&mut self, _config: &BotConfig) {
         self.slots_usage_last_time = [[None; 10]; 9];
+        self.restorations.reset();
+        self.exhausted_slot_type = None;
+        self.transition(AssistState::Reacquiring, "stopped");
+        self.last_follow_time = None;
+        self.last_reacquire_time = None;
+        self.is_mounted = false;
+        self.leader_engaged_time = None;
+        self.leader_target_offset = None;
+        self.buff_requested = false;
+        self.leader_selected = false;
+        self.failed_reacquisitions = 0;
     }
 
     fn run_iteration(
         &mut self,
-        _frontend_info: &mut FrontendInfo,
+        frontend_info: &mut FrontendInfo,
         config: &BotConfig,
         image: &mut ImageAnalyzer,
     ) {
+        let farming_config = config.farming_config();
         let config = config.support_config();
 
         self.update_slots_usage(config);
 
-        self.check_restorations(config, image);
-        if image.client_stats.target_hp.value > 0 {
+        // Never heal the monster we are assisting on
+        let is_assisting = self.machine.state() == AssistState::Assisting;
+        self.check_restorations(config, image, !is_assisting);
+
+        if config.assist_enabled() {
+            // Monster takes too long to die, most likely we are stuck on it
+            if let Some(fallback) = self.machine.timed_out(|state| match state {
+                AssistState::Assisting => Some((config.assist_timeout(), AssistState::Reacquiring)),
+                _ => None,
+            }) {
+                self.transition(fallback, "timeout");
+            }
+
+            let (next, reason) = match self.machine.state() {
+                AssistState::Reacquiring => self.on_reacquiring(config),
+                AssistState::Following => self.on_following(config, farming_config, image),
+                AssistState::Assisting => self.on_assisting(config, farming_config, image),
+            };
+            self.transition(next, reason);
+        } else if image.client_stats.target_hp.value > 0 {
             self.check_buffs(config);
 
             use crate::movement::prelude::*;
@@ -73,10 +173,62 @@ impl<'a> Behavior<'a> for SupportBehavior<'a> {
             }
 
         }
+
+        frontend_info.set_exhausted_slot_type(self.exhausted_slot_type);
+    }
+
+    fn is_mounted(&self) -> bool {
+        self.is_mounted
+    }
+
+    fn take_transitions(&mut self) -> Vec<StateTransition> {
+        self.machine.take_transitions()
+    }
+
+    fn on_bus_event(&mut self, event: &BusEvent, _config: &BotConfig) {
+        match event {
+            BusEvent::LeaderStats { hp, .. } | BusEvent::LeaderHpLow(hp) => {
+                self.leader_hp = Some((*hp, Instant::now()));
+            }
+            BusEvent::LeaderTargetChanged { offset } => {
+                self.leader_engaged_time = Some(Instant::now());
+                self.leader_target_offset = *offset;
+            }
+            BusEvent::LeaderMounted(mounted) => self.leader_mounted = Some(*mounted),
+            BusEvent::BuffRequest => self.buff_requested = true,
+            BusEvent::LeaderDied => {
+                slog::info!(self.logger, "Leader died");
+                self.leader_hp = None;
+                self.leader_engaged_time = None;
+                self.leader_target_offset = None;
+                self.transition(AssistState::Reacquiring, "leader died");
+            }
+            BusEvent::PauseAll => {}
+        }
     }
 }
 
-impl<'a> SupportBehavior<'_> {
+impl<'a, I: InputSink> StatefulBehavior<AssistState> for SupportBehavior<'a, I> {
+    fn machine(&mut self) -> &mut StateMachine<AssistState> {
+        &mut self.machine
+    }
+
+    fn on_exit(&mut self, state: AssistState) {
+        if state == AssistState::Assisting {
+            // Select the leader right away
+            self.last_reacquire_time = None;
+        }
+    }
+}
+
+impl<'a, I: InputSink> SupportBehavior<'a, I> {
+    /// Log inputs the client didn't accept
+    fn check_input(&self, result: Result<(), InputError>) {
+        if let Err(err) = result {
+            slog::warn!(self.logger, "Failed to send input"; "error" => err.to_string());
+        }
+    }
+
     /// Update slots cooldown timers
     fn update_slots_usage(&mut self, config: &SupportConfig) {
         let mut slotbar_index = 0;
@@ -125,46 +277,310 @@ impl<'a> SupportBehavior<'_> {
 
     fn send_slot(&mut self, slot_index: (usize, usize)) {
         // Send keystroke for first slot mapped to pill
-        send_slot_eval(self.window, slot_index.0, slot_index.1);
+        self.check_input(self.input.send_slot(slot_index.0, slot_index.1));
         // Update usage last time
         self.slots_usage_last_time[slot_index.0][slot_index.1] = Some(Instant::now());
     }
 
     fn check_buffs(&mut self, config: &SupportConfig) {
-        if self.last_buff_usage.elapsed().as_millis() > 2000 {
+        if self.buff_requested || self.last_buff_usage.elapsed().as_millis() > 2000 {
+            self.buff_requested = false;
             self.last_buff_usage = Instant::now();
             self.get_slot_for(config, None, SlotType::BuffSkill, true);
         }
     }
 
-    fn check_restorations(&mut self, config: &SupportConfig, image: &mut ImageAnalyzer) {
+    fn check_restorations(
+        &mut self,
+        config: &SupportConfig,
+        image: &mut ImageAnalyzer,
+        heal_target: bool,
+    ) {
+        self.restorations.observe(&image.client_stats);
+
         // Check HP
         let stat = Some(image.client_stats.hp.value);
         if image.client_stats.hp.value > 0 {
             if self
                 .get_slot_for(config, stat, SlotType::Pill, true)
-                .is_none()
+                .is_some()
             {
-                self.get_slot_for(config, stat, SlotType::Food, true);
+                self.track_restoration(SlotType::Pill, image);
+            } else if self
+                .get_slot_for(config, stat, SlotType::Food, true)
+                .is_some()
+            {
+                self.track_restoration(SlotType::Food, image);
             }
         }
 
-        //Check target HP
-        let stat = Some(image.client_stats.target_hp.value);
-        if image.client_stats.target_hp.value > 0 {
-            self.get_slot_for(config, stat, SlotType::HealSkill, true);
+        //Check target HP, trusting the leader's own reading when we have one
+        let stat = Some(
+            self.leader_hp()
+                .unwrap_or(image.client_stats.target_hp.value),
+        );
+        if heal_target
+            && image.client_stats.target_hp.value > 0
+            && self
+                .get_slot_for(config, stat, SlotType::HealSkill, true)
+                .is_some()
+        {
+            self.track_restoration(SlotType::HealSkill, image);
         }
 
         // Check MP
         let stat = Some(image.client_stats.mp.value);
-        if image.client_stats.mp.value > 0 {
-            self.get_slot_for(config, stat, SlotType::MpRestorer, true);
+        if image.client_stats.mp.value > 0
+            && self
+                .get_slot_for(config, stat, SlotType::MpRestorer, true)
+                .is_some()
+        {
+            self.track_restoration(SlotType::MpRestorer, image);
         }
 
         // Check FP
         let stat = Some(image.client_stats.fp.value);
-        if image.client_stats.fp.value > 0 {
-            self.get_slot_for(config, stat, SlotType::FpRestorer, true);
+        if image.client_stats.fp.value > 0
+            && self
+                .get_slot_for(config, stat, SlotType::FpRestorer, true)
+                .is_some()
+        {
+            self.track_restoration(SlotType::FpRestorer, image);
+        }
+    }
+
+    /// Latest HP reading shared by the leader, if it is recent enough
+    fn leader_hp(&self) -> Option<u32> {
+        self.leader_hp
+            .filter(|(_, time)| time.elapsed().as_millis() < LEADER_STATS_TIMEOUT)
+            .map(|(hp, _)| hp)
+    }
+
+    /// Remember restorations that stopped having any effect
+    fn track_restoration(&mut self, slot_type: SlotType, image: &ImageAnalyzer) {
+        if self.restorations.on_use(slot_type, &image.client_stats) {
+            slog::warn!(self.logger, "Restoration seems exhausted"; "slot_type" => slot_type.to_string());
+            self.exhausted_slot_type = Some(slot_type);
+        }
+    }
+
+    /// Select the leader by name and start following it
+    fn on_reacquiring(&mut self, config: &SupportConfig) -> (AssistState, &'static str) {
+        use crate::movement::prelude::*;
+
+        guard!(let Some(leader) = config.leader_name() else {
+            return (AssistState::Reacquiring, "");
+        });
+
+        // Don't spam the chat while the leader is out of range, nor while the target command
+        // keeps failing
+        let interval = if self.failed_reacquisitions >= MAX_FAILED_REACQUISITIONS {
+            config.reacquire_interval() * 10
+        } else {
+            config.reacquire_interval()
+        };
+        if let Some(last_reacquire_time) = self.last_reacquire_time {
+            if last_reacquire_time.elapsed().as_millis() < interval {
+                return (AssistState::Reacquiring, "");
+            }
+        }
+        self.last_reacquire_time = Some(Instant::now());
+        self.leader_selected = false;
+
+        // Without a command the leader has to be selected by hand
+        if let Some(command) = config.leader_target_command() {
+            slog::debug!(self.logger, "Reacquiring leader"; "leader" => leader);
+            send_chat_message(self.movement, command.replace("{leader}", leader));
+        }
+
+        let follow_key = config.follow_key();
+        play!(self.movement => [
+            Wait(dur::Fixed(200)),
+            PressKey(&follow_key),
+        ]);
+        self.last_follow_time = Some(Instant::now());
+
+        (AssistState::Following, "leader selected")
+    }
+
+    fn on_following(
+        &mut self,
+        config: &SupportConfig,
+        farming_config: &FarmingConfig,
+        image: &mut ImageAnalyzer,
+    ) -> (AssistState, &'static str) {
+        use crate::movement::prelude::*;
+
+        // Get on and off the board/mount along with the leader, when it tells us
+        if let Some(mounted) = self.leader_mounted {
+            if config.mount_with_leader() && mounted != self.is_mounted {
+                self.toggle_mount(config);
+            }
+        }
+
+        if image.client_stats.target_hp.value > 0 {
+            if !self.leader_selected && self.failed_reacquisitions >= MAX_FAILED_REACQUISITIONS {
+                slog::info!(self.logger, "Leader selected again");
+            }
+            self.leader_selected = true;
+            self.failed_reacquisitions = 0;
+        } else if !self.leader_selected {
+            // Check that the target command selected someone before relying on it
+            if self.machine.time_in_state() < REACQUIRE_CHECK_DELAY {
+                return (AssistState::Following, "");
+            }
+            self.failed_reacquisitions += 1;
+            if self.failed_reacquisitions == MAX_FAILED_REACQUISITIONS {
+                slog::warn!(self.logger, "The leader isn't selected, check the target command or select it by hand"; "attempts" => self.failed_reacquisitions);
+            }
+            return (AssistState::Reacquiring, "leader not selected");
+        } else {
+            // Leader got out of range. Leaders that aren't instances on the bus don't tell when
+            // they mount, losing them is taken as a sign they did.
+            slog::debug!(self.logger, "Leader lost");
+            if config.mount_on_leader_lost() && self.leader_mounted.is_none() && !self.is_mounted
+            {
+                self.toggle_mount(config);
+            }
+            return (AssistState::Reacquiring, "leader lost");
+        }
+
+        self.check_buffs(config);
+
+        let leader_engaged = self
+            .leader_engaged_time
+            .map(|time| time.elapsed().as_millis() < LEADER_ENGAGED_TIMEOUT)
+            .unwrap_or(false);
+        let mobs = image.identify_mobs(farming_config);
+        let dimensions = image.image().map(|image| image.dimensions());
+        let mob = match (leader_engaged, self.leader_target_offset, dimensions) {
+            // We stand next to the leader, its target is about where it is from the leader
+            (true, Some((offset_x, offset_y)), Some((width, height))) => {
+                let expected_x = (width / 2) as i32 + offset_x;
+                let expected_y = (height / 2) as i32 + offset_y;
+                mobs.iter()
+                    .map(|mob| {
+                        let coords = mob.get_attack_coords();
+                        let distance = (((coords.x as i32 - expected_x).pow(2)
+                            + (coords.y as i32 - expected_y).pow(2))
+                            as f64)
+                            .sqrt() as i32;
+                        (mob, distance)
+                    })
+                    .filter(|(_, distance)| *distance <= config.assist_radius())
+                    .min_by_key(|(_, distance)| *distance)
+                    .map(|(mob, _)| *mob)
+            }
+            // Otherwise guess it: monsters fighting next to us are most likely fighting the
+            // leader, and passive ones too right after the leader told us it engaged something
+            _ => {
+                let candidates = mobs
+                    .iter()
+                    .filter(|mob| {
+                        leader_engaged || mob.target_type == TargetType::Mob(MobType::Aggressive)
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>();
+                image
+                    .mobs_around_player(&candidates, config.assist_radius())
+                    .into_iter()
+                    .next()
+                    .copied()
+            }
+        };
+        if let Some(mob) = mob {
+            if self.is_mounted {
+                self.toggle_mount(config);
+            }
+            self.check_input(
+                image
+                    .coordinates()
+                    .to_input(CapturePoint(mob.get_attack_coords()))
+                    .and_then(|point| self.input.click_at(point)),
+            );
+            self.leader_engaged_time = None;
+            self.leader_target_offset = None;
+            return (AssistState::Assisting, "monster next to leader");
+        }
+
+        // The leader is fighting somewhere we don't get to
+        if leader_engaged && Self::is_stuck(farming_config, image, self.machine.time_in_state()) {
+            slog::debug!(self.logger, "Stuck while following the leader"; "last_motion" => image.motion.last_motion());
+            self.movement.unstick();
+            self.last_follow_time = None;
+        }
+
+        // Follow again regularly in case something interrupted us
+        let should_follow = self
+            .last_follow_time
+            .map(|time| time.elapsed().as_millis() > config.follow_interval())
+            .unwrap_or(true);
+        if should_follow {
+            let follow_key = config.follow_key();
+            play!(self.movement => [
+                PressKey(&follow_key),
+            ]);
+            self.last_follow_time = Some(Instant::now());
+        }
+
+        if config.jump_cooldown() > 0
+            && self.last_jump_time.elapsed().as_millis() > config.jump_cooldown()
+        {
+            self.last_jump_time = Instant::now();
+            play!(self.movement => [
+                Jump,
+            ]);
+        }
+
+        (AssistState::Following, "")
+    }
+
+    fn on_assisting(
+        &mut self,
+        config: &SupportConfig,
+        farming_config: &FarmingConfig,
+        image: &mut ImageAnalyzer,
+    ) -> (AssistState, &'static str) {
+        // Monster is dead, go back to the leader
+        if image.client_stats.target_hp.value == 0 {
+            return (AssistState::Reacquiring, "monster killed");
+        }
+
+        // The monster takes no damage while we stand still: we ran into an obstacle
+        let target_hp_stale_for = image
+            .client_stats
+            .target_hp
+            .last_update_time
+            .map(|time| time.elapsed().as_millis())
+            .unwrap_or(0);
+        if Self::is_stuck(
+            farming_config,
+            image,
+            target_hp_stale_for.min(self.machine.time_in_state()),
+        ) {
+            image.client_stats.target_hp.reset_last_update_time();
+            slog::debug!(self.logger, "Stuck while assisting"; "last_motion" => image.motion.last_motion());
+            self.movement.unstick();
+        }
+
+        self.get_slot_for(config, None, SlotType::AttackSkill, true);
+        (AssistState::Assisting, "")
+    }
+
+    /// Check whether the scene stayed still although we've been trying to move for `moving_for`
+    fn is_stuck(farming_config: &FarmingConfig, image: &ImageAnalyzer, moving_for: u128) -> bool {
+        let stuck_duration = farming_config.stuck_duration();
+        farming_config.stuck_detection_enabled()
+            && moving_for > stuck_duration
+            && image.motion.still_duration().as_millis() > stuck_duration
+    }
+
+    fn toggle_mount(&mut self, config: &SupportConfig) {
+        if self
+            .get_slot_for(config, None, SlotType::Flying, true)
+            .is_some()
+        {
+            self.is_mounted = !self.is_mounted;
         }
     }
 }
@@ -0,0 +1,219 @@
+use std::time::Instant;
+
+use crate::{
+    data::{Bounds, MobType, Point, Target, TargetType},
+    ipc::{ScoringWeights, TargetFilter},
+};
+
+/// Max distance in pixels between two frames for a mob to be considered the same
+const TRACK_RADIUS: i32 = 30;
+
+/// Forget mobs that haven't been seen for this long
+const TRACK_TIMEOUT: u128 = 1000;
+
+/// Time in view after which the seen time term is maxed out
+const MAX_SEEN_TIME: u128 = 5000;
+
+/// Distance in pixels from an avoided area under which the avoid proximity term starts growing
+const AVOID_PROXIMITY_RADIUS: f32 = 200.0;
+
+#[derive(Debug, Clone, Copy)]
+struct Track {
+    position: Point,
+    first_seen: Instant,
+    last_seen: Instant,
+}
+
+/// Everything the scoring terms and filters need to know besides the mob itself
+pub struct ScoringContext<'a> {
+    pub max_distance: i32,
+    pub max_name_width: u32,
+    pub hp: u32,
+    pub min_hp_attack: u32,
+    pub avoided_bounds: &'a [(Bounds, Instant, u128)],
+}
+
+/// Picks the mob to attack by scoring visible mobs with weighted terms.
+#[derive(Debug, Default)]
+pub struct TargetScorer {
+    tracks: Vec<Track>,
+}
+
+impl TargetScorer {
+    fn distance(a: &Point, b: &Point) -> i32 {
+        (((a.x as i32 - b.x as i32).pow(2) + (a.y as i32 - b.y as i32).pow(2)) as f64).sqrt() as i32
+    }
+
+    /// Match visible mobs with the ones seen in previous frames
+    pub fn observe(&mut self, mobs: &[Target]) {
+        let now = Instant::now();
+        for mob in mobs {
+            let position = mob.get_attack_coords();
+            match self
+                .tracks
+                .iter_mut()
+                .filter(|track| track.last_seen != now)
+                .find(|track| Self::distance(&track.position, &position) <= TRACK_RADIUS)
+            {
+                Some(track) => {
+                    track.position = position;
+                    track.last_seen = now;
+                }
+                None => self.tracks.push(Track {
+                    position,
+                    first_seen: now,
+                    last_seen: now,
+                }),
+            }
+        }
+        self.tracks
+            .retain(|track| track.last_seen.elapsed().as_millis() < TRACK_TIMEOUT);
+    }
+
+    pub fn reset(&mut self) {
+        self.tracks.clear();
+    }
+
+    /// Time the mob has been in view in milliseconds
+    fn seen_time(&self, mob: &Target) -> u128 {
+        let position = mob.get_attack_coords();
+        self.tracks
+            .iter()
+            .find(|track| Self::distance(&track.position, &position) <= TRACK_RADIUS)
+            .map(|track| track.first_seen.elapsed().as_millis())
+            .unwrap_or(0)
+    }
+
+    fn is_avoided(mob: &Target, context: &ScoringContext) -> bool {
+        let mut coords = mob.get_attack_coords();
+        coords.y = coords.y.saturating_sub(10);
+        context
+            .avoided_bounds
+            .iter()
+            .any(|(bounds, _, _)| bounds.contains_point(&coords))
+    }
+
+    fn matches(filter: &TargetFilter, mob: &Target, distance: i32, context: &ScoringContext) -> bool {
+        let is_aggressive = mob.target_type == TargetType::Mob(MobType::Aggressive);
+        match filter {
+            TargetFilter::MaxDistance(max_distance) => distance <= *max_distance,
+            TargetFilter::MinNameWidth(width) => mob.bounds.w >= *width,
+            TargetFilter::MaxNameWidth(width) => mob.bounds.w <= *width,
+            TargetFilter::OnlyAggressive => is_aggressive,
+            TargetFilter::OnlyPassive => !is_aggressive,
+            TargetFilter::NotAvoided => !Self::is_avoided(mob, context),
+            TargetFilter::PassiveMinHp => is_aggressive || context.hp >= context.min_hp_attack,
+        }
+    }
+
+    fn score(
+        &self,
+        weights: &ScoringWeights,
+        mob: &Target,
+        distance: i32,
+        context: &ScoringContext,
+    ) -> f32 {
+        let distance_term = 1.0 - distance as f32 / context.max_distance.max(1) as f32;
+        let aggressive_term = match mob.target_type {
+            TargetType::Mob(MobType::Aggressive) => 1.0,
+            _ => 0.0,
+        };
+        let name_width_term = mob.bounds.w as f32 / context.max_name_width.max(1) as f32;
+        let seen_time_term = self.seen_time(mob).min(MAX_SEEN_TIME) as f32 / MAX_SEEN_TIME as f32;
+
+        let center = mob.bounds.center();
+        let avoid_proximity_term = context
+            .avoided_bounds
+            .iter()
+            .map(|(bounds, _, _)| Self::distance(&bounds.center(), &center) as f32)
+            .map(|distance| 1.0 - distance / AVOID_PROXIMITY_RADIUS)
+            .fold(0.0, f32::max);
+
+        weights.distance * distance_term
+            + weights.aggressive * aggressive_term
+            + weights.name_width * name_width_term.min(1.0)
+            + weights.seen_time * seen_time_term
+            + weights.avoid_proximity * avoid_proximity_term
+    }
+
+    /// Get the best scored mob in range and matching all filters
+    pub fn select<'a, F>(
+        &self,
+        mobs: &'a [Target],
+        weights: &ScoringWeights,
+        filters: &[TargetFilter],
+        context: &ScoringContext,
+        distance_to_player: F,
+    ) -> Option<&'a Target>
+    where
+        F: Fn(&Target) -> i32,
+    {
+        mobs.iter()
+            .map(|mob| (mob, distance_to_player(mob)))
+            .filter(|(_, distance)| *distance <= context.max_distance)
+            .filter(|(mob, distance)| {
+                filters
+                    .iter()
+                    .all(|filter| Self::matches(filter, mob, *distance, context))
+            })
+            .map(|(mob, distance)| (mob, self.score(weights, mob, distance, context)))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(mob, _)| mob)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::{ScoringContext, TargetScorer};
+    use crate::{
+        data::{Bounds, MobType, Target, TargetType},
+        ipc::{ScoringPreset, ScoringWeights, TargetFilter},
+    };
+
+    fn mob(mob_type: MobType, x: u32, w: u32) -> Target {
+        Target {
+            target_type: TargetType::Mob(mob_type),
+            bounds: Bounds { x, y: 0, w, h: 10 },
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let mobs = [
+            mob(MobType::Passive, 100, 40),
+            mob(MobType::Aggressive, 300, 40),
+            mob(MobType::Passive, 500, 120),
+        ];
+        let avoided_bounds = [(Bounds { x: 80, y: 0, w: 80, h: 40 }, Instant::now(), 1000)];
+        let context = ScoringContext {
+            max_distance: 1000,
+            max_name_width: 180,
+            hp: 100,
+            min_hp_attack: 30,
+            avoided_bounds: &avoided_bounds,
+        };
+        let scorer = TargetScorer::default();
+        let distance = |mob: &Target| mob.bounds.x as i32;
+        let select = |preset: ScoringPreset, filters: &[TargetFilter]| {
+            scorer
+                .select(&mobs, &ScoringWeights::preset(preset), filters, &context, distance)
+                .map(|mob| mob.bounds.x)
+        };
+
+        // Closest mob sits next to an avoided area
+        assert_eq!(select(ScoringPreset::Closest, &[]), Some(300));
+        assert_eq!(select(ScoringPreset::Closest, &[TargetFilter::MaxDistance(200)]), Some(100));
+        assert_eq!(
+            select(ScoringPreset::Closest, &[TargetFilter::MaxDistance(200), TargetFilter::NotAvoided]),
+            None
+        );
+        assert_eq!(
+            select(ScoringPreset::Closest, &[TargetFilter::OnlyPassive, TargetFilter::NotAvoided]),
+            Some(500)
+        );
+        assert_eq!(select(ScoringPreset::AggressiveFirst, &[]), Some(300));
+        assert_eq!(select(ScoringPreset::BigMobs, &[TargetFilter::NotAvoided]), Some(500));
+    }
+}
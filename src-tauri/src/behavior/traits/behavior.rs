@@ -1,18 +1,20 @@
 use slog::Logger;
-use tauri::Window;
 
+use super::super::StateTransition;
 use crate::{
+    bus::BusEvent,
     image_analyzer::ImageAnalyzer,
     ipc::{BotConfig, FrontendInfo},
     movement::MovementAccessor,
+    platform::InputSink,
 };
 
-pub trait Behavior<'a> {
+pub trait Behavior<'a, I: InputSink> {
     /// Runs on initialization
     fn new(
         logger: &'a Logger,
         movement_accessor: &'a MovementAccessor,
-        window: &'a Window
+        input: &'a I,
     ) -> Self;
 
     /// Runs on activation
@@ -31,4 +33,23 @@ pub trait Behavior<'a> {
         config: &BotConfig,
         analyzer: &mut ImageAnalyzer,
     );
+
+    /// Runs for every event received from other instances
+    fn on_bus_event(&mut self, _event: &BusEvent, _config: &BotConfig) {}
+
+    /// Where the target picked by the behavior stands relative to the player, in captured pixels.
+    /// Shared with the other instances when leading them.
+    fn target_offset(&self, _image: &ImageAnalyzer) -> Option<(i32, i32)> {
+        None
+    }
+
+    /// Whether the behavior got on the board/mount
+    fn is_mounted(&self) -> bool {
+        false
+    }
+
+    /// State changes since the last call, for behaviors driven by a state machine
+    fn take_transitions(&mut self) -> Vec<StateTransition> {
+        vec![]
+    }
 }
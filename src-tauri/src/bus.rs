@@ -0,0 +1,7 @@
+mod bus_event;
+mod instance_bus;
+mod leader_reporter;
+
+pub use self::bus_event::{BusEvent, BusMessage};
+pub use self::instance_bus::InstanceBus;
+pub use self::leader_reporter::LeaderReporter;
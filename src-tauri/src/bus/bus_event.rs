@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+/// Event shared between the neuz instances running on this machine
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BusEvent {
+    /// The leader selected a new target. `offset` is where the target stands relative to the
+    /// leader's character on its screen, in pixels, when the leader's behavior picked it.
+    LeaderTargetChanged {
+        offset: Option<(i32, i32)>,
+    },
+    /// The leader mounted or got off its board/mount
+    LeaderMounted(bool),
+    /// Latest stat readings of the leader, in percent
+    LeaderStats { hp: u32, mp: u32, fp: u32 },
+    /// The leader HP dropped below its configured threshold
+    LeaderHpLow(u32),
+    LeaderDied,
+    /// The leader asks supporters to buff it
+    BuffRequest,
+    /// Every instance should stop
+    PauseAll,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BusMessage {
+    /// Id of the sending bus
+    pub sender: u64,
+    pub event: BusEvent,
+}
@@ -0,0 +1,278 @@
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    time::Duration,
+};
+
+use interprocess::local_socket::{LocalSocketListener, LocalSocketStream, NameTypeSupport};
+use slog::Logger;
+
+use super::{BusEvent, BusMessage};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Unix domain socket path or named pipe name, depending on what the platform supports
+fn bus_name(file_name: &str) -> String {
+    match NameTypeSupport::query() {
+        NameTypeSupport::OnlyPaths => format!("/tmp/{}", file_name),
+        NameTypeSupport::OnlyNamespaced | NameTypeSupport::Both => format!("@{}", file_name),
+    }
+}
+
+/// Local channel shared by all neuz instances of this machine.
+///
+/// The first instance binds the socket and relays messages between the others, which connect to
+/// it as clients. When that instance goes away, the next one to reconnect takes its place. Every
+/// client window of a process joins with its own bus.
+pub struct InstanceBus {
+    /// Random id telling our messages apart, the process id is shared by all client windows
+    id: u64,
+    outgoing: Sender<BusMessage>,
+    incoming: Receiver<BusMessage>,
+}
+
+impl InstanceBus {
+    /// Join the bus in a background thread. The thread exits once the bus is dropped.
+    pub fn start(logger: &Logger) -> Self {
+        Self::start_on(logger, bus_name("neuz-bus.sock"))
+    }
+
+    fn start_on(logger: &Logger, name: String) -> Self {
+        let (outgoing, outgoing_rx) = mpsc::channel();
+        let (incoming_tx, incoming) = mpsc::channel();
+        let logger = logger.clone();
+        std::thread::spawn(move || run(&logger, &name, &outgoing_rx, &incoming_tx));
+
+        Self {
+            id: rand::random(),
+            outgoing,
+            incoming,
+        }
+    }
+
+    /// Send an event to every other instance
+    pub fn publish(&self, event: BusEvent) {
+        drop(self.outgoing.send(BusMessage {
+            sender: self.id,
+            event,
+        }));
+    }
+
+    /// Take all events received from other instances since the last call
+    pub fn drain(&self) -> Vec<BusEvent> {
+        self.incoming
+            .try_iter()
+            .filter(|message| message.sender != self.id)
+            .map(|message| message.event)
+            .collect()
+    }
+}
+
+/// Connection to another instance, read and written without blocking
+struct Peer {
+    stream: LocalSocketStream,
+    buffer: Vec<u8>,
+}
+
+impl Peer {
+    fn new(stream: LocalSocketStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        Ok(Self {
+            stream,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Read all complete lines available right now
+    fn read_lines(&mut self) -> io::Result<Vec<String>> {
+        let mut chunk = [0u8; 1024];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(len) => self.buffer.extend_from_slice(&chunk[..len]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        let mut lines = vec![];
+        while let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line = self.buffer.drain(..=end).collect::<Vec<_>>();
+            lines.push(String::from_utf8_lossy(&line).trim().to_string());
+        }
+        Ok(lines)
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        self.stream.write_all(format!("{}\n", line).as_bytes())
+    }
+}
+
+/// Keep the instance on the bus, either as relay or as client, until the bus is dropped
+fn run(
+    logger: &Logger,
+    name: &str,
+    outgoing: &Receiver<BusMessage>,
+    incoming: &Sender<BusMessage>,
+) {
+    loop {
+        let result = match LocalSocketListener::bind(name) {
+            Ok(listener) => {
+                slog::info!(logger, "Relaying instance bus"; "name" => name);
+                run_relay(&listener, outgoing, incoming)
+            }
+            Err(_) => match LocalSocketStream::connect(name) {
+                Ok(stream) => {
+                    slog::info!(logger, "Joined instance bus"; "name" => name);
+                    Peer::new(stream).and_then(|peer| run_client(peer, outgoing, incoming))
+                }
+                Err(err) => {
+                    // Nobody is listening, remove the socket file left behind by a crashed relay
+                    if NameTypeSupport::query() == NameTypeSupport::OnlyPaths {
+                        drop(std::fs::remove_file(name));
+                    }
+                    Err(err)
+                }
+            },
+        };
+
+        match result {
+            Ok(()) => return,
+            Err(err) => {
+                slog::debug!(logger, "Instance bus disconnected"; "error" => err.to_string());
+                std::thread::sleep(RECONNECT_DELAY);
+            }
+        }
+    }
+}
+
+fn run_relay(
+    listener: &LocalSocketListener,
+    outgoing: &Receiver<BusMessage>,
+    incoming: &Sender<BusMessage>,
+) -> io::Result<()> {
+    listener.set_nonblocking(true)?;
+    let mut peers: Vec<Peer> = vec![];
+
+    loop {
+        // Accept new instances
+        loop {
+            match listener.accept() {
+                Ok(stream) => peers.push(Peer::new(stream)?),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        // Collect our own messages and the ones of every peer
+        let mut lines: Vec<(Option<usize>, String)> = vec![];
+        loop {
+            match outgoing.try_recv() {
+                Ok(message) => lines.push((None, serde_json::to_string(&message)?)),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Ok(()),
+            }
+        }
+        let mut disconnected = vec![];
+        for (index, peer) in peers.iter_mut().enumerate() {
+            match peer.read_lines() {
+                Ok(peer_lines) => lines.extend(peer_lines.into_iter().map(|line| (Some(index), line))),
+                Err(_) => disconnected.push(index),
+            }
+        }
+
+        // Relay every message to everyone but its sender
+        for (source, line) in lines {
+            if source.is_some() {
+                if let Ok(message) = serde_json::from_str::<BusMessage>(&line) {
+                    drop(incoming.send(message));
+                }
+            }
+            for (index, peer) in peers.iter_mut().enumerate() {
+                if Some(index) != source && peer.write_line(&line).is_err() {
+                    disconnected.push(index);
+                }
+            }
+        }
+
+        // Forget instances that went away
+        let mut index = 0;
+        peers.retain(|_| {
+            index += 1;
+            !disconnected.contains(&(index - 1))
+        });
+
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn run_client(
+    mut relay: Peer,
+    outgoing: &Receiver<BusMessage>,
+    incoming: &Sender<BusMessage>,
+) -> io::Result<()> {
+    loop {
+        loop {
+            match outgoing.try_recv() {
+                Ok(message) => relay.write_line(&serde_json::to_string(&message)?)?,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Ok(()),
+            }
+        }
+
+        for line in relay.read_lines()? {
+            if let Ok(message) = serde_json::from_str::<BusMessage>(&line) {
+                drop(incoming.send(message));
+            }
+        }
+
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{bus_name, InstanceBus};
+    use crate::bus::BusEvent;
+
+    /// Wait for `bus` to receive an event
+    fn receive(bus: &InstanceBus) -> Vec<BusEvent> {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(5) {
+            let events = bus.drain();
+            if !events.is_empty() {
+                return events;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        vec![]
+    }
+
+    #[test]
+    fn test_buses_of_one_process() {
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let name = bus_name(&format!("neuz-bus-test-{}.sock", std::process::id()));
+        let first = InstanceBus::start_on(&logger, name.clone());
+        std::thread::sleep(Duration::from_millis(200));
+        let second = InstanceBus::start_on(&logger, name);
+        // Leave the second bus time to find out the first one relays
+        std::thread::sleep(Duration::from_millis(1500));
+
+        first.publish(BusEvent::BuffRequest);
+        assert_eq!(receive(&second), vec![BusEvent::BuffRequest]);
+        second.publish(BusEvent::LeaderDied);
+        assert_eq!(receive(&first), vec![BusEvent::LeaderDied]);
+        let target = BusEvent::LeaderTargetChanged {
+            offset: Some((-40, 25)),
+        };
+        first.publish(target.clone());
+        assert_eq!(receive(&second), vec![target]);
+
+        // Our own events don't come back
+        assert!(first.drain().is_empty());
+        assert!(second.drain().is_empty());
+    }
+}
@@ -0,0 +1,103 @@
+use std::time::Instant;
+
+use crate::{
+    data::ClientStats,
+    ipc::{BusConfig, InstanceRole},
+};
+
+use super::{BusEvent, InstanceBus};
+
+/// Time between two stat updates sent by the leader in milliseconds
+const STATS_INTERVAL: u128 = 500;
+
+/// Publishes the state of a leader instance on the bus.
+pub struct LeaderReporter {
+    last_stats_time: Option<Instant>,
+    last_buff_request_time: Instant,
+    was_alive: bool,
+    had_target: bool,
+    last_target_offset: Option<(i32, i32)>,
+    was_mounted: bool,
+    was_hp_low: bool,
+}
+
+impl Default for LeaderReporter {
+    fn default() -> Self {
+        Self {
+            last_stats_time: None,
+            last_buff_request_time: Instant::now(),
+            was_alive: true,
+            had_target: false,
+            last_target_offset: None,
+            was_mounted: false,
+            was_hp_low: false,
+        }
+    }
+}
+
+impl LeaderReporter {
+    /// `target_offset` and `is_mounted` come from the running behavior, see `Behavior`.
+    pub fn report(
+        &mut self,
+        bus: &InstanceBus,
+        config: &BusConfig,
+        stats: &ClientStats,
+        is_alive: bool,
+        target_offset: Option<(i32, i32)>,
+        is_mounted: bool,
+    ) {
+        if config.role() != InstanceRole::Leader {
+            return;
+        }
+
+        if self.was_alive && !is_alive {
+            bus.publish(BusEvent::LeaderDied);
+        }
+        self.was_alive = is_alive;
+        if !is_alive {
+            return;
+        }
+
+        // Targets picked one after another keep the target bar shown
+        let has_target = stats.target_hp.value > 0;
+        if has_target && (!self.had_target || target_offset != self.last_target_offset) {
+            bus.publish(BusEvent::LeaderTargetChanged {
+                offset: target_offset,
+            });
+        }
+        self.had_target = has_target;
+        self.last_target_offset = target_offset;
+
+        if is_mounted != self.was_mounted {
+            bus.publish(BusEvent::LeaderMounted(is_mounted));
+        }
+        self.was_mounted = is_mounted;
+
+        let is_hp_low = stats.hp.value > 0 && stats.hp.value < config.hp_low_threshold();
+        if is_hp_low && !self.was_hp_low {
+            bus.publish(BusEvent::LeaderHpLow(stats.hp.value));
+        }
+        self.was_hp_low = is_hp_low;
+
+        let should_send_stats = self
+            .last_stats_time
+            .map(|time| time.elapsed().as_millis() > STATS_INTERVAL)
+            .unwrap_or(true);
+        if should_send_stats {
+            self.last_stats_time = Some(Instant::now());
+            bus.publish(BusEvent::LeaderStats {
+                hp: stats.hp.value,
+                mp: stats.mp.value,
+                fp: stats.fp.value,
+            });
+        }
+
+        let buff_request_interval = config.buff_request_interval();
+        if buff_request_interval > 0
+            && self.last_buff_request_time.elapsed().as_millis() > buff_request_interval
+        {
+            self.last_buff_request_time = Instant::now();
+            bus.publish(BusEvent::BuffRequest);
+        }
+    }
+}
@@ -0,0 +1,7 @@
+mod auto_responder;
+mod chat_message;
+mod chat_reader;
+
+pub use self::auto_responder::{send_chat_message, AutoResponder};
+pub use self::chat_message::ChatMessage;
+pub use self::chat_reader::ChatReader;
@@ -0,0 +1,103 @@
+use std::time::Instant;
+
+use regex::{Regex, RegexBuilder};
+use slog::Logger;
+
+use crate::{
+    ipc::{ChatConfig, ChatRule, ChatRuleAction},
+    movement::MovementAccessor,
+    play,
+};
+
+use super::ChatMessage;
+
+struct CompiledRule {
+    rule: ChatRule,
+    matcher: Regex,
+    last_trigger: Option<Instant>,
+}
+
+/// Matches incoming chat messages against the configured rules.
+#[derive(Default)]
+pub struct AutoResponder {
+    rules: Vec<CompiledRule>,
+}
+
+impl AutoResponder {
+    /// Compile the configured rules. Invalid patterns are logged and skipped.
+    pub fn update(&mut self, logger: &Logger, config: &ChatConfig) {
+        self.rules = config
+            .rules()
+            .into_iter()
+            .filter(|rule| rule.is_enabled() && !rule.pattern().is_empty())
+            .filter_map(|rule| {
+                let pattern = if rule.is_regex() {
+                    rule.pattern().to_string()
+                } else {
+                    regex::escape(rule.pattern())
+                };
+                match RegexBuilder::new(&pattern).case_insensitive(true).build() {
+                    Ok(matcher) => Some(CompiledRule {
+                        rule,
+                        matcher,
+                        last_trigger: None,
+                    }),
+                    Err(err) => {
+                        slog::warn!(logger, "Invalid chat rule pattern"; "pattern" => rule.pattern(), "error" => err.to_string());
+                        None
+                    }
+                }
+            })
+            .collect();
+    }
+
+    /// Get the actions of all rules matching the message
+    pub fn process(&mut self, config: &ChatConfig, message: &ChatMessage) -> Vec<ChatRuleAction> {
+        // Never react to our own messages
+        if message.sender.is_some() && message.sender.as_deref() == config.character_name() {
+            return vec![];
+        }
+
+        let mut actions = vec![];
+        for compiled in self.rules.iter_mut() {
+            let channels = compiled.rule.channels();
+            if !channels.is_empty() && !channels.contains(&message.channel) {
+                continue;
+            }
+            if !compiled.matcher.is_match(&message.text) {
+                continue;
+            }
+            if let Some(last_trigger) = compiled.last_trigger {
+                if last_trigger.elapsed().as_millis() < compiled.rule.cooldown() as u128 {
+                    continue;
+                }
+            }
+            compiled.last_trigger = Some(Instant::now());
+            actions.push(compiled.rule.action().clone());
+        }
+        actions
+    }
+}
+
+/// Type a message into the chat box and send it
+pub fn send_chat_message(movement: &MovementAccessor, text: String) {
+    use crate::movement::prelude::*;
+
+    play!(movement => [
+        // Open chatbox
+        PressKey("Enter"),
+        Wait(dur::Random(100..250)),
+
+        // Type message
+        Type(text.to_string()),
+        Wait(dur::Random(100..200)),
+
+        // Send message
+        PressKey("Enter"),
+        Wait(dur::Random(100..250)),
+
+        // Close chatbox
+        PressKey("Escape"),
+        Wait(dur::Fixed(100)),
+    ]);
+}
@@ -0,0 +1,111 @@
+use serde::Serialize;
+
+use crate::ipc::ChatChannel;
+
+/// A single line of the in-game chat
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChatMessage {
+    pub channel: ChatChannel,
+    pub sender: Option<String>,
+    pub text: String,
+}
+
+impl ChatMessage {
+    /// Parse a raw chat line as shown in the chat panel.
+    ///
+    /// Lines look like `[Party] Name: text`, `[Whisper] From Name: text` or `Name: text`.
+    /// Anything else is considered a system message.
+    pub fn parse(line: &str) -> Self {
+        let line = line.trim();
+
+        let (channel, rest) = match line.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+            Some((tag, rest)) => {
+                let channel = match tag.trim().to_lowercase().as_str() {
+                    "shout" => Some(ChatChannel::Shout),
+                    "party" => Some(ChatChannel::Party),
+                    "guild" => Some(ChatChannel::Guild),
+                    "whisper" => Some(ChatChannel::Whisper),
+                    _ => None,
+                };
+                match channel {
+                    Some(channel) => (channel, rest.trim_start()),
+                    None => return Self::system(line),
+                }
+            }
+            None => (ChatChannel::Normal, line),
+        };
+
+        let (sender, text) = match rest.split_once(':') {
+            Some((sender, text)) => (sender.trim(), text.trim()),
+            None => return Self::system(line),
+        };
+
+        // Incoming whispers are prefixed with "From", our own ones with "To"
+        let sender = match channel {
+            ChatChannel::Whisper => match sender.strip_prefix("From ") {
+                Some(sender) => sender.trim(),
+                None => return Self::system(line),
+            },
+            _ => sender,
+        };
+
+        // Names never contain spaces, so this is most likely a system message with a colon
+        if sender.is_empty() || sender.contains(' ') {
+            return Self::system(line);
+        }
+
+        Self {
+            channel,
+            sender: Some(sender.to_string()),
+            text: text.to_string(),
+        }
+    }
+
+    fn system(line: &str) -> Self {
+        Self {
+            channel: ChatChannel::System,
+            sender: None,
+            text: line.to_string(),
+        }
+    }
+
+    /// Chat command prefix to answer this message on its own channel
+    pub fn reply_prefix(&self) -> Option<String> {
+        match self.channel {
+            ChatChannel::Normal => Some(String::new()),
+            ChatChannel::Shout => Some("/s ".to_string()),
+            ChatChannel::Party => Some("/p ".to_string()),
+            ChatChannel::Guild => Some("/g ".to_string()),
+            ChatChannel::Whisper => self.sender.as_ref().map(|sender| format!("/w {} ", sender)),
+            ChatChannel::System => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChatMessage;
+    use crate::ipc::ChatChannel;
+
+    #[test]
+    fn test_parse() {
+        let message = ChatMessage::parse("[Whisper] From Seller: hey, still buying?");
+        assert_eq!(message.channel, ChatChannel::Whisper);
+        assert_eq!(message.sender.as_deref(), Some("Seller"));
+        assert_eq!(message.text, "hey, still buying?");
+        assert_eq!(message.reply_prefix().as_deref(), Some("/w Seller "));
+
+        let message = ChatMessage::parse("[Party] Healer: buff pls");
+        assert_eq!(message.channel, ChatChannel::Party);
+        assert_eq!(message.sender.as_deref(), Some("Healer"));
+
+        let message = ChatMessage::parse("Passerby: hello");
+        assert_eq!(message.channel, ChatChannel::Normal);
+        assert_eq!(message.text, "hello");
+
+        // Our own whispers and unknown lines are not attributed to anyone
+        assert_eq!(ChatMessage::parse("[Whisper] To Seller: ok").channel, ChatChannel::System);
+        assert_eq!(ChatMessage::parse("You gained 120 exp: nice").channel, ChatChannel::System);
+        assert_eq!(ChatMessage::parse("[Notice] Server restart").reply_prefix(), None);
+    }
+}
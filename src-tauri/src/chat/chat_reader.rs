@@ -0,0 +1,80 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use parking_lot::Mutex;
+use tauri::Window;
+
+use super::ChatMessage;
+
+/// Keep at most this many unread lines around
+const MAX_PENDING_LINES: usize = 200;
+
+/// Watches the chat panel of the client and forwards every new line as a `chat_line` event.
+///
+/// It follows chat lines added to the page. A client drawing its chat on the canvas has no such
+/// lines, so whether a chat panel is found is sent as `chat_reader_status` every few seconds.
+const CHAT_READER_JS: &str = "window.neuzChatReader=new MutationObserver(mutations=>{for(const mutation of mutations){for(const node of mutation.addedNodes){const chat=node.parentElement&&node.parentElement.closest('[class*=\"chat\"]');if(!chat||node.nodeName==='INPUT')continue;for(const line of (node.textContent||'').split('\\n')){if(line.trim())window.__TAURI_INVOKE__('tauri',{__tauriModule:'Event',message:{cmd:'emit',event:'chat_line',payload:JSON.stringify(line)}})}}}}),window.neuzChatReader.observe(document.body,{childList:true,subtree:true});const neuzChatStatus=()=>window.__TAURI_INVOKE__('tauri',{__tauriModule:'Event',message:{cmd:'emit',event:'chat_reader_status',payload:JSON.stringify(!!document.querySelector('[class*=\"chat\"]'))}});neuzChatStatus(),setInterval(neuzChatStatus,5000)";
+
+/// Collects chat lines sent by a client window.
+pub struct ChatReader {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    panel_found: Arc<Mutex<Option<bool>>>,
+}
+
+impl ChatReader {
+    pub fn new(window: &Window) -> Self {
+        let lines: Arc<Mutex<VecDeque<String>>> = Arc::new(Mutex::new(VecDeque::new()));
+
+        let local_lines = lines.clone();
+        window.listen("chat_line", move |e| {
+            if let Some(line) = e
+                .payload()
+                .and_then(|payload| serde_json::from_str::<String>(payload).ok())
+            {
+                let mut lines = local_lines.lock();
+                if lines.len() >= MAX_PENDING_LINES {
+                    lines.pop_front();
+                }
+                lines.push_back(line);
+            }
+        });
+
+        let panel_found = Arc::new(Mutex::new(None));
+        let local_panel_found = panel_found.clone();
+        window.listen("chat_reader_status", move |e| {
+            if let Some(found) = e
+                .payload()
+                .and_then(|payload| serde_json::from_str::<bool>(payload).ok())
+            {
+                *local_panel_found.lock() = Some(found);
+            }
+        });
+
+        Self { lines, panel_found }
+    }
+
+    /// Whether the page has a chat panel to read, `None` until the observer reported
+    pub fn panel_found(&self) -> Option<bool> {
+        *self.panel_found.lock()
+    }
+
+    /// Inject the chat observer into the client if it isn't there yet
+    pub fn install(&self, window: &Window) {
+        drop(window.eval(
+            format!("if(!window.neuzChatReader){{ {} }}", CHAT_READER_JS).as_str(),
+        ));
+    }
+
+    /// Take all messages received since the last call
+    pub fn drain(&self) -> Vec<ChatMessage> {
+        self.lines
+            .lock()
+            .drain(..)
+            .map(|line| ChatMessage::parse(&line))
+            .collect()
+    }
+
+    /// Drop unread messages
+    pub fn clear(&self) {
+        self.lines.lock().clear();
+    }
+}
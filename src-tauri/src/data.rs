@@ -15,3 +15,6 @@ pub use self::stats_info::{ClientStats, StatInfo, StatusBarConfig, StatusBarKind
 
 mod pixel_detection;
 pub use self::pixel_detection::{PixelDetection, PixelDetectionConfig, PixelDetectionKind};
+
+mod motion_estimator;
+pub use self::motion_estimator::{static_regions, MotionEstimator};
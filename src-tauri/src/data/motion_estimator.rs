@@ -0,0 +1,247 @@
+use std::time::{Duration, Instant};
+
+use libscreenshot::ImageBuffer;
+
+use super::Bounds;
+use crate::platform::{IGNORE_AREA_BOTTOM, IGNORE_AREA_TOP};
+
+/// Size of the downsampled luminance grid
+const GRID_WIDTH: usize = 32;
+const GRID_HEIGHT: usize = 18;
+
+/// Only every n-th pixel of a cell is sampled
+const SAMPLE_STEP: u32 = 4;
+
+/// Luminance change for a cell to count as changed
+const CELL_THRESHOLD: f32 = 8.0;
+
+/// Share of changed cells above which the scene is considered moving
+const MOTION_THRESHOLD: f32 = 0.15;
+
+/// Minimum amount of cells for a still region to be reported
+const MIN_REGION_CELLS: usize = 4;
+
+/// Estimates whether the camera moved by comparing consecutive frames.
+///
+/// Frames are reduced to a coarse luminance grid. The cells around the character are left out
+/// since its own animations keep changing even when it is wedged against an obstacle.
+#[derive(Debug, Clone)]
+pub struct MotionEstimator {
+    previous: Option<Vec<f32>>,
+    last_motion: f32,
+    last_moving_time: Instant,
+}
+
+impl Default for MotionEstimator {
+    fn default() -> Self {
+        Self {
+            previous: None,
+            last_motion: 0.0,
+            last_moving_time: Instant::now(),
+        }
+    }
+}
+
+impl MotionEstimator {
+    /// Compare a new frame with the previous one
+    pub fn update(&mut self, image: &ImageBuffer) {
+        let grid = match luminance_grid(image) {
+            Some(grid) => grid,
+            None => return,
+        };
+
+        if let Some(previous) = self.previous.as_ref() {
+            self.last_motion = motion_between(previous, &grid);
+            if self.last_motion > MOTION_THRESHOLD {
+                self.last_moving_time = Instant::now();
+            }
+        } else {
+            self.last_moving_time = Instant::now();
+        }
+        self.previous = Some(grid);
+    }
+
+    /// Share of the scene that changed between the last two frames, from 0 to 1
+    pub fn last_motion(&self) -> f32 {
+        self.last_motion
+    }
+
+    /// Time since the scene last moved
+    pub fn still_duration(&self) -> Duration {
+        self.last_moving_time.elapsed()
+    }
+}
+
+/// Get the top of the grid and the size of its cells, if the frame is large enough
+fn grid_geometry(image: &ImageBuffer) -> Option<(u32, u32, u32)> {
+    let top = IGNORE_AREA_TOP;
+    let bottom = image.height().checked_sub(IGNORE_AREA_BOTTOM)?;
+    if bottom <= top || image.width() < GRID_WIDTH as u32 || bottom - top < GRID_HEIGHT as u32 {
+        return None;
+    }
+    Some((
+        top,
+        image.width() / GRID_WIDTH as u32,
+        (bottom - top) / GRID_HEIGHT as u32,
+    ))
+}
+
+/// Build the luminance grid of a frame, if it is large enough
+fn luminance_grid(image: &ImageBuffer) -> Option<Vec<f32>> {
+    let (top, cell_width, cell_height) = grid_geometry(image)?;
+
+    let mut grid = Vec::with_capacity(GRID_WIDTH * GRID_HEIGHT);
+    for cell_y in 0..GRID_HEIGHT as u32 {
+        for cell_x in 0..GRID_WIDTH as u32 {
+            let mut sum = 0.0;
+            let mut count = 0;
+            for y in (0..cell_height).step_by(SAMPLE_STEP as usize) {
+                for x in (0..cell_width).step_by(SAMPLE_STEP as usize) {
+                    let pixel =
+                        image.get_pixel(cell_x * cell_width + x, top + cell_y * cell_height + y);
+                    sum += 0.299 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.114 * pixel[2] as f32;
+                    count += 1;
+                }
+            }
+            grid.push(sum / count.max(1) as f32);
+        }
+    }
+    Some(grid)
+}
+
+/// Whether a grid cell is close to the character, which stands in the middle of the screen
+fn is_center_cell(index: usize) -> bool {
+    let (x, y) = (index % GRID_WIDTH, index / GRID_WIDTH);
+    (GRID_WIDTH * 3 / 8..GRID_WIDTH * 5 / 8).contains(&x)
+        && (GRID_HEIGHT / 3..GRID_HEIGHT * 2 / 3).contains(&y)
+}
+
+fn motion_between(previous: &[f32], current: &[f32]) -> f32 {
+    let mut changed = 0;
+    let mut total = 0;
+    for (index, (a, b)) in previous.iter().zip(current).enumerate() {
+        if is_center_cell(index) {
+            continue;
+        }
+        total += 1;
+        if (a - b).abs() > CELL_THRESHOLD {
+            changed += 1;
+        }
+    }
+    changed as f32 / total.max(1) as f32
+}
+
+/// Find the parts of the screen that stayed still while the rest of the scene moved.
+///
+/// Between two frames taken before and after rotating the camera, these are UI panels drawn
+/// over the scene.
+pub fn static_regions(before: &ImageBuffer, after: &ImageBuffer) -> Vec<Bounds> {
+    let (previous, current) = match (luminance_grid(before), luminance_grid(after)) {
+        (Some(previous), Some(current)) => (previous, current),
+        _ => return vec![],
+    };
+    let (top, cell_width, cell_height) = match grid_geometry(after) {
+        Some(geometry) => geometry,
+        None => return vec![],
+    };
+
+    // Nothing can be told apart if the camera didn't move
+    if motion_between(&previous, &current) < MOTION_THRESHOLD {
+        return vec![];
+    }
+
+    // Merge runs of still cells of each row with identical runs of the rows below
+    let is_still = |index: usize| {
+        !is_center_cell(index) && (previous[index] - current[index]).abs() <= CELL_THRESHOLD
+    };
+    let mut regions: Vec<(usize, usize, usize, usize)> = vec![];
+    let mut open: Vec<usize> = vec![];
+    for y in 0..GRID_HEIGHT {
+        let mut still_open = vec![];
+        let mut x = 0;
+        while x < GRID_WIDTH {
+            if !is_still(y * GRID_WIDTH + x) {
+                x += 1;
+                continue;
+            }
+            let start = x;
+            while x < GRID_WIDTH && is_still(y * GRID_WIDTH + x) {
+                x += 1;
+            }
+            match open
+                .iter()
+                .find(|index| regions[**index].0 == start && regions[**index].2 == x)
+            {
+                Some(index) => {
+                    regions[*index].3 = y + 1;
+                    still_open.push(*index);
+                }
+                None => {
+                    regions.push((start, y, x, y + 1));
+                    still_open.push(regions.len() - 1);
+                }
+            }
+        }
+        open = still_open;
+    }
+
+    regions
+        .into_iter()
+        .filter(|(x0, y0, x1, y1)| (x1 - x0) * (y1 - y0) >= MIN_REGION_CELLS)
+        .map(|(x0, y0, x1, y1)| Bounds {
+            x: x0 as u32 * cell_width,
+            y: top + y0 as u32 * cell_height,
+            w: (x1 - x0) as u32 * cell_width,
+            h: (y1 - y0) as u32 * cell_height,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::{static_regions, MotionEstimator, MOTION_THRESHOLD};
+
+    /// Vertical stripes, shifted horizontally like a rotating camera would do
+    fn frame(offset: u32) -> RgbaImage {
+        RgbaImage::from_fn(800, 600, |x, _| {
+            if ((x + offset) / 40) % 2 == 0 {
+                Rgba([200, 200, 200, 255])
+            } else {
+                Rgba([20, 20, 20, 255])
+            }
+        })
+    }
+
+    #[test]
+    fn test_motion_estimator() {
+        let mut estimator = MotionEstimator::default();
+        estimator.update(&frame(0));
+        estimator.update(&frame(0));
+        assert!(estimator.last_motion() < MOTION_THRESHOLD);
+
+        estimator.update(&frame(40));
+        assert!(estimator.last_motion() > MOTION_THRESHOLD);
+    }
+
+    #[test]
+    fn test_static_regions() {
+        // A panel covering the top left corner doesn't move with the scene
+        let with_panel = |offset: u32| {
+            let mut image = frame(offset);
+            for y in 0..200 {
+                for x in 0..200 {
+                    image.put_pixel(x, y, Rgba([90, 60, 30, 255]));
+                }
+            }
+            image
+        };
+
+        assert!(static_regions(&with_panel(0), &with_panel(0)).is_empty());
+
+        let regions = static_regions(&with_panel(0), &with_panel(40));
+        assert_eq!(regions.len(), 1);
+        assert!(regions[0].x == 0 && regions[0].w <= 200 && regions[0].h <= 200);
+    }
+}
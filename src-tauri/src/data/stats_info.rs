@@ -1,11 +1,10 @@
 use std::{fmt, time::Instant};
 
 use slog::Logger;
-use tauri::Window;
 
 use crate::{
     image_analyzer::{Color, ImageAnalyzer},
-    platform::{KeyMode, eval_send_key},
+    platform::{InputRouter, InputSink, KeyMode},
 };
 
 use super::PointCloud;
@@ -40,10 +39,12 @@ pub struct ClientStats {
     pub target_mp: StatInfo,
 
     pub stat_try_not_detected_count: i32,
-    window: Window,
+    /// T was pressed since the stat tray was last seen
+    stat_tray_open_attempted: bool,
+    input: InputRouter,
 }
 impl ClientStats {
-    pub fn new(window: Window) -> Self {
+    pub fn new(input: InputRouter) -> Self {
         Self {
             hp: StatInfo::new(0, 0, StatusBarKind::Hp, None),
             mp: StatInfo::new(0, 0, StatusBarKind::Mp, None),
@@ -52,7 +53,8 @@ impl ClientStats {
             target_mp: StatInfo::new(0, 0, StatusBarKind::TargetMP, None),
 
             stat_try_not_detected_count: 0,
-            window
+            stat_tray_open_attempted: false,
+            input
         }
     }
 
@@ -70,19 +72,25 @@ impl ClientStats {
         }
     }
 
-    // Detect whether we can read or not stat_tray and open it if needed
+    /// Whether the stat tray is open, none of the bars can be read otherwise
+    pub fn is_stat_tray_visible(&self) -> bool {
+        self.hp.value > 0 || self.mp.value > 0 || self.fp.value > 0
+    }
+
+    // Detect whether we can read or not stat_tray and open it if needed. Only call it in game.
     pub fn detect_stat_tray(&mut self) {
         // Since HP/MP/FP are 0 we know bar should be hidden
         if self.hp.value == 0 && self.mp.value == 0 && self.fp.value == 0 {
             self.stat_try_not_detected_count += 1;
-            if self.stat_try_not_detected_count == 4 {
-                self.stat_try_not_detected_count = 0;
-
-                // Try to open char stat tray
-                eval_send_key(&self.window, "T", KeyMode::Press);
+            // Try to open char stat tray once, pressing T again would close a tray we opened
+            // but can't read
+            if self.stat_try_not_detected_count == 4 && !self.stat_tray_open_attempted {
+                self.stat_tray_open_attempted = true;
+                drop(self.input.send_key("T", KeyMode::Press));
             }
         } else {
             self.stat_try_not_detected_count = 0;
+            self.stat_tray_open_attempted = false;
         }
     }
 
@@ -1,18 +1,21 @@
 use std::{
     sync::mpsc::{sync_channel, Receiver},
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 use libscreenshot::shared::Area;
 use libscreenshot::{ImageBuffer, WindowCaptureProvider};
 use rayon::iter::{ParallelBridge, ParallelIterator};
+use serde::Serialize;
 use slog::Logger;
-use tauri::Window;
 
 use crate::{
-    data::{point_selector, Bounds, ClientStats, MobType, Point, PointCloud, Target, TargetType},
-    ipc::FarmingConfig,
-    platform::{IGNORE_AREA_BOTTOM, IGNORE_AREA_TOP},
+    data::{
+        point_selector, Bounds, ClientStats, MobType, MotionEstimator, Point, PointCloud, Target,
+        TargetType,
+    },
+    ipc::{CaptureBackend, FarmingConfig},
+    platform::{CanvasCapture, CoordinateSpace, InputRouter, IGNORE_AREA_BOTTOM, IGNORE_AREA_TOP},
     utils::Timer,
 };
 
@@ -27,19 +30,55 @@ impl Color {
     }
 }
 
+/// Average capture time of a backend, sent to the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureBenchmark {
+    backend: CaptureBackend,
+    average_ms: Option<f64>,
+    failures: usize,
+}
+
 #[derive(Debug, Clone)]
 pub struct ImageAnalyzer {
     image: Option<ImageBuffer>,
     pub window_id: u64,
     pub client_stats: ClientStats,
+    pub motion: MotionEstimator,
+    exclusion_zones: Vec<Bounds>,
+    capture_backend: CaptureBackend,
+    canvas: CanvasCapture,
+    coordinates: CoordinateSpace,
 }
 
 impl ImageAnalyzer {
-    pub fn new(window: &Window) -> Self {
+    pub fn new(input: &InputRouter, canvas: CanvasCapture, coordinates: CoordinateSpace) -> Self {
         Self {
             window_id: 0,
             image: None,
-            client_stats: ClientStats::new(window.to_owned()),
+            client_stats: ClientStats::new(input.clone()),
+            motion: MotionEstimator::default(),
+            exclusion_zones: vec![],
+            capture_backend: CaptureBackend::default(),
+            canvas,
+            coordinates,
+        }
+    }
+
+    pub fn set_capture_backend(&mut self, backend: CaptureBackend) {
+        self.capture_backend = backend;
+        self.coordinates.set_capture_backend(backend);
+    }
+
+    /// Mapping from the pixels of the captured frames to input coordinates
+    pub fn coordinates(&self) -> &CoordinateSpace {
+        &self.coordinates
+    }
+
+    /// Rows at the top of the frame that aren't part of the game. Canvas frames hold nothing else.
+    fn ignore_area_top(&self) -> u32 {
+        match self.capture_backend {
+            CaptureBackend::Window => IGNORE_AREA_TOP,
+            CaptureBackend::Canvas => 0,
         }
     }
 
@@ -47,34 +86,104 @@ impl ImageAnalyzer {
         self.image.is_some()
     }
 
-    pub fn capture_window(&mut self, logger: &Logger, _config: &FarmingConfig) {
-        let _timer = Timer::start_new("capture_window");
-        if self.window_id == 0 {
-            return;
-        }
+    pub fn image(&self) -> Option<&ImageBuffer> {
+        self.image.as_ref()
+    }
 
-        if let Some(provider) = libscreenshot::get_window_capture_provider() {
-            if let Ok(image) = provider.capture_window(self.window_id) {
-                self.image = Some(image);
-            } else {
-                slog::warn!(logger, "Failed to capture window"; "window_id" => self.window_id);
+    /// Set the screen areas where mobs are never detected
+    pub fn set_exclusion_zones(&mut self, zones: Vec<Bounds>) {
+        self.coordinates.set_exclusion_zones(zones.clone());
+        self.exclusion_zones = zones;
+    }
+
+    fn is_excluded(&self, point: &Point) -> bool {
+        self.exclusion_zones
+            .iter()
+            .any(|zone| zone.contains_point(point))
+    }
+
+    /// Capture a frame with `backend`, `None` if it isn't available right now
+    fn capture(
+        &self,
+        logger: &Logger,
+        backend: CaptureBackend,
+        area: Option<Area>,
+    ) -> Option<ImageBuffer> {
+        match backend {
+            CaptureBackend::Window => {
+                if self.window_id == 0 {
+                    return None;
+                }
+                let provider = libscreenshot::get_window_capture_provider()?;
+                let image = match area {
+                    Some(area) => provider.capture_window_area(self.window_id, area),
+                    None => provider.capture_window(self.window_id),
+                };
+                if image.is_err() {
+                    slog::warn!(logger, "Failed to capture window"; "window_id" => self.window_id);
+                }
+                image.ok()
             }
+            CaptureBackend::Canvas => match self.canvas.capture() {
+                Ok(image) => Some(match area {
+                    Some(area) => image::imageops::crop_imm(
+                        &image,
+                        area.x.max(0) as u32,
+                        area.y.max(0) as u32,
+                        area.width,
+                        area.height,
+                    )
+                    .to_image(),
+                    None => image,
+                }),
+                Err(err) => {
+                    slog::warn!(logger, "Failed to capture canvas"; "error" => err.to_string());
+                    None
+                }
+            },
+        }
+    }
+
+    pub fn capture_window(&mut self, logger: &Logger, _config: &FarmingConfig) {
+        let _timer = Timer::start_new("capture_window");
+        if let Some(image) = self.capture(logger, self.capture_backend, None) {
+            self.motion.update(&image);
+            self.image = Some(image);
         }
     }
 
     pub fn capture_window_area(&mut self, logger: &Logger, _config: &FarmingConfig, area: Area) {
         let _timer = Timer::start_new("capture_window_area");
-        if self.window_id == 0 {
-            return;
+        if let Some(image) = self.capture(logger, self.capture_backend, Some(area)) {
+            self.image = Some(image);
         }
+    }
 
-        if let Some(provider) = libscreenshot::get_window_capture_provider() {
-            if let Ok(image) = provider.capture_window_area(self.window_id, area) {
-                self.image = Some(image);
-            } else {
-                slog::warn!(logger, "Failed to capture window"; "window_id" => self.window_id);
-            }
-        }
+    /// Time `rounds` full frame captures with each backend
+    pub fn benchmark_capture(&self, logger: &Logger, rounds: usize) -> Vec<CaptureBenchmark> {
+        [CaptureBackend::Window, CaptureBackend::Canvas]
+            .into_iter()
+            .map(|backend| {
+                let mut total = Duration::ZERO;
+                let mut failures = 0;
+                for _ in 0..rounds {
+                    let start = Instant::now();
+                    match self.capture(logger, backend, None) {
+                        Some(_) => total += start.elapsed(),
+                        None => failures += 1,
+                    }
+                }
+                let successes = rounds - failures;
+                let average_ms = (successes > 0)
+                    .then(|| total.as_secs_f64() * 1000.0 / successes as f64);
+                slog::info!(logger, "Capture benchmark"; "backend" => format!("{:?}", backend), "average_ms" => format!("{:?}", average_ms), "failures" => failures);
+                CaptureBenchmark {
+                    backend,
+                    average_ms,
+                    failures,
+                }
+            })
+            .collect()
     }
 
     pub fn pixel_detection(
@@ -88,6 +197,7 @@ impl ImageAnalyzer {
     ) -> Receiver<Point> {
         let (snd, recv) = sync_channel::<Point>(4096);
         let image = self.image.as_ref().unwrap();
+        let ignore_area_top = self.ignore_area_top();
 
         if max_x == 0 {
             max_x = image.width();
@@ -103,12 +213,11 @@ impl ImageAnalyzer {
             .for_each(move |(y, row)| {
                 // Skip this row if it's in an ignored area
                 let image_height = image.height();
-                #[allow(clippy::absurd_extreme_comparisons)] // not always 0 (macOS)
-                if y <= IGNORE_AREA_TOP
+                if y <= ignore_area_top
                     || y > image_height
                         .checked_sub(IGNORE_AREA_BOTTOM)
                         .unwrap_or(image_height)
-                    || y > IGNORE_AREA_TOP + max_y
+                    || y > ignore_area_top + max_y
                     || y > max_y
                     || y < min_y
                 {
@@ -220,12 +329,13 @@ impl ImageAnalyzer {
         struct MobPixel(u32, u32, TargetType);
         let (snd, recv) = sync_channel::<MobPixel>(4096);
         let image = self.image.as_ref().unwrap();
+        let exclusion_zones = &self.exclusion_zones;
+        let ignore_area_top = self.ignore_area_top();
         image
             .enumerate_rows()
             .par_bridge()
             .for_each(move |(y, row)| {
-                #[allow(clippy::absurd_extreme_comparisons)] // not always 0 (macOS)
-                if y <= IGNORE_AREA_TOP || y > image.height() - IGNORE_AREA_BOTTOM {
+                if y <= ignore_area_top || y > image.height() - IGNORE_AREA_BOTTOM {
                     return;
                 }
                 for (x, _, px) in row {
@@ -234,6 +344,12 @@ impl ImageAnalyzer {
                     } else if x <= 250 && y <= 110 {
                         // avoid detect the health bar as a monster
                         continue;
+                    } else if exclusion_zones
+                        .iter()
+                        .any(|zone| zone.contains_point(&Point::new(x, y)))
+                    {
+                        // names drawn behind panels can't be clicked
+                        continue;
                     }
                     if Self::pixel_matches(&px.0, &ref_color_pas, config.get_passive_tolerence()) {
                         drop(snd.send(MobPixel(x, y, TargetType::Mob(MobType::Passive))));
@@ -268,8 +384,12 @@ impl ImageAnalyzer {
             false,
         );
 
-        // Return all mobs
-        Vec::from_iter(mobs_agg.into_iter().chain(mobs_pas.into_iter()))
+        // Return all mobs, leaving out the ones whose attack point is hidden behind a panel
+        mobs_agg
+            .into_iter()
+            .chain(mobs_pas.into_iter())
+            .filter(|mob| !self.is_excluded(&mob.get_attack_coords()))
+            .collect()
     }
 
     pub fn identify_target_marker(&self, config: &FarmingConfig) -> Option<Target> {
@@ -299,70 +419,22 @@ impl ImageAnalyzer {
         target_markers.into_iter().max_by_key(|x| x.bounds.size())
     }
 
-    /// Distance: `[0..=500]`
-    pub fn find_closest_mob<'a>(
-        &self,
-        mobs: &'a [Target],
-        //avoid_bounds: Option<&Bounds>,
-        avoid_list: Option<&Vec<(Bounds, Instant, u128)>>,
-        max_distance: i32,
-        _logger: &Logger,
-    ) -> Option<&'a Target> {
-        let _timer = Timer::start_new("find_closest_mob");
+    /// Distance in pixels between a point and the middle of the screen, where the player stands
+    pub fn distance_to_player(&self, point: Point) -> i32 {
         let image = self.image.as_ref().unwrap();
 
         // Calculate middle point of player
         let mid_x = (image.width() / 2) as i32;
         let mid_y = (image.height() / 2) as i32;
 
-        // Calculate 2D euclidian distances to player
-        let mut distances = Vec::default();
-        for mob in mobs {
-            let point = mob.get_attack_coords();
-            let distance = (((mid_x - point.x as i32).pow(2) + (mid_y - point.y as i32).pow(2))
-                as f64)
-                .sqrt() as i32;
-            distances.push((mob, distance));
-        }
-
-        // Sort by distance
-        distances.sort_by_key(|&(_, distance)| distance);
+        (((mid_x - point.x as i32).pow(2) + (mid_y - point.y as i32).pow(2)) as f64).sqrt() as i32
+    }
 
-        // Remove mobs that are too far away
-        distances = distances
-            .into_iter()
-            .filter(|&(_, distance)| distance <= max_distance)
-            .collect();
-
-        if let Some(avoided_bounds) = avoid_list {
-            // Try finding closest mob that's not the mob to be avoided
-            if let Some((mob, _distance)) = distances.iter().find(|(mob, _distance)| {
-                //*distance > 55
-                let mut coords = mob.get_attack_coords();
-                coords.y -= 10;
-                let mut result = true;
-                for avoided_item in avoided_bounds {
-                    if avoided_item.0.contains_point(&coords) {
-                        //slog::debug!(logger, ""; "Avoided bounds" => avoided_item.0);
-                        result = false;
-                        break;
-                    }
-                }
-                result // && *distance > 20
-                       // let coords = mob.name_bounds.get_lowest_center_point();
-                       // !avoid_bounds.grow_by(100).contains_point(&coords) && *distance > 200
-            }) {
-                Some(mob)
-            } else {
-                None
-            }
-        } else {
-            // Return closest mob
-            if let Some((mob, _distance)) = distances.first() {
-                Some(*mob)
-            } else {
-                None
-            }
-        }
+    /// Get mobs standing within `max_distance` of the player
+    pub fn mobs_around_player<'a>(&self, mobs: &'a [Target], max_distance: i32) -> Vec<&'a Target> {
+        mobs.iter()
+            .filter(|mob| self.distance_to_player(mob.get_attack_coords()) <= max_distance)
+            .collect()
     }
+
 }
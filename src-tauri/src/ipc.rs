@@ -2,6 +2,15 @@ mod bot_config;
 mod frontend_info;
 
 pub use self::bot_config::{
-    BotConfig, BotMode, FarmingConfig, ShoutConfig, Slot, SlotType, SupportConfig,
+    BotConfig, BotMode, BusConfig, CaptureBackend, ChatChannel, ChatConfig, ChatRule,
+    ChatRuleAction, FarmingConfig, FarmingStrategy, HuntingSpot, InputBackend, InstanceRole,
+    ReconnectConfig, ReconnectScreen, ScoringPreset, ScoringWeights, SessionConfig, ShoutConfig,
+    ShoutMessage, ShoutSelection, Slot, SlotType, StopAction, StopCondition, StopTrigger,
+    SupportConfig, TargetFilter,
 };
-pub use self::frontend_info::FrontendInfo;
+pub use self::frontend_info::{FrontendInfo, SessionState};
+
+/// Name of an event exchanged with the UI about the client window `label`
+pub fn client_event(label: &str, event: &str) -> String {
+    format!("{}/{}", label, event)
+}
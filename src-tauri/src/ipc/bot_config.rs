@@ -1,7 +1,9 @@
-use std::{fmt, fs::File, time::Instant};
+use std::{fmt, fs::File, path::Path, time::Instant};
 
 use serde::{Deserialize, Serialize};
 
+use crate::data::{Bounds, Point};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SlotType {
     Unused,
@@ -15,18 +17,22 @@ pub enum SlotType {
     AttackSkill,
     BuffSkill,
     Flying,
+    AoeSkill,
 }
 impl fmt::Display for SlotType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             SlotType::Food => write!(f, "food"),
             SlotType::Pill => write!(f, "pill"),
+            SlotType::HealSkill => write!(f, "heal skill"),
             SlotType::MpRestorer => write!(f, "mp restorer"),
             SlotType::FpRestorer => write!(f, "fp restorer"),
             SlotType::PickupPet => write!(f, "pickup pet"),
+            SlotType::PickupMotion => write!(f, "pickup motion"),
             SlotType::AttackSkill => write!(f, "attack skill"),
             SlotType::BuffSkill => write!(f, "buff skill"),
             SlotType::Flying => write!(f, "fly"),
+            SlotType::AoeSkill => write!(f, "aoe skill"),
             _ => write!(f, "??none??"),
         }
     }
@@ -127,6 +133,139 @@ impl ToString for BotMode {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputBackend {
+    /// Events dispatched in the client page
+    Webview,
+    /// Native events sent through XTest, Linux only
+    X11,
+}
+
+impl Default for InputBackend {
+    fn default() -> Self {
+        InputBackend::Webview
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaptureBackend {
+    /// Screenshots of the client window taken by the OS
+    Window,
+    /// Pixels read from the game canvas by the page
+    Canvas,
+}
+
+impl Default for CaptureBackend {
+    fn default() -> Self {
+        CaptureBackend::Window
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FarmingStrategy {
+    /// Attack one mob at a time
+    SingleTarget,
+    /// Tag several mobs, let them gather around the player and finish them with area skills
+    AoePull,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScoringPreset {
+    /// Aggressive mobs first, then the closest one
+    AggressiveFirst,
+    /// Closest mob whatever its type
+    Closest,
+    /// Biggest names first, which usually means higher level or giant mobs
+    BigMobs,
+    /// Mobs that stayed in view for a while, skipping names flickering at the edge of the screen
+    Steady,
+    /// Weights from the configuration
+    Custom,
+}
+
+/// Weights of the terms of a target score. Every term is in `[0, 1]`, the highest score wins.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ScoringWeights {
+    /// Closeness to the player
+    pub distance: f32,
+    /// Aggressive mobs
+    pub aggressive: f32,
+    /// Width of the name, a proxy for the level and size of the mob
+    pub name_width: f32,
+    /// Time the mob has been in view
+    pub seen_time: f32,
+    /// Closeness to areas we're avoiding, usually negative
+    pub avoid_proximity: f32,
+}
+
+impl ScoringWeights {
+    pub fn preset(preset: ScoringPreset) -> Self {
+        let weights = Self {
+            distance: 1.0,
+            aggressive: 0.0,
+            name_width: 0.0,
+            seen_time: 0.0,
+            avoid_proximity: -1.0,
+        };
+        match preset {
+            ScoringPreset::AggressiveFirst | ScoringPreset::Custom => Self {
+                aggressive: 10.0,
+                ..weights
+            },
+            ScoringPreset::Closest => weights,
+            ScoringPreset::BigMobs => Self {
+                name_width: 2.0,
+                ..weights
+            },
+            ScoringPreset::Steady => Self {
+                aggressive: 10.0,
+                seen_time: 1.0,
+                ..weights
+            },
+        }
+    }
+}
+
+/// Mobs not matching a filter are never targeted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TargetFilter {
+    /// Max distance to the player in pixels
+    MaxDistance(i32),
+    MinNameWidth(u32),
+    MaxNameWidth(u32),
+    OnlyAggressive,
+    OnlyPassive,
+    /// Skip mobs inside avoided areas (last kill, missclicks...)
+    NotAvoided,
+    /// Attack passive mobs only if HP is above `min_hp_attack`
+    PassiveMinHp,
+}
+
+/// Leg of a flying route, starting from the previous hunting spot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HuntingSpot {
+    name: Option<String>,
+    /// Heading to fly in, in degrees from the direction faced on arrival at the previous spot (at
+    /// start for the first spot), positive values to the right
+    turn: Option<i32>,
+    /// Time to fly forward in milliseconds
+    flight_duration: Option<u64>,
+}
+
+impl HuntingSpot {
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or("")
+    }
+
+    pub fn turn(&self) -> i32 {
+        self.turn.unwrap_or(0)
+    }
+
+    pub fn flight_duration(&self) -> u64 {
+        self.flight_duration.unwrap_or(0)
+    }
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct FarmingConfig {
     /// Slot configuration
@@ -134,6 +273,8 @@ pub struct FarmingConfig {
 
     /// Search for mob circle pattern
     circle_pattern_rotation_duration: Option<u64>,
+    /// Movement script played instead of the circle pattern, from the scripts directory
+    search_script: Option<String>,
 
     /// Disable farming
     farming_enabled: Option<bool>,
@@ -156,6 +297,39 @@ pub struct FarmingConfig {
     max_mobs_name_width: Option<u32>,
 
     min_hp_attack: Option<u32>,
+
+    /// Stay close to the spot where farming started
+    leash_enabled: Option<bool>,
+    leash_radius: Option<u64>,
+
+    farming_strategy: Option<FarmingStrategy>,
+    aoe_pull_count: Option<u32>,
+    aoe_cluster_radius: Option<u32>,
+    aoe_gather_timeout: Option<u64>,
+
+    /// Step away from aggressive mobs coming too close (ranged classes)
+    kiting_enabled: Option<bool>,
+    kiting_min_distance: Option<u32>,
+    kiting_retreat_duration: Option<u64>,
+    kiting_cooldown: Option<u64>,
+
+    /// Notice when the scene stops moving while the character should be walking
+    stuck_detection_enabled: Option<bool>,
+    stuck_duration: Option<u64>,
+
+    /// Give up on a target after fighting it this long, 0 to never give up
+    attack_timeout: Option<u64>,
+
+    /// Fly between hunting spots using the board from the flying slot
+    flying_enabled: Option<bool>,
+    flying_altitude: Option<u64>,
+    hunting_spots: Option<Vec<HuntingSpot>>,
+    spot_dwell_time: Option<u64>,
+
+    /// How the next mob to attack is picked
+    scoring_preset: Option<ScoringPreset>,
+    scoring_weights: Option<ScoringWeights>,
+    target_filters: Option<Vec<TargetFilter>>,
 }
 
 impl FarmingConfig {
@@ -191,6 +365,110 @@ impl FarmingConfig {
         self.min_hp_attack.unwrap_or(0)
     }
 
+    pub fn leash_enabled(&self) -> bool {
+        self.leash_enabled.unwrap_or(false)
+    }
+
+    /// Max distance from the home anchor, in milliseconds of walking
+    pub fn leash_radius(&self) -> u64 {
+        self.leash_radius.unwrap_or(5000)
+    }
+
+    pub fn farming_strategy(&self) -> FarmingStrategy {
+        self.farming_strategy.unwrap_or(FarmingStrategy::SingleTarget)
+    }
+
+    /// Amount of mobs to tag before bursting them down
+    pub fn aoe_pull_count(&self) -> u32 {
+        self.aoe_pull_count.unwrap_or(3).max(1)
+    }
+
+    /// Max distance in pixels between the player and a mob considered in range of area skills
+    pub fn aoe_cluster_radius(&self) -> i32 {
+        self.aoe_cluster_radius.unwrap_or(150) as i32
+    }
+
+    pub fn aoe_gather_timeout(&self) -> u128 {
+        self.aoe_gather_timeout.unwrap_or(6000).into()
+    }
+
+    pub fn kiting_enabled(&self) -> bool {
+        self.kiting_enabled.unwrap_or(false)
+    }
+
+    /// Distance in pixels from the player under which an aggressive mob is considered too close
+    pub fn kiting_min_distance(&self) -> i32 {
+        self.kiting_min_distance.unwrap_or(120) as i32
+    }
+
+    pub fn kiting_retreat_duration(&self) -> u64 {
+        self.kiting_retreat_duration.unwrap_or(600)
+    }
+
+    pub fn kiting_cooldown(&self) -> u128 {
+        self.kiting_cooldown.unwrap_or(2000).into()
+    }
+
+    pub fn stuck_detection_enabled(&self) -> bool {
+        self.stuck_detection_enabled.unwrap_or(false)
+    }
+
+    pub fn stuck_duration(&self) -> u128 {
+        self.stuck_duration.unwrap_or(1500).into()
+    }
+
+    pub fn search_script(&self) -> Option<&str> {
+        self.search_script.as_deref().filter(|name| !name.is_empty())
+    }
+
+    pub fn attack_timeout(&self) -> u128 {
+        self.attack_timeout.unwrap_or(120000).into()
+    }
+
+    pub fn flying_enabled(&self) -> bool {
+        self.flying_enabled.unwrap_or(false)
+    }
+
+    /// Time to hold the climb key after taking off, in milliseconds
+    pub fn flying_altitude(&self) -> u64 {
+        self.flying_altitude.unwrap_or(1500)
+    }
+
+    pub fn hunting_spots(&self) -> Vec<HuntingSpot> {
+        self.hunting_spots.clone().unwrap_or_default()
+    }
+
+    /// Time to farm a hunting spot before flying to the next one
+    pub fn spot_dwell_time(&self) -> u128 {
+        self.spot_dwell_time.unwrap_or(600000).into()
+    }
+
+    pub fn scoring_preset(&self) -> ScoringPreset {
+        self.scoring_preset.unwrap_or(ScoringPreset::AggressiveFirst)
+    }
+
+    pub fn scoring_weights(&self) -> ScoringWeights {
+        match self.scoring_preset() {
+            ScoringPreset::Custom => self
+                .scoring_weights
+                .unwrap_or_else(|| ScoringWeights::preset(ScoringPreset::Custom)),
+            preset => ScoringWeights::preset(preset),
+        }
+    }
+
+    /// Filters set by the user. Avoided areas are always skipped, even when the user filters leave
+    /// them out.
+    pub fn target_filters(&self) -> Vec<TargetFilter> {
+        let mut filters = self
+            .target_filters
+            .clone()
+            .unwrap_or_else(|| vec![TargetFilter::NotAvoided, TargetFilter::PassiveMinHp]);
+        if !filters.contains(&TargetFilter::NotAvoided) {
+            filters.push(TargetFilter::NotAvoided);
+        }
+        filters
+    }
+
     pub fn get_passive_mobs_colors(&self) -> [Option<u8>; 3] {
         self.passive_mobs_colors.unwrap_or([None, None, None])
     }
@@ -267,6 +545,23 @@ impl FarmingConfig {
 pub struct SupportConfig {
     slot_bars: Option<[SlotBar; 9]>,
     jump_cooldown: Option<u64>,
+
+    /// Follow a leader and attack the monsters it's fighting
+    assist_enabled: Option<bool>,
+    leader_name: Option<String>,
+    /// Chat command selecting the leader, `{leader}` is replaced by its name. Empty when the
+    /// leader is selected by hand.
+    leader_target_command: Option<String>,
+    follow_key: Option<String>,
+    follow_interval: Option<u64>,
+    reacquire_interval: Option<u64>,
+    assist_radius: Option<u32>,
+    assist_timeout: Option<u64>,
+    /// Mount and land when a leader instance does
+    mount_with_leader: Option<bool>,
+    /// Mount when the leader runs out of range, guessing that it mounted. Only for leaders that
+    /// don't tell whether they mounted.
+    mount_on_leader_lost: Option<bool>,
 }
 
 impl SupportConfig {
@@ -274,6 +569,50 @@ impl SupportConfig {
         return self.jump_cooldown.unwrap_or(0).into();
     }
 
+    pub fn assist_enabled(&self) -> bool {
+        self.assist_enabled.unwrap_or(false)
+    }
+
+    pub fn leader_name(&self) -> Option<&str> {
+        self.leader_name.as_deref().filter(|name| !name.is_empty())
+    }
+
+    pub fn leader_target_command(&self) -> Option<String> {
+        match self.leader_target_command.as_deref() {
+            None => Some("/target {leader}".to_string()),
+            Some(command) if command.trim().is_empty() => None,
+            Some(command) => Some(command.to_string()),
+        }
+    }
+
+    pub fn follow_key(&self) -> String {
+        self.follow_key.clone().unwrap_or_else(|| "Z".to_string())
+    }
+
+    pub fn follow_interval(&self) -> u128 {
+        self.follow_interval.unwrap_or(3000).into()
+    }
+
+    pub fn reacquire_interval(&self) -> u128 {
+        self.reacquire_interval.unwrap_or(2000).into()
+    }
+
+    pub fn assist_radius(&self) -> i32 {
+        self.assist_radius.unwrap_or(200) as i32
+    }
+
+    pub fn assist_timeout(&self) -> u128 {
+        self.assist_timeout.unwrap_or(30000).into()
+    }
+
+    pub fn mount_with_leader(&self) -> bool {
+        self.mount_with_leader.unwrap_or(true)
+    }
+
+    pub fn mount_on_leader_lost(&self) -> bool {
+        self.mount_on_leader_lost.unwrap_or(true)
+    }
+
     pub fn slot_bars(&self) -> Vec<SlotBar> {
         self.slot_bars
             .map(|slots| slots.into_iter().collect::<Vec<_>>())
@@ -311,10 +650,97 @@ impl SupportConfig {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShoutChannel {
+    Normal,
+    Shout,
+    Party,
+    Guild,
+}
+
+impl ShoutChannel {
+    /// Chat command prefix used to send a message to this channel
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            ShoutChannel::Normal => "",
+            ShoutChannel::Shout => "/s ",
+            ShoutChannel::Party => "/p ",
+            ShoutChannel::Guild => "/g ",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShoutSelection {
+    RoundRobin,
+    WeightedRandom,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShoutMessage {
+    /// Message template, see `utils::render_template`
+    text: String,
+    interval: Option<u64>,
+    jitter: Option<u64>,
+    weight: Option<u32>,
+    channel: Option<ShoutChannel>,
+    /// Shouted as written instead of rendered, for the plain messages
+    #[serde(skip)]
+    verbatim: bool,
+}
+
+impl ShoutMessage {
+    /// Message shouted as written, braces included
+    pub fn verbatim(text: String, interval: u64) -> Self {
+        Self {
+            text,
+            interval: Some(interval),
+            jitter: None,
+            weight: None,
+            channel: None,
+            verbatim: true,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether the text is a template, see `utils::render_template`
+    pub fn is_template(&self) -> bool {
+        !self.verbatim
+    }
+
+    pub fn interval(&self) -> Option<u64> {
+        self.interval
+    }
+
+    /// Max random delay added to the interval
+    pub fn jitter(&self) -> u64 {
+        self.jitter.unwrap_or(0)
+    }
+
+    pub fn weight(&self) -> u32 {
+        self.weight.unwrap_or(1)
+    }
+
+    pub fn channel(&self) -> ShoutChannel {
+        self.channel.unwrap_or(ShoutChannel::Normal)
+    }
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct ShoutConfig {
     shout_interval: Option<u64>,
     shout_messages: Option<Vec<String>>,
+
+    /// Messages with their own schedule and channel
+    scheduled_messages: Option<Vec<ShoutMessage>>,
+    shout_selection: Option<ShoutSelection>,
+
+    /// Local hours (0-23) during which nothing is shouted
+    quiet_hours_start: Option<u32>,
+    quiet_hours_end: Option<u32>,
 }
 
 impl ShoutConfig {
@@ -325,6 +751,360 @@ impl ShoutConfig {
     pub fn shout_messages(&self) -> Vec<String> {
         self.shout_messages.clone().unwrap_or_default()
     }
+
+    pub fn scheduled_messages(&self) -> Vec<ShoutMessage> {
+        self.scheduled_messages.clone().unwrap_or_default()
+    }
+
+    pub fn shout_selection(&self) -> ShoutSelection {
+        self.shout_selection.unwrap_or(ShoutSelection::RoundRobin)
+    }
+
+    /// Check whether the given local hour is within quiet hours
+    pub fn is_quiet_hour(&self, hour: u32) -> bool {
+        match (self.quiet_hours_start, self.quiet_hours_end) {
+            (Some(start), Some(end)) if start < end => hour >= start && hour < end,
+            (Some(start), Some(end)) if start > end => hour >= start || hour < end,
+            _ => false,
+        }
+    }
+}
+
+/// Event ending a bot session
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum StopTrigger {
+    /// Elapsed time in minutes
+    ElapsedTime(u64),
+    KillCount(u32),
+    /// Restoration slot type used repeatedly without any gain
+    SlotTypeExhausted(SlotType),
+    ConsecutiveDeaths(u32),
+    MissclickResets(u32),
+}
+
+impl fmt::Display for StopTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StopTrigger::ElapsedTime(minutes) => write!(f, "{} minutes elapsed", minutes),
+            StopTrigger::KillCount(count) => write!(f, "{} kills", count),
+            StopTrigger::SlotTypeExhausted(slot_type) => write!(f, "{} exhausted", slot_type),
+            StopTrigger::ConsecutiveDeaths(count) => write!(f, "{} consecutive deaths", count),
+            StopTrigger::MissclickResets(count) => write!(f, "{} missclick resets", count),
+        }
+    }
+}
+
+/// What to do once a stop condition is reached
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum StopAction {
+    Stop,
+    SwitchMode(BotMode),
+    Logout,
+}
+
+impl fmt::Display for StopAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StopAction::Stop => write!(f, "stop"),
+            StopAction::SwitchMode(mode) => write!(f, "switch to {}", mode.to_string()),
+            StopAction::Logout => write!(f, "logout"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StopCondition {
+    trigger: StopTrigger,
+    action: StopAction,
+    enabled: Option<bool>,
+}
+
+impl StopCondition {
+    pub fn trigger(&self) -> &StopTrigger {
+        &self.trigger
+    }
+
+    pub fn action(&self) -> &StopAction {
+        &self.action
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SessionConfig {
+    stop_conditions: Option<Vec<StopCondition>>,
+}
+
+impl SessionConfig {
+    pub fn stop_conditions(&self) -> Vec<StopCondition> {
+        self.stop_conditions.clone().unwrap_or_default()
+    }
+}
+
+/// Chat channel a message was received on
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ChatChannel {
+    Normal,
+    Shout,
+    Party,
+    Guild,
+    Whisper,
+    System,
+}
+
+impl fmt::Display for ChatChannel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChatChannel::Normal => write!(f, "normal"),
+            ChatChannel::Shout => write!(f, "shout"),
+            ChatChannel::Party => write!(f, "party"),
+            ChatChannel::Guild => write!(f, "guild"),
+            ChatChannel::Whisper => write!(f, "whisper"),
+            ChatChannel::System => write!(f, "system"),
+        }
+    }
+}
+
+/// What to do when a chat rule matches
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ChatRuleAction {
+    /// Reply on the channel the message came from, see `utils::render_template`
+    Reply(String),
+    PauseBot,
+    Alert,
+}
+
+impl fmt::Display for ChatRuleAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChatRuleAction::Reply(_) => write!(f, "reply"),
+            ChatRuleAction::PauseBot => write!(f, "pause bot"),
+            ChatRuleAction::Alert => write!(f, "alert"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatRule {
+    /// Keyword, or regular expression if `is_regex` is set
+    pattern: String,
+    is_regex: Option<bool>,
+    /// Channels the rule applies to, all channels if empty
+    channels: Option<Vec<ChatChannel>>,
+    action: ChatRuleAction,
+    /// Minimum time between two triggers of the rule in milliseconds
+    cooldown: Option<u64>,
+    enabled: Option<bool>,
+}
+
+impl ChatRule {
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn is_regex(&self) -> bool {
+        self.is_regex.unwrap_or(false)
+    }
+
+    pub fn channels(&self) -> Vec<ChatChannel> {
+        self.channels.clone().unwrap_or_default()
+    }
+
+    pub fn action(&self) -> &ChatRuleAction {
+        &self.action
+    }
+
+    pub fn cooldown(&self) -> u64 {
+        self.cooldown.unwrap_or(30000)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ChatConfig {
+    enabled: Option<bool>,
+    /// Own character name, messages sent by it are ignored
+    character_name: Option<String>,
+    rules: Option<Vec<ChatRule>>,
+    /// How long a pausing rule pauses the bot in milliseconds, 0 to wait for a resume from the UI
+    pause_duration: Option<u64>,
+}
+
+impl ChatConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(false)
+    }
+
+    pub fn character_name(&self) -> Option<&str> {
+        self.character_name.as_deref().filter(|name| !name.is_empty())
+    }
+
+    pub fn rules(&self) -> Vec<ChatRule> {
+        self.rules.clone().unwrap_or_default()
+    }
+
+    pub fn pause_duration(&self) -> u64 {
+        self.pause_duration.unwrap_or(300000)
+    }
+}
+
+/// Role of this instance on the local coordination bus
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum InstanceRole {
+    /// Publishes its own state for the other instances
+    Leader,
+    Follower,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct BusConfig {
+    enabled: Option<bool>,
+    role: Option<InstanceRole>,
+    /// HP percentage under which the leader warns the other instances
+    hp_low_threshold: Option<u32>,
+    /// Time between two buff requests of the leader in milliseconds, 0 to disable
+    buff_request_interval: Option<u64>,
+}
+
+impl BusConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(false)
+    }
+
+    pub fn role(&self) -> InstanceRole {
+        self.role.unwrap_or(InstanceRole::Follower)
+    }
+
+    pub fn hp_low_threshold(&self) -> u32 {
+        self.hp_low_threshold.unwrap_or(50)
+    }
+
+    pub fn buff_request_interval(&self) -> u128 {
+        self.buff_request_interval.unwrap_or(0).into()
+    }
+}
+
+/// Screens of the game a reconnection goes through, drawn in the canvas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReconnectScreen {
+    Server,
+    Character,
+    Disconnected,
+}
+
+impl fmt::Display for ReconnectScreen {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReconnectScreen::Server => write!(f, "server select"),
+            ReconnectScreen::Character => write!(f, "character select"),
+            ReconnectScreen::Disconnected => write!(f, "disconnected"),
+        }
+    }
+}
+
+/// Screen drawn in the canvas, recognized from a snapshot of it
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CanvasScreen {
+    /// Colors of a grid of pixels of the screen, taken from the UI while it's shown
+    signature: Option<Vec<[u8; 3]>>,
+    /// Point clicked to pick the entry, in pixels of the captured frame
+    click_x: Option<u32>,
+    click_y: Option<u32>,
+    /// Key pressed after the click to confirm the choice, none to only click
+    confirm_key: Option<String>,
+}
+
+impl CanvasScreen {
+    pub fn signature(&self) -> Option<&[[u8; 3]]> {
+        self.signature.as_deref()
+    }
+
+    pub fn click_point(&self) -> Option<Point> {
+        Some(Point::new(self.click_x?, self.click_y?))
+    }
+
+    pub fn confirm_key(&self) -> Option<&str> {
+        self.confirm_key.as_deref().filter(|key| !key.is_empty())
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ReconnectConfig {
+    enabled: Option<bool>,
+    /// Attempts before giving up and stopping the bot
+    max_attempts: Option<u32>,
+    /// Time before the first attempt in milliseconds
+    initial_delay: Option<u64>,
+    /// Upper bound of the time between two attempts in milliseconds
+    max_delay: Option<u64>,
+    /// Factor applied to the time before the next attempt after each failure
+    backoff_factor: Option<f32>,
+    /// Time an attempt may take to get back in game, in milliseconds
+    attempt_timeout: Option<u64>,
+    /// Position of the server in the server list, from 0
+    server_index: Option<u32>,
+    /// Position of the character in the character list, from 0
+    character_index: Option<u32>,
+    server_screen: Option<CanvasScreen>,
+    character_screen: Option<CanvasScreen>,
+    disconnected_screen: Option<CanvasScreen>,
+}
+
+impl ReconnectConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(false)
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts.unwrap_or(5)
+    }
+
+    pub fn initial_delay(&self) -> u64 {
+        self.initial_delay.unwrap_or(10000)
+    }
+
+    pub fn max_delay(&self) -> u64 {
+        self.max_delay.unwrap_or(300000)
+    }
+
+    pub fn backoff_factor(&self) -> f32 {
+        self.backoff_factor.unwrap_or(2.0).max(1.0)
+    }
+
+    pub fn attempt_timeout(&self) -> u64 {
+        self.attempt_timeout.unwrap_or(120000)
+    }
+
+    pub fn server_index(&self) -> u32 {
+        self.server_index.unwrap_or(0)
+    }
+
+    pub fn character_index(&self) -> u32 {
+        self.character_index.unwrap_or(0)
+    }
+
+    pub fn screen(&self, screen: ReconnectScreen) -> Option<&CanvasScreen> {
+        match screen {
+            ReconnectScreen::Server => self.server_screen.as_ref(),
+            ReconnectScreen::Character => self.character_screen.as_ref(),
+            ReconnectScreen::Disconnected => self.disconnected_screen.as_ref(),
+        }
+    }
+
+    fn screen_mut(&mut self, screen: ReconnectScreen) -> &mut CanvasScreen {
+        match screen {
+            ReconnectScreen::Server => &mut self.server_screen,
+            ReconnectScreen::Character => &mut self.character_screen,
+            ReconnectScreen::Disconnected => &mut self.disconnected_screen,
+        }
+        .get_or_insert_with(CanvasScreen::default)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -341,6 +1121,26 @@ pub struct BotConfig {
     farming_config: FarmingConfig,
     support_config: SupportConfig,
     shout_config: ShoutConfig,
+    #[serde(default)]
+    session_config: SessionConfig,
+    #[serde(default)]
+    chat_config: ChatConfig,
+    #[serde(default)]
+    bus_config: BusConfig,
+    #[serde(default)]
+    reconnect_config: ReconnectConfig,
+
+    /// Screen areas where mobs are never detected nor clicked
+    #[serde(default)]
+    exclusion_zones: Vec<Bounds>,
+
+    /// How keys and clicks reach the client
+    #[serde(default)]
+    input_backend: InputBackend,
+
+    /// How frames are read from the client
+    #[serde(default)]
+    capture_backend: CaptureBackend,
 }
 
 impl Default for BotConfig {
@@ -352,6 +1152,13 @@ impl Default for BotConfig {
             farming_config: FarmingConfig::default(),
             support_config: SupportConfig::default(),
             shout_config: ShoutConfig::default(),
+            session_config: SessionConfig::default(),
+            chat_config: ChatConfig::default(),
+            bus_config: BusConfig::default(),
+            reconnect_config: ReconnectConfig::default(),
+            exclusion_zones: vec![],
+            input_backend: InputBackend::default(),
+            capture_backend: CaptureBackend::default(),
         }
     }
 }
@@ -365,6 +1172,11 @@ impl BotConfig {
         self.is_running
     }
 
+    pub fn stop(&mut self) {
+        self.is_running = false;
+        self.change_id += 1;
+    }
+
     pub fn change_id(&self) -> u64 {
         self.change_id
     }
@@ -374,6 +1186,12 @@ impl BotConfig {
         self
     }
 
+    /// Take the place of `previous`, so the change is picked up like an edit from the UI
+    pub fn replacing(mut self, previous: &BotConfig) -> Self {
+        self.change_id = previous.change_id + 1;
+        self
+    }
+
     pub fn farming_config(&self) -> &FarmingConfig {
         &self.farming_config
     }
@@ -386,25 +1204,68 @@ impl BotConfig {
         &self.shout_config
     }
 
+    pub fn session_config(&self) -> &SessionConfig {
+        &self.session_config
+    }
+
+    pub fn chat_config(&self) -> &ChatConfig {
+        &self.chat_config
+    }
+
+    pub fn bus_config(&self) -> &BusConfig {
+        &self.bus_config
+    }
+
+    pub fn reconnect_config(&self) -> &ReconnectConfig {
+        &self.reconnect_config
+    }
+
+    pub fn exclusion_zones(&self) -> &[Bounds] {
+        &self.exclusion_zones
+    }
+
+    pub fn input_backend(&self) -> InputBackend {
+        self.input_backend
+    }
+
+    pub fn capture_backend(&self) -> CaptureBackend {
+        self.capture_backend
+    }
+
+    pub fn add_exclusion_zones(&mut self, zones: Vec<Bounds>) {
+        self.exclusion_zones.extend(zones);
+        self.change_id += 1;
+    }
+
+    pub fn set_screen_signature(&mut self, screen: ReconnectScreen, signature: Vec<[u8; 3]>) {
+        self.reconnect_config.screen_mut(screen).signature = Some(signature);
+        self.change_id += 1;
+    }
+
     pub fn mode(&self) -> Option<BotMode> {
         self.mode.clone()
     }
 
+    pub fn set_mode(&mut self, mode: BotMode) {
+        self.mode = Some(mode);
+        self.change_id += 1;
+    }
+
     /// Serialize config to disk
-    pub fn serialize(&self) {
+    pub fn serialize(&self, path: &Path) {
         let config = {
             let mut config = self.clone();
             config.is_running = false;
             config
         };
-        if let Ok(mut file) = File::create(".botconfig") {
+        if let Ok(mut file) = File::create(path) {
             let _ = serde_json::to_writer(&mut file, &config);
         }
     }
 
     /// Deserialize config from disk
-    pub fn deserialize_or_default() -> Self {
-        if let Ok(mut file) = File::open(".botconfig") {
+    pub fn deserialize_or_default(path: &Path) -> Self {
+        if let Ok(mut file) = File::open(path) {
             serde_json::from_reader::<_, BotConfig>(&mut file).unwrap_or_default()
         } else {
             Self::default()
@@ -1,5 +1,41 @@
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 
+use super::SlotType;
+
+/// Screen the game client currently shows
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SessionState {
+    Loading,
+    Login,
+    CharacterSelect,
+    InGame,
+    /// The game shows that the connection to the server is gone
+    Disconnected,
+    /// None of the screens above could be recognized
+    Unknown,
+}
+
+impl Default for SessionState {
+    fn default() -> Self {
+        Self::Loading
+    }
+}
+
+impl fmt::Display for SessionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionState::Loading => write!(f, "loading"),
+            SessionState::Login => write!(f, "login"),
+            SessionState::CharacterSelect => write!(f, "character select"),
+            SessionState::InGame => write!(f, "in game"),
+            SessionState::Disconnected => write!(f, "disconnected"),
+            SessionState::Unknown => write!(f, "unknown screen"),
+        }
+    }
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Copy, Clone)]
 pub struct FrontendInfo {
     /* enemy_bounds: Option<Vec<Bounds>>,
@@ -10,6 +46,13 @@ pub struct FrontendInfo {
     is_attacking: bool,
     is_running: bool,
     is_alive: bool,
+    missclick_reset_count: u32,
+    exhausted_slot_type: Option<SlotType>,
+    session_state: SessionState,
+    /// A chat rule paused the bot
+    is_paused: bool,
+    /// Whether the page has a chat panel the chat reader can follow, unknown until it looked
+    chat_panel_found: Option<bool>,
 }
 
 impl FrontendInfo {
@@ -26,6 +69,10 @@ impl FrontendInfo {
         self.enemy_kill_count = enemy_kill_count;
     }
 
+    pub fn kill_count(&self) -> u32 {
+        self.enemy_kill_count
+    }
+
     pub fn set_kill_avg(&mut self, active_enemy_bounds: (f32, f32)) {
         self.kill_min_avg = active_enemy_bounds.0;
         self.kill_hour_avg = active_enemy_bounds.1;
@@ -45,6 +92,34 @@ impl FrontendInfo {
     pub fn is_alive(&mut self) -> bool {
         self.is_alive
     }
+
+    pub fn set_missclick_reset_count(&mut self, missclick_reset_count: u32) {
+        self.missclick_reset_count = missclick_reset_count;
+    }
+
+    pub fn missclick_reset_count(&self) -> u32 {
+        self.missclick_reset_count
+    }
+
+    pub fn set_exhausted_slot_type(&mut self, slot_type: Option<SlotType>) {
+        self.exhausted_slot_type = slot_type;
+    }
+
+    pub fn exhausted_slot_type(&self) -> Option<SlotType> {
+        self.exhausted_slot_type
+    }
+
+    pub fn set_session_state(&mut self, session_state: SessionState) {
+        self.session_state = session_state;
+    }
+
+    pub fn set_is_paused(&mut self, is_paused: bool) {
+        self.is_paused = is_paused;
+    }
+
+    pub fn set_chat_panel_found(&mut self, chat_panel_found: Option<bool>) {
+        self.chat_panel_found = chat_panel_found;
+    }
     /// Serialize config to disk
     /* pub fn serialize(&self) {
         let config = {
@@ -4,31 +4,61 @@
 )]
 
 mod behavior;
+mod bus;
+mod chat;
 mod data;
 mod image_analyzer;
 mod ipc;
 mod movement;
 mod platform;
+mod profiles;
+mod reconnect;
+mod session_monitor;
+mod session_state;
 mod utils;
 
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 use guard::guard;
 use ipc::FrontendInfo;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use slog::{Drain, Level, Logger};
-use tauri::Manager;
+use tauri::{Manager, WindowEvent};
 
 use crate::{
     behavior::{Behavior, FarmingBehavior, ShoutBehavior, SupportBehavior},
+    bus::{BusEvent, InstanceBus, LeaderReporter},
+    chat::{send_chat_message, AutoResponder, ChatReader},
+    data::{static_regions, Bounds},
     image_analyzer::ImageAnalyzer,
-    ipc::{BotConfig, BotMode},
-    movement::MovementAccessor,
-    utils::Timer,
+    ipc::{
+        client_event, BotConfig, BotMode, ChatRuleAction, InputBackend, ReconnectScreen,
+        SessionState, StopAction,
+    },
+    movement::{MovementAccessor, MovementRecorder},
+    platform::{CanvasCapture, CoordinateSpace, InputRouter, InputSink, JsBridge},
+    profiles::{profile_arg, ProfileError, ProfileList, ProfileStore},
+    reconnect::{CredentialStore, Credentials, ReconnectOutcome, Reconnector},
+    session_monitor::SessionMonitor,
+    session_state::{screen_signature, SessionTracker},
+    utils::{render_template, TemplateContext, Timer},
 };
 
 struct AppState {
     logger: Logger,
+    /// Labels of the client windows a bot runs for
+    clients: Mutex<HashSet<String>>,
+    profiles: Arc<Mutex<ProfileStore>>,
+    /// Where the key of the stored credentials is kept, away from the credentials
+    key_dir: PathBuf,
 }
 
 fn main() {
@@ -62,34 +92,166 @@ fn main() {
     let drain = sentry_slog::SentryDrain::new(drain).fuse();
     let logger = Logger::root(drain.fuse(), slog::o!());
 
+    // Profile asked for on the command line, used by the first client window
+    let profile = profile_arg(std::env::args().skip(1));
+
     // Build app
     tauri::Builder::default()
         // .menu(tauri::Menu::os_default(&context.package_info().name))
-        .manage(AppState {
-            logger,
+        .setup(move |app| {
+            let app_dir = app.handle().path_resolver().app_dir().unwrap_or_default();
+            let mut profiles = ProfileStore::open(&app_dir)?;
+            // Machine local data rather than the roaming app data, where both exist
+            let key_dir = match (tauri::api::path::local_data_dir(), app_dir.file_name()) {
+                (Some(dir), Some(name)) => dir.join(name).join("keys"),
+                _ => app_dir.join("keys"),
+            };
+            if let Some(name) = profile {
+                let result = match profiles.switch("client", &name) {
+                    Err(ProfileError::NotFound(_)) => profiles
+                        .create(&name)
+                        .and_then(|_| profiles.switch("client", &name)),
+                    result => result,
+                };
+                if let Err(err) = result {
+                    slog::error!(logger, "Failed to use the profile"; "profile" => name, "error" => err.to_string());
+                }
+            }
+            app.manage(AppState {
+                logger,
+                clients: Mutex::new(HashSet::new()),
+                profiles: Arc::new(Mutex::new(profiles)),
+                key_dir,
+            });
+            Ok(())
         })
-        .invoke_handler(tauri::generate_handler![start_bot,])
+        .invoke_handler(tauri::generate_handler![
+            start_bot,
+            list_profiles,
+            create_profile,
+            duplicate_profile,
+            rename_profile,
+            delete_profile,
+            switch_profile,
+        ])
         .run(context)
         .expect("error while running tauri application");
 }
 
+/// Profiles, and the one the client window `label` uses
+#[tauri::command]
+fn list_profiles(label: String, state: tauri::State<AppState>) -> ProfileList {
+    state.profiles.lock().list(&label)
+}
+
+#[tauri::command]
+fn create_profile(
+    label: String,
+    name: String,
+    state: tauri::State<AppState>,
+) -> Result<ProfileList, String> {
+    let profiles = state.profiles.lock();
+    profiles.create(&name).map_err(|err| err.to_string())?;
+    Ok(profiles.list(&label))
+}
+
+#[tauri::command]
+fn duplicate_profile(
+    label: String,
+    from: String,
+    to: String,
+    state: tauri::State<AppState>,
+) -> Result<ProfileList, String> {
+    let profiles = state.profiles.lock();
+    profiles
+        .duplicate(&from, &to)
+        .map_err(|err| err.to_string())?;
+    Ok(profiles.list(&label))
+}
+
+#[tauri::command]
+fn rename_profile(
+    label: String,
+    from: String,
+    to: String,
+    state: tauri::State<AppState>,
+) -> Result<ProfileList, String> {
+    let mut profiles = state.profiles.lock();
+    profiles.rename(&from, &to).map_err(|err| err.to_string())?;
+    Ok(profiles.list(&label))
+}
+
+#[tauri::command]
+fn delete_profile(
+    label: String,
+    name: String,
+    state: tauri::State<AppState>,
+) -> Result<ProfileList, String> {
+    let mut profiles = state.profiles.lock();
+    profiles
+        .delete(&name, &state.clients.lock())
+        .map_err(|err| err.to_string())?;
+    Ok(profiles.list(&label))
+}
+
+/// Make the client window `label` use another profile, its bot stops and loads its config
 #[tauri::command]
-fn start_bot(state: tauri::State<AppState>, app_handle: tauri::AppHandle) {
-    let window = app_handle.get_window("client").unwrap();
-    let logger = state.logger.clone();
-    let mut image_analyzer: ImageAnalyzer = ImageAnalyzer::new(&window);
+fn switch_profile(
+    label: String,
+    name: String,
+    state: tauri::State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<ProfileList, String> {
+    let mut profiles = state.profiles.lock();
+    profiles
+        .switch(&label, &name)
+        .map_err(|err| err.to_string())?;
+    app_handle.trigger_global(&client_event(&label, "profile_switched"), None);
+    Ok(profiles.list(&label))
+}
+
+/// Run a bot for the client window `label` on its own thread, until the window is closed
+#[tauri::command]
+fn start_bot(label: String, state: tauri::State<AppState>, app_handle: tauri::AppHandle) {
+    guard!(let Some(window) = app_handle.get_window(&label) else {
+        slog::error!(state.logger, "Client window not found"; "label" => &label);
+        return;
+    });
+    if !state.clients.lock().insert(label.clone()) {
+        return;
+    }
+    let logger = state.logger.new(slog::o!("client" => label.clone()));
+    let profiles = state.profiles.clone();
+    let key_dir = state.key_dir.clone();
+
+    let closed = Arc::new(AtomicBool::new(false));
+    let local_closed = closed.clone();
+    window.on_window_event(move |event| {
+        if matches!(event, WindowEvent::Destroyed) {
+            local_closed.store(true, Ordering::Relaxed);
+        }
+    });
 
-    image_analyzer.window_id = platform::get_window_id(&window).unwrap_or(0);
+    let bridge = JsBridge::new(window.clone());
+    let coordinates = CoordinateSpace::default();
+    let window_id = platform::get_window_id(&window).unwrap_or(0);
+    let input = InputRouter::new(bridge.clone(), window_id, coordinates.clone());
+    let mut image_analyzer: ImageAnalyzer = ImageAnalyzer::new(
+        &input,
+        CanvasCapture::new(window.clone()),
+        coordinates.clone(),
+    );
+    image_analyzer.window_id = window_id;
     std::thread::spawn(move || {
         let logger = logger.clone();
         let mut last_config_change_id = 0;
-        let config: Arc<RwLock<BotConfig>> =
-            Arc::new(RwLock::new(BotConfig::deserialize_or_default()));
+        let config: Arc<RwLock<BotConfig>> = Arc::new(RwLock::new(profiles.lock().load(&label)));
+        let mut event_handlers = vec![];
 
         // Listen for config changes from the UI
         let local_config = config.clone();
         let logger_botconfig_c2s = logger.clone();
-        app_handle.listen_global("bot_config_c2s", move |e| {
+        event_handlers.push(app_handle.listen_global(client_event(&label, "bot_config_c2s"), move |e| {
             slog::trace!(logger_botconfig_c2s, "Received config change"; "event_payload" => e.payload());
             if let Some(payload) = e.payload() {
                 match serde_json::from_str::<BotConfig>(payload) {
@@ -101,20 +263,126 @@ fn start_bot(state: tauri::State<AppState>, app_handle: tauri::AppHandle) {
                     }
                 }
             }
-        });
+        }));
 
         // Listen for bot activation state
         let local_config = config.clone();
-        app_handle.listen_global("toggle_bot", move |_| {
-            local_config.write().toggle_active();
-        });
+        event_handlers.push(app_handle.listen_global(
+            client_event(&label, "toggle_bot"),
+            move |_| {
+                local_config.write().toggle_active();
+            },
+        ));
+
+        // Listen for the UI asking for the current state, when it switches to this client
+        let state_requested = Arc::new(AtomicBool::new(false));
+        let local_state_requested = state_requested.clone();
+        event_handlers.push(app_handle.listen_global(
+            client_event(&label, "bot_state_request"),
+            move |_| {
+                local_state_requested.store(true, Ordering::Relaxed);
+            },
+        ));
+
+        // Listen for requests to stop every instance
+        let local_config = config.clone();
+        let pause_all_requested = Arc::new(AtomicBool::new(false));
+        let local_pause_all_requested = pause_all_requested.clone();
+        event_handlers.push(app_handle.listen_global("pause_all", move |_| {
+            local_config.write().stop();
+            local_pause_all_requested.store(true, Ordering::Relaxed);
+        }));
+
+        // Listen for the client window switching to another profile
+        let profile_switched = Arc::new(AtomicBool::new(false));
+        let local_profile_switched = profile_switched.clone();
+        event_handlers.push(app_handle.listen_global(
+            client_event(&label, "profile_switched"),
+            move |_| {
+                local_profile_switched.store(true, Ordering::Relaxed);
+            },
+        ));
+
+        // Listen for the UI resuming a bot paused by a chat rule
+        let resume_requested = Arc::new(AtomicBool::new(false));
+        let local_resume_requested = resume_requested.clone();
+        event_handlers.push(app_handle.listen_global(
+            client_event(&label, "resume_bot"),
+            move |_| {
+                local_resume_requested.store(true, Ordering::Relaxed);
+            },
+        ));
+
+        // Listen for requests to exclude the panels currently open
+        let snapshot_requested = Arc::new(AtomicBool::new(false));
+        let local_snapshot_requested = snapshot_requested.clone();
+        event_handlers.push(app_handle.listen_global(
+            client_event(&label, "snapshot_exclusion_zones"),
+            move |_| {
+                local_snapshot_requested.store(true, Ordering::Relaxed);
+            },
+        ));
+
+        // Listen for requests to take a snapshot of a reconnect screen shown in the canvas
+        let screen_snapshot_requested: Arc<Mutex<Option<ReconnectScreen>>> = Arc::default();
+        let local_screen_snapshot_requested = screen_snapshot_requested.clone();
+        let logger_screen_snapshot = logger.clone();
+        event_handlers.push(app_handle.listen_global(
+            client_event(&label, "snapshot_reconnect_screen"),
+            move |e| match e.payload().map(serde_json::from_str::<ReconnectScreen>) {
+                Some(Ok(screen)) => *local_screen_snapshot_requested.lock() = Some(screen),
+                _ => {
+                    slog::error!(logger_screen_snapshot, "Invalid reconnect screen"; "payload" => e.payload())
+                }
+            },
+        ));
+
+        // Listen for requests to compare the capture backends
+        let benchmark_requested = Arc::new(AtomicBool::new(false));
+        let local_benchmark_requested = benchmark_requested.clone();
+        event_handlers.push(app_handle.listen_global(
+            client_event(&label, "benchmark_capture"),
+            move |_| {
+                local_benchmark_requested.store(true, Ordering::Relaxed);
+            },
+        ));
+
+        // Listen for the login used to reconnect, an empty password forgets it
+        let credential_store = CredentialStore::new(profiles.clone(), &label, &key_dir);
+        let local_credential_store = credential_store.clone();
+        let local_app_handle = app_handle.clone();
+        let credentials_event = client_event(&label, "credentials_s2c");
+        let logger_credentials = logger.clone();
+        event_handlers.push(app_handle.listen_global(
+            client_event(&label, "save_credentials_c2s"),
+            move |e| {
+                guard!(let Some(credentials) = e
+                    .payload()
+                    .and_then(|payload| serde_json::from_str::<Credentials>(payload).ok()) else { return });
+                let result = if credentials.password.is_empty() {
+                    local_credential_store.clear()
+                } else {
+                    local_credential_store.save(&credentials)
+                };
+                if let Err(err) = result {
+                    slog::warn!(logger_credentials, "Failed to store credentials"; "error" => err.to_string());
+                }
+                drop(local_app_handle.emit_all(&credentials_event, local_credential_store.status()));
+            },
+        ));
 
         let send_config = |config: &BotConfig| {
-            drop(app_handle.emit_all("bot_config_s2c", &*config) as Result<(), _>)
+            drop(
+                app_handle.emit_all(&client_event(&label, "bot_config_s2c"), &*config)
+                    as Result<(), _>,
+            )
         };
 
         let send_info = |config: &FrontendInfo| {
-            drop(app_handle.emit_all("bot_info_s2c", &*config) as Result<(), _>)
+            drop(
+                app_handle.emit_all(&client_event(&label, "bot_info_s2c"), &*config)
+                    as Result<(), _>,
+            )
         };
 
         // Wait a second for frontend to become ready
@@ -124,12 +392,12 @@ fn start_bot(state: tauri::State<AppState>, app_handle: tauri::AppHandle) {
         send_config(&*config.read());
 
         // Create movement accessor
-        let movement = MovementAccessor::new(window.clone()/*&accessor*/);
+        let movement = MovementAccessor::new(Arc::new(input.clone())/*&accessor*/);
 
         // Instantiate behaviors
-        let mut farming_behavior = FarmingBehavior::new(&logger, &movement, &window);
-        let mut shout_behavior = ShoutBehavior::new(&logger, &movement, &window);
-        let mut support_behavior = SupportBehavior::new(&logger, &movement, &window);
+        let mut farming_behavior = FarmingBehavior::new(&logger, &movement, &input);
+        let mut shout_behavior = ShoutBehavior::new(&logger, &movement, &input);
+        let mut support_behavior = SupportBehavior::new(&logger, &movement, &input);
 
         let mut last_mode: Option<BotMode> = None;
 
@@ -137,9 +405,95 @@ fn start_bot(state: tauri::State<AppState>, app_handle: tauri::AppHandle) {
         let mut frontend_info: Arc<RwLock<FrontendInfo>> =
             Arc::new(RwLock::new(FrontendInfo::deserialize_or_default()));
         send_info(&*frontend_info.read());
+
+        let mut session_monitor = SessionMonitor::default();
+        let mut pending_stop_action: Option<StopAction> = None;
+        let mut pending_exclusion_zones: Option<Vec<Bounds>> = None;
+        let mut pending_screen_signature: Option<(ReconnectScreen, Vec<[u8; 3]>)> = None;
+
+        // Keep detection and clicks away from excluded areas
+        image_analyzer.set_exclusion_zones(config.read().exclusion_zones().to_vec());
+
+        // Record the player's input into movement scripts
+        let recorder = MovementRecorder::new(&app_handle, &window, &logger);
+
+        // Read the chat and react to it
+        let chat_reader = ChatReader::new(&window);
+        let mut auto_responder = AutoResponder::default();
+        let mut chat_panel_found = None;
+        // Time a chat rule paused the bot
+        let mut chat_pause: Option<Instant> = None;
+        auto_responder.update(&logger, config.read().chat_config());
+        let mut rng = rand::thread_rng();
+
+        // Coordinate with the other instances of this machine
+        let mut instance_bus: Option<InstanceBus> = None;
+        let mut leader_reporter = LeaderReporter::default();
+
+        let mut coordinates_refresh_time: Option<Instant> = None;
+
+        // Tell whether the client is in game, and get back in game after a disconnection
+        let mut session_tracker = SessionTracker::new(bridge.clone());
+        let mut reconnector = Reconnector::new(
+            bridge.clone(),
+            Arc::new(input.clone()),
+            image_analyzer.coordinates().clone(),
+            credential_store.clone(),
+        );
+
         // Enter main loop
         loop {
             let timer = Timer::start_new("main_loop");
+
+            // Stop once the client window is gone
+            if closed.load(Ordering::Relaxed) {
+                slog::info!(logger, "Client window closed");
+                movement.cancel_all();
+                break;
+            }
+
+            // Keep the coordinate mapping in sync with the window scale and layout
+            if coordinates_refresh_time
+                .map_or(true, |time| time.elapsed() >= Duration::from_secs(5))
+            {
+                if let Err(err) = coordinates.refresh(&bridge, &window) {
+                    slog::debug!(logger, "Failed to refresh coordinates"; "error" => err.to_string());
+                }
+                coordinates_refresh_time = Some(Instant::now());
+            }
+
+            // Apply the action of a reached stop condition
+            if let Some(action) = pending_stop_action.take() {
+                let mut config = config.write();
+                match action {
+                    StopAction::Stop => config.stop(),
+                    StopAction::SwitchMode(mode) => config.set_mode(mode),
+                    StopAction::Logout => {
+                        config.stop();
+                        if let Err(err) = input.logout() {
+                            slog::warn!(logger, "Failed to log out"; "error" => err.to_string());
+                        }
+                    }
+                }
+            }
+
+            // Load the config of the profile switched to, the bot stops with it
+            if profile_switched.swap(false, Ordering::Relaxed) {
+                movement.cancel_all();
+                let loaded = profiles.lock().load(&label);
+                let mut config = config.write();
+                *config = loaded.replacing(&config);
+                slog::info!(logger, "Switched profile"; "profile" => profiles.lock().active(&label));
+            }
+
+            // Save the zones found by the last snapshot
+            if let Some(zones) = pending_exclusion_zones.take() {
+                config.write().add_exclusion_zones(zones);
+            }
+            if let Some((screen, signature)) = pending_screen_signature.take() {
+                config.write().set_screen_signature(screen, signature);
+            }
+
             let config = &*config.read();
             let mut frontend_info_mut = *frontend_info.read();
 
@@ -154,9 +508,19 @@ fn start_bot(state: tauri::State<AppState>, app_handle: tauri::AppHandle) {
                 ),
             );
 
+            // Send the whole state when the UI switched to this client
+            if state_requested.swap(false, Ordering::Relaxed) {
+                send_config(config);
+                send_info(&*frontend_info.read());
+                drop(app_handle.emit_all(
+                    &client_event(&label, "credentials_s2c"),
+                    credential_store.status(),
+                ));
+            }
+
             // Send changed config to frontend if needed
             if config.change_id() > last_config_change_id {
-                config.serialize();
+                profiles.lock().save(&label, config);
                 send_config(config);
                 last_config_change_id = config.change_id();
 
@@ -164,10 +528,82 @@ fn start_bot(state: tauri::State<AppState>, app_handle: tauri::AppHandle) {
                 farming_behavior.update(config);
                 shout_behavior.update(config);
                 support_behavior.update(config);
+                auto_responder.update(&logger, config.chat_config());
+                image_analyzer.set_exclusion_zones(config.exclusion_zones().to_vec());
+                image_analyzer.set_capture_backend(config.capture_backend());
+                if let Err(err) = input.set_backend(config.input_backend()) {
+                    slog::warn!(logger, "Input backend unavailable, using the webview"; "error" => err.to_string());
+                }
+            }
+
+            // Rotate the camera and exclude whatever didn't move with the scene
+            if snapshot_requested.swap(false, Ordering::Relaxed) {
+                use crate::movement::prelude::*;
+
+                image_analyzer.capture_window(&logger, config.farming_config());
+                let before = image_analyzer.image().cloned();
+                play!(movement => [
+                    HoldKeyFor("D", dur::Fixed(400)),
+                    Wait(dur::Fixed(300)),
+                ]);
+                image_analyzer.capture_window(&logger, config.farming_config());
+                if let (Some(before), Some(after)) = (before, image_analyzer.image()) {
+                    let zones = static_regions(&before, after);
+                    slog::info!(logger, "Exclusion zones snapshot"; "zones" => zones.len());
+                    pending_exclusion_zones = Some(zones);
+                }
+            }
+
+            // Remember how a reconnect screen looks, to recognize it in the canvas
+            if let Some(screen) = screen_snapshot_requested.lock().take() {
+                image_analyzer.capture_window(&logger, config.farming_config());
+                if let Some(image) = image_analyzer.image() {
+                    slog::info!(logger, "Reconnect screen snapshot"; "screen" => screen.to_string());
+                    pending_screen_signature = Some((screen, screen_signature(image)));
+                }
+            }
+
+            // Time both capture backends
+            if benchmark_requested.swap(false, Ordering::Relaxed) {
+                let results = image_analyzer.benchmark_capture(&logger, 10);
+                drop(app_handle.emit_all(&client_event(&label, "capture_benchmark_s2c"), &results));
+            }
+
+            // Exchange events with the other instances
+            if config.bus_config().is_enabled() {
+                let bus = instance_bus.get_or_insert_with(|| InstanceBus::start(&logger));
+                if pause_all_requested.swap(false, Ordering::Relaxed) {
+                    bus.publish(BusEvent::PauseAll);
+                }
+                for event in bus.drain() {
+                    if event == BusEvent::PauseAll && config.is_running() {
+                        slog::info!(logger, "Paused by another instance");
+                        pending_stop_action = Some(StopAction::Stop);
+                    }
+                    farming_behavior.on_bus_event(&event, config);
+                    shout_behavior.on_bus_event(&event, config);
+                    support_behavior.on_bus_event(&event, config);
+                }
+            } else {
+                instance_bus = None;
+                pause_all_requested.store(false, Ordering::Relaxed);
             }
 
-            // Continue early if the bot is not engaged
-            if !config.is_running() {
+            // X11 input is trusted by the page, it would end up in recordings
+            recorder.set_bot_input_trusted(
+                config.is_running() && config.input_backend() == InputBackend::X11,
+            );
+
+            // Continue early if the bot is not engaged or the player is recording a movement
+            if !config.is_running() || recorder.is_recording() {
+                session_monitor.reset();
+                reconnector.cancel(&logger);
+                chat_reader.clear();
+                if chat_pause.take().is_some() {
+                    frontend_info_mut.set_is_paused(false);
+                    frontend_info = Arc::new(RwLock::new(frontend_info_mut));
+                    send_info(&*frontend_info.read());
+                }
                 std::thread::sleep(std::time::Duration::from_millis(250));
                 timer.silence();
                 continue;
@@ -187,7 +623,8 @@ fn start_bot(state: tauri::State<AppState>, app_handle: tauri::AppHandle) {
                 if &mode != last_mode {
                     slog::info!(logger, "Mode changed"; "old_mode" => last_mode.to_string(), "new_mode" => mode.to_string());
 
-                    // Stop all behaviors
+                    // Stop all behaviors and whatever they left moving
+                    movement.cancel_all();
                     farming_behavior.stop(&config);
                     support_behavior.stop(&config);
                     shout_behavior.stop(&config);
@@ -201,6 +638,57 @@ fn start_bot(state: tauri::State<AppState>, app_handle: tauri::AppHandle) {
                 }
             }
 
+            // React to new chat messages
+            if config.chat_config().is_enabled() {
+                chat_reader.install(&window);
+                if chat_reader.panel_found() != chat_panel_found {
+                    chat_panel_found = chat_reader.panel_found();
+                    if chat_panel_found == Some(false) {
+                        slog::warn!(logger, "No chat panel in the page, chat rules can't see any message");
+                    }
+                    frontend_info_mut.set_chat_panel_found(chat_panel_found);
+                    frontend_info = Arc::new(RwLock::new(frontend_info_mut));
+                    send_info(&*frontend_info.read());
+                }
+                for message in chat_reader.drain() {
+                    for action in auto_responder.process(config.chat_config(), &message) {
+                        slog::info!(logger, "Chat rule matched"; "channel" => message.channel.to_string(), "sender" => message.sender.as_deref().unwrap_or(""), "text" => &message.text, "action" => action.to_string());
+                        match action {
+                            ChatRuleAction::Reply(reply) => {
+                                if let Some(prefix) = message.reply_prefix() {
+                                    let context = TemplateContext {
+                                        elapsed: session_monitor.elapsed(),
+                                    };
+                                    let reply = reply.replace(
+                                        "{sender}",
+                                        message.sender.as_deref().unwrap_or(""),
+                                    );
+                                    let reply = render_template(&reply, &context, &mut rng);
+                                    send_chat_message(&movement, format!("{}{}", prefix, reply));
+                                }
+                            }
+                            ChatRuleAction::PauseBot => {
+                                if chat_pause.is_none() {
+                                    movement.cancel_all();
+                                    chat_pause = Some(Instant::now());
+                                    resume_requested.store(false, Ordering::Relaxed);
+                                    frontend_info_mut.set_is_paused(true);
+                                    frontend_info = Arc::new(RwLock::new(frontend_info_mut));
+                                    send_info(&*frontend_info.read());
+                                }
+                            }
+                            ChatRuleAction::Alert => drop(
+                                app_handle
+                                    .emit_all(&client_event(&label, "chat_alert_s2c"), &message)
+                                    as Result<(), _>,
+                            ),
+                        }
+                    }
+                }
+            } else {
+                chat_reader.clear();
+            }
+
             // Capture client window
             image_analyzer.capture_window(&logger, config.farming_config());
 
@@ -211,11 +699,106 @@ fn start_bot(state: tauri::State<AppState>, app_handle: tauri::AppHandle) {
                     .client_stats
                     .update(&image_analyzer.clone(), &logger);
 
+                // Pause the behaviors outside the game
+                let mut reconnect_outcome = None;
+                if let Some(previous) =
+                    session_tracker.update(&image_analyzer, config.reconnect_config())
+                {
+                    let state = session_tracker.state();
+                    slog::info!(logger, "Session state changed"; "from" => previous.to_string(), "to" => state.to_string());
+                    if previous == SessionState::InGame {
+                        movement.cancel_all();
+                    }
+                    reconnect_outcome =
+                        reconnector.on_session_change(&logger, config, previous, state);
+                    frontend_info_mut.set_session_state(state);
+                    frontend_info = Arc::new(RwLock::new(frontend_info_mut));
+                    send_info(&*frontend_info.read());
+                }
+                match reconnect_outcome.or_else(|| {
+                    reconnector.update(
+                        &logger,
+                        config.reconnect_config(),
+                        session_tracker.state(),
+                        session_tracker.screen().canvas_screen,
+                    )
+                }) {
+                    Some(ReconnectOutcome::Resumed(Some(mode)))
+                        if config.mode().as_ref() != Some(&mode) =>
+                    {
+                        pending_stop_action = Some(StopAction::SwitchMode(mode));
+                    }
+                    Some(ReconnectOutcome::GaveUp) => pending_stop_action = Some(StopAction::Stop),
+                    _ => {}
+                }
+                // No input outside the game. A game started with a closed stat tray can't be
+                // recognized until the tray is opened, which is tried once from a screen that may
+                // be the game.
+                if session_tracker.state() != SessionState::InGame {
+                    if session_tracker.may_be_in_game() {
+                        image_analyzer.client_stats.detect_stat_tray();
+                    }
+                    continue;
+                }
+
+                // Stay idle while a chat rule paused the bot, until resumed from the UI or the
+                // pause is over
+                if let Some(since) = chat_pause {
+                    let duration = config.chat_config().pause_duration();
+                    if resume_requested.swap(false, Ordering::Relaxed)
+                        || (duration > 0 && since.elapsed() >= Duration::from_millis(duration))
+                    {
+                        slog::info!(logger, "Resuming after a chat pause");
+                        chat_pause = None;
+                        frontend_info_mut.set_is_paused(false);
+                        frontend_info = Arc::new(RwLock::new(frontend_info_mut));
+                        send_info(&*frontend_info.read());
+                    } else {
+                        continue;
+                    }
+                }
+
                 // Run the current behavior
                 guard!(let Some(mode) = config.mode() else { continue; });
 
                 // Stop bot in case of death
                 let is_alive = image_analyzer.client_stats.is_alive();
+
+                // Share our state when leading other instances
+                if let Some(bus) = instance_bus.as_ref() {
+                    let (target_offset, is_mounted) = match mode {
+                        BotMode::Farming => (
+                            farming_behavior.target_offset(&image_analyzer),
+                            farming_behavior.is_mounted(),
+                        ),
+                        BotMode::AutoShout => (
+                            shout_behavior.target_offset(&image_analyzer),
+                            shout_behavior.is_mounted(),
+                        ),
+                        BotMode::Support => (
+                            support_behavior.target_offset(&image_analyzer),
+                            support_behavior.is_mounted(),
+                        ),
+                    };
+                    leader_reporter.report(
+                        bus,
+                        config.bus_config(),
+                        &image_analyzer.client_stats,
+                        is_alive,
+                        target_offset,
+                        is_mounted,
+                    );
+                }
+
+                // Check whether the session reached one of its goals
+                session_monitor.update(&frontend_info_mut, is_alive);
+                if let Some(condition) =
+                    session_monitor.check(config.session_config(), &frontend_info_mut)
+                {
+                    slog::info!(logger, "Stop condition reached"; "trigger" => condition.trigger().to_string(), "action" => condition.action().to_string());
+                    pending_stop_action = Some(condition.action().clone());
+                }
+
                 if !is_alive {
                     frontend_info_mut.set_is_alive(false);
                     frontend_info = Arc::new(RwLock::new(frontend_info_mut));
@@ -248,6 +831,23 @@ fn start_bot(state: tauri::State<AppState>, app_handle: tauri::AppHandle) {
                         );
                     }
                 }
+
+                // Report state changes
+                let transitions = match mode {
+                    BotMode::Farming => farming_behavior.take_transitions(),
+                    BotMode::AutoShout => shout_behavior.take_transitions(),
+                    BotMode::Support => support_behavior.take_transitions(),
+                };
+                if !transitions.is_empty() {
+                    for transition in transitions.iter() {
+                        slog::debug!(logger, "State changed"; "from" => transition.from, "to" => transition.to, "reason" => transition.reason);
+                    }
+                    drop(
+                        app_handle
+                            .emit_all(&client_event(&label, "state_transitions_s2c"), &transitions)
+                            as Result<(), _>,
+                    );
+                }
                 frontend_info = Arc::new(RwLock::new(frontend_info_mut));
                 // Send infos to frontend
                 send_info(&*frontend_info.read());
@@ -256,5 +856,10 @@ fn start_bot(state: tauri::State<AppState>, app_handle: tauri::AppHandle) {
             // Update last mode
             last_mode = config.mode();
         }
+
+        for handler in event_handlers {
+            app_handle.unlisten(handler);
+        }
+        app_handle.state::<AppState>().clients.lock().remove(&label);
     });
 }
@@ -1,10 +1,19 @@
+mod dead_reckoning;
 mod movement_accessor;
 mod movement_coordinator;
+mod movement_executor;
+mod movement_recorder;
+mod movement_script;
 
+pub use self::dead_reckoning::{DeadReckoning, TURN_RATE};
 pub use self::movement_accessor::MovementAccessor;
 pub use self::movement_coordinator::{
     ActionDuration, Movement, MovementCoordinator, MovementDirection, RotationDirection,
 };
+pub use self::movement_executor::MovementHandle;
+pub use self::movement_recorder::MovementRecorder;
+pub use self::movement_script::{Script, ScriptError};
+use self::movement_executor::{MovementExecutor, Step};
 
 pub mod prelude {
     pub use super::{
@@ -21,3 +30,12 @@ macro_rules! play {
         });
     }};
 }
+
+/// Like `play!`, but returns a handle instead of waiting for the movements to be over
+#[macro_export]
+macro_rules! play_async {
+    ($scheduler:expr => [ $($movement:expr,)+ $(,)? ]) => {{
+        let scheduler: &MovementAccessor = &$scheduler;
+        scheduler.start(|coordinator| coordinator.start([$($movement),+]))
+    }};
+}
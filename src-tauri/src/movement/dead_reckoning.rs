@@ -0,0 +1,219 @@
+use std::{f32::consts::PI, time::Instant};
+
+/// Approximate turn rate of the character while a rotation key is held, in radians per millisecond.
+pub const TURN_RATE: f32 = PI / 1000.0;
+
+/// Approximate walking time per pixel of screen distance to a clicked point. Rough, the
+/// perspective makes far points count for less than they should.
+const CLICK_MOVE_RATE: f32 = 6.0;
+
+/// Estimates the displacement of the character since the last anchor by
+/// integrating the movement keys that were held down.
+///
+/// Distances are expressed in milliseconds of forward walking.
+#[derive(Debug, Clone)]
+pub struct DeadReckoning {
+    x: f32,
+    y: f32,
+    heading: f32,
+    forward: bool,
+    backward: bool,
+    turn_left: bool,
+    turn_right: bool,
+    last_update: Instant,
+}
+
+impl Default for DeadReckoning {
+    fn default() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            heading: 0.0,
+            forward: false,
+            backward: false,
+            turn_left: false,
+            turn_right: false,
+            last_update: Instant::now(),
+        }
+    }
+}
+
+impl DeadReckoning {
+    /// Set the current position as the new anchor.
+    pub fn reset(&mut self) {
+        self.advance();
+        self.x = 0.0;
+        self.y = 0.0;
+        self.heading = 0.0;
+    }
+
+    pub fn key_down(&mut self, key: &str) {
+        self.advance();
+        self.set_key(key, true);
+    }
+
+    pub fn key_up(&mut self, key: &str) {
+        self.advance();
+        self.set_key(key, false);
+    }
+
+    fn set_key(&mut self, key: &str, held: bool) {
+        match key {
+            "W" => self.forward = held,
+            "S" => self.backward = held,
+            "A" | "Left" => self.turn_left = held,
+            "D" | "Right" => self.turn_right = held,
+            _ => {}
+        }
+    }
+
+    /// Account for the character walking to a point clicked at `dx`, `dy` pixels from it on
+    /// screen. It turns to face the point first, up on screen being ahead of it.
+    pub fn click_move(&mut self, dx: f32, dy: f32) {
+        self.advance();
+        self.heading = normalize_angle(self.heading + dx.atan2(-dy));
+        let distance = (dx.powi(2) + dy.powi(2)).sqrt() * CLICK_MOVE_RATE;
+        self.x += self.heading.cos() * distance;
+        self.y += self.heading.sin() * distance;
+    }
+
+    /// Integrate held keys up to now.
+    fn advance(&mut self) {
+        let elapsed = self.last_update.elapsed().as_millis() as f32;
+        self.last_update = Instant::now();
+        self.advance_by(elapsed);
+    }
+
+    /// Integrate held keys over `elapsed` milliseconds.
+    fn advance_by(&mut self, elapsed: f32) {
+        let turn = match (self.turn_left, self.turn_right) {
+            (true, false) => -TURN_RATE * elapsed,
+            (false, true) => TURN_RATE * elapsed,
+            _ => 0.0,
+        };
+        let speed = match (self.forward, self.backward) {
+            (true, false) => 1.0,
+            (false, true) => -1.0,
+            _ => 0.0,
+        };
+
+        // Use the mean heading over the interval so arcs are approximated reasonably
+        let mean_heading = self.heading + turn / 2.0;
+        self.x += mean_heading.cos() * speed * elapsed;
+        self.y += mean_heading.sin() * speed * elapsed;
+        self.heading = normalize_angle(self.heading + turn);
+    }
+
+    /// Distance to the anchor.
+    pub fn distance(&mut self) -> f32 {
+        self.advance();
+        (self.x.powi(2) + self.y.powi(2)).sqrt()
+    }
+
+    /// Angle the character has to turn to face the anchor. Positive values mean turning right.
+    pub fn turn_to_anchor(&mut self) -> f32 {
+        self.advance();
+        let target_heading = (-self.y).atan2(-self.x);
+        normalize_angle(target_heading - self.heading)
+    }
+
+    /// Angle the character has to turn to face `heading`, given relative to the heading it had at
+    /// the anchor. Positive values mean turning right.
+    pub fn turn_to(&mut self, heading: f32) -> f32 {
+        self.advance();
+        normalize_angle(heading - self.heading)
+    }
+}
+
+/// Normalize an angle into `[-PI, PI]`.
+fn normalize_angle(angle: f32) -> f32 {
+    let mut angle = angle % (2.0 * PI);
+    if angle > PI {
+        angle -= 2.0 * PI;
+    } else if angle < -PI {
+        angle += 2.0 * PI;
+    }
+    angle
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::{normalize_angle, DeadReckoning, CLICK_MOVE_RATE};
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-2,
+            "{} isn't close to {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_walk_and_turn() {
+        let mut reckoning = DeadReckoning::default();
+        reckoning.set_key("W", true);
+        reckoning.advance_by(1000.0);
+        reckoning.set_key("W", false);
+        assert_close(reckoning.x, 1000.0);
+        assert_close(reckoning.y, 0.0);
+
+        // Quarter turn to the right, then walk back
+        reckoning.set_key("D", true);
+        reckoning.advance_by(500.0);
+        reckoning.set_key("D", false);
+        assert_close(reckoning.heading, PI / 2.0);
+        reckoning.set_key("S", true);
+        reckoning.advance_by(400.0);
+        reckoning.set_key("S", false);
+        assert_close(reckoning.x, 1000.0);
+        assert_close(reckoning.y, -400.0);
+
+        // Turning while walking follows an arc
+        let mut reckoning = DeadReckoning::default();
+        reckoning.set_key("W", true);
+        reckoning.set_key("A", true);
+        reckoning.advance_by(1000.0);
+        assert_close(reckoning.heading, -PI);
+        assert_close(reckoning.x, 0.0);
+        assert_close(reckoning.y, -1000.0);
+    }
+
+    #[test]
+    fn test_click_move() {
+        let mut reckoning = DeadReckoning::default();
+        // Straight ahead
+        reckoning.click_move(0.0, -100.0);
+        assert_close(reckoning.heading, 0.0);
+        assert_close(reckoning.x, 100.0 * CLICK_MOVE_RATE);
+        assert_close(reckoning.y, 0.0);
+
+        // To the right, the character turns to face it
+        reckoning.click_move(50.0, 0.0);
+        assert_close(reckoning.heading, PI / 2.0);
+        assert_close(reckoning.x, 100.0 * CLICK_MOVE_RATE);
+        assert_close(reckoning.y, 50.0 * CLICK_MOVE_RATE);
+    }
+
+    #[test]
+    fn test_turn_to() {
+        let mut reckoning = DeadReckoning::default();
+        reckoning.set_key("D", true);
+        reckoning.advance_by(250.0);
+        reckoning.set_key("D", false);
+        // The same heading is reached however the character turned before
+        assert_close(reckoning.turn_to(PI / 2.0), PI / 4.0);
+        assert_close(reckoning.turn_to(0.0), -PI / 4.0);
+        assert_close(reckoning.turn_to(-PI), 3.0 * PI / 4.0);
+    }
+
+    #[test]
+    fn test_normalize_angle() {
+        assert_close(normalize_angle(3.0 * PI / 2.0), -PI / 2.0);
+        assert_close(normalize_angle(-3.0 * PI / 2.0), PI / 2.0);
+        assert_close(normalize_angle(9.0 * PI / 2.0), PI / 2.0);
+        assert_close(normalize_angle(0.5), 0.5);
+    }
+}
@@ -1,18 +1,20 @@
+use std::sync::Arc;
+
 use parking_lot::Mutex;
-use tauri::Window;
 
 //use crate::platform::PlatformAccessor;
+use crate::platform::InputSink;
 
-use super::MovementCoordinator;
+use super::{MovementCoordinator, MovementHandle, Script, ScriptError};
 
 pub struct MovementAccessor /*<'a>*/ {
     coordinator: Mutex<MovementCoordinator /*<'a>*/>,
 }
 
 impl<'a> MovementAccessor /*<'a>*/ {
-    pub fn new(window: Window/*platform: &'a PlatformAccessor<'a>*/) -> Self {
+    pub fn new<I: InputSink>(input: Arc<I>/*platform: &'a PlatformAccessor<'a>*/) -> Self {
         Self {
-            coordinator: Mutex::new(MovementCoordinator::new(window/*platform*/)),
+            coordinator: Mutex::new(MovementCoordinator::new(input/*platform*/)),
         }
     }
 
@@ -23,4 +25,65 @@ impl<'a> MovementAccessor /*<'a>*/ {
         let mut coordinator = self.coordinator.lock();
         func(&mut coordinator);
     }
+
+    /// Run a function queuing movements without waiting for them
+    pub fn start<F>(&self, func: F) -> MovementHandle
+    where
+        F: Fn(&mut MovementCoordinator) -> MovementHandle,
+    {
+        let mut coordinator = self.coordinator.lock();
+        func(&mut coordinator)
+    }
+
+    /// Queue a movement script without waiting for it
+    pub fn start_script(
+        &self,
+        script: &Script,
+        label: Option<&str>,
+    ) -> Result<MovementHandle, ScriptError> {
+        self.coordinator.lock().start_script(script, label)
+    }
+
+    /// Stop every queued or running movement
+    pub fn cancel_all(&self) {
+        self.coordinator.lock().cancel_all();
+    }
+
+    /// Back off and walk around whatever is blocking the way, turning to a random side
+    pub fn unstick(&self) {
+        use super::prelude::*;
+
+        let rotation_key = if rand::random() { "A" } else { "D" };
+        self.schedule(|coordinator| {
+            coordinator.play([
+                HoldKeyFor("S", dur::Fixed(300)),
+                HoldKeyFor(rotation_key, dur::Random(300..600)),
+                HoldKeys(vec!["W", "Space"]),
+                Wait(dur::Fixed(800)),
+                ReleaseKeys(vec!["Space", "W"]),
+            ]);
+        });
+    }
+
+    /// Set the current position as home anchor.
+    pub fn reset_anchor(&self) {
+        self.coordinator.lock().dead_reckoning().reset();
+    }
+
+    /// Account for the character walking to a point clicked at `dx`, `dy` pixels from it.
+    pub fn click_move(&self, dx: f32, dy: f32) {
+        self.coordinator.lock().dead_reckoning().click_move(dx, dy);
+    }
+
+    /// Get the angle needed to face `heading`, relative to the heading at the home anchor.
+    pub fn turn_to(&self, heading: f32) -> f32 {
+        self.coordinator.lock().dead_reckoning().turn_to(heading)
+    }
+
+    /// Get the estimated distance to the home anchor and the angle needed to face it.
+    pub fn anchor_offset(&self) -> (f32, f32) {
+        let mut coordinator = self.coordinator.lock();
+        let mut dead_reckoning = coordinator.dead_reckoning();
+        (dead_reckoning.distance(), dead_reckoning.turn_to_anchor())
+    }
 }
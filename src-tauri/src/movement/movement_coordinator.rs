@@ -1,11 +1,10 @@
-use std::{ops::Range, thread, time::Duration};
+use std::{ops::Range, sync::Arc, time::Duration};
 
+use parking_lot::{Mutex, MutexGuard};
 use rand::Rng;
-use tauri::Window;
-use crate::platform::{
-    eval_send_message, /* , PlatformAccessor*/
-    KeyMode, eval_send_key,
-};
+use crate::platform::{InputSink, KeyMode};
+
+use super::{DeadReckoning, MovementExecutor, MovementHandle, Script, ScriptError, Step};
 
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy)]
@@ -58,19 +57,26 @@ pub enum Movement<'a> {
 
 pub struct MovementCoordinator {
     rng: rand::rngs::ThreadRng,
-    window: Window,
+    dead_reckoning: Arc<Mutex<DeadReckoning>>,
+    executor: MovementExecutor,
 }
 
 impl<'a> MovementCoordinator {
-    pub fn new(window: Window) -> Self {
+    pub fn new<I: InputSink>(input: Arc<I>) -> Self {
         let rng = rand::thread_rng();
+        let dead_reckoning = Arc::new(Mutex::new(DeadReckoning::default()));
 
         Self {
             rng, /*, platform */
-            window
+            executor: MovementExecutor::new(input, dead_reckoning.clone()),
+            dead_reckoning,
         }
     }
 
+    pub fn dead_reckoning(&mut self) -> MutexGuard<'_, DeadReckoning> {
+        self.dead_reckoning.lock()
+    }
+
     // Wrapper functions
 
   /*   pub fn with_probability<F>(&mut self, probability: f64, func: F)
@@ -84,21 +90,56 @@ impl<'a> MovementCoordinator {
 
     // Movement functions
 
+    /// Play movements and wait for them to be over
     pub fn play<M>(&mut self, movements: M)
     where
         M: AsRef<[Movement<'a>]>,
     {
+        self.start(movements).wait();
+    }
+
+    /// Queue movements on the executor and return right away
+    pub fn start<M>(&mut self, movements: M) -> MovementHandle
+    where
+        M: AsRef<[Movement<'a>]>,
+    {
+        let mut steps = vec![];
         for movement in movements.as_ref() {
-            self.play_single(movement.clone());
+            self.compile(movement, &mut steps);
         }
+        self.executor.queue(steps)
+    }
+
+    /// Queue the movements of a script, starting at `label` if given
+    pub fn start_script(
+        &mut self,
+        script: &Script,
+        label: Option<&str>,
+    ) -> Result<MovementHandle, ScriptError> {
+        Ok(self.start(script.movements(label)?))
+    }
+
+    /// Cancel every movement that is queued or being played
+    pub fn cancel_all(&self) {
+        self.executor.cancel_all();
+    }
+
+    fn key(key: &str, mode: KeyMode) -> Step {
+        Step::Key(key.to_string(), mode)
     }
 
-    fn play_single(&mut self, movement: Movement) {
+    /// Hold a key for a while
+    fn hold_for(&mut self, key: &str, duration: &ActionDuration, steps: &mut Vec<Step>) {
+        steps.push(Self::key(key, KeyMode::Hold));
+        steps.push(Step::Sleep(duration.to_duration(&mut self.rng)));
+        steps.push(Self::key(key, KeyMode::Release));
+    }
+
+    /// Turn a movement into steps, resolving random parts
+    fn compile(&mut self, movement: &Movement, steps: &mut Vec<Step>) {
         match movement {
             Movement::Jump => {
-                eval_send_key(&self.window, "Space", KeyMode::Hold);
-                std::thread::sleep(std::time::Duration::from_millis(500));
-                eval_send_key(&self.window, "Space", KeyMode::Release);
+                self.hold_for("Space", &ActionDuration::Fixed(500), steps);
             }
             Movement::Move(direction, duration) => {
                 let key = match direction {
@@ -112,9 +153,7 @@ impl<'a> MovementCoordinator {
                         }
                     }
                 };
-                eval_send_key(&self.window, key, KeyMode::Hold);
-                thread::sleep(duration.to_duration(&mut self.rng));
-                eval_send_key(&self.window, key, KeyMode::Release);
+                self.hold_for(key, duration, steps);
             }
             Movement::Rotate(direction, duration) => {
                 let key = match direction {
@@ -128,41 +167,29 @@ impl<'a> MovementCoordinator {
                         }
                     }
                 };
-                eval_send_key(&self.window, key, KeyMode::Hold);
-                thread::sleep(duration.to_duration(&mut self.rng));
-                eval_send_key(&self.window, key, KeyMode::Release);
-            }
-            Movement::Wait(duration) => thread::sleep(duration.to_duration(&mut self.rng)),
-            Movement::Type(text) => {
-                eval_send_message(&self.window,&text);
-            }
-            Movement::PressKey(key) => {
-                eval_send_key(&self.window, key, KeyMode::Press);
-            }
-            Movement::HoldKeyFor(key, duration) => {
-                eval_send_key(&self.window, key, KeyMode::Hold);
-                thread::sleep(duration.to_duration(&mut self.rng));
-                eval_send_key(&self.window, key, KeyMode::Release);
-            }
-            Movement::HoldKey(key) => {
-                eval_send_key(&self.window, key, KeyMode::Hold);
+                self.hold_for(key, duration, steps);
             }
+            Movement::Wait(duration) => steps.push(Step::Sleep(duration.to_duration(&mut self.rng))),
+            Movement::Type(text) => steps.push(Step::Type(text.clone())),
+            Movement::PressKey(key) => steps.push(Self::key(key, KeyMode::Press)),
+            Movement::HoldKeyFor(key, duration) => self.hold_for(key, duration, steps),
+            Movement::HoldKey(key) => steps.push(Self::key(key, KeyMode::Hold)),
             Movement::HoldKeys(keys) => {
                 for key in keys {
-                    eval_send_key(&self.window, key, KeyMode::Hold);
+                    steps.push(Self::key(key, KeyMode::Hold));
                 }
             }
-            Movement::ReleaseKey(key) => {
-                eval_send_key(&self.window, key, KeyMode::Release);
-            }
+            Movement::ReleaseKey(key) => steps.push(Self::key(key, KeyMode::Release)),
             Movement::ReleaseKeys(keys) => {
                 for key in keys {
-                    eval_send_key(&self.window, key, KeyMode::Release);
+                    steps.push(Self::key(key, KeyMode::Release));
                 }
             }
             Movement::Repeat(times, movements) => {
-                for _ in 0..times {
-                    self.play(&movements);
+                for _ in 0..*times {
+                    for movement in movements {
+                        self.compile(movement, steps);
+                    }
                 }
             }
         }
@@ -0,0 +1,192 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        mpsc::{channel, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use parking_lot::{Condvar, Mutex};
+
+use super::DeadReckoning;
+use crate::platform::{InputError, InputSink, KeyMode};
+
+/// A single low level action, as compiled from movements
+#[derive(Debug, Clone)]
+pub enum Step {
+    Key(String, KeyMode),
+    Sleep(Duration),
+    Type(String),
+}
+
+#[derive(Debug, Default)]
+struct JobState {
+    cancelled: Mutex<bool>,
+    cancel_signal: Condvar,
+    done: Mutex<bool>,
+    done_signal: Condvar,
+    error: Mutex<Option<InputError>>,
+}
+
+impl JobState {
+    fn is_cancelled(&self) -> bool {
+        *self.cancelled.lock()
+    }
+
+    /// Sleep for the given duration, returns early with `false` when cancelled
+    fn sleep(&self, duration: Duration) -> bool {
+        let deadline = Instant::now() + duration;
+        let mut cancelled = self.cancelled.lock();
+        while !*cancelled {
+            if self
+                .cancel_signal
+                .wait_until(&mut cancelled, deadline)
+                .timed_out()
+            {
+                break;
+            }
+        }
+        !*cancelled
+    }
+
+    fn finish(&self) {
+        *self.done.lock() = true;
+        self.done_signal.notify_all();
+    }
+}
+
+/// Handle to movements queued on the executor
+#[derive(Debug, Clone)]
+pub struct MovementHandle {
+    state: Arc<JobState>,
+}
+
+impl MovementHandle {
+    /// Block until the movements finished or were cancelled
+    pub fn wait(&self) {
+        let mut done = self.state.done.lock();
+        while !*done {
+            self.state.done_signal.wait(&mut done);
+        }
+    }
+
+    /// Stop the movements as soon as possible, releasing held keys
+    pub fn cancel(&self) {
+        *self.state.cancelled.lock() = true;
+        self.state.cancel_signal.notify_all();
+    }
+
+    pub fn is_done(&self) -> bool {
+        *self.state.done.lock()
+    }
+
+    /// Input failure that stopped the movements early
+    pub fn error(&self) -> Option<InputError> {
+        self.state.error.lock().clone()
+    }
+}
+
+struct Job {
+    steps: Vec<Step>,
+    state: Arc<JobState>,
+}
+
+/// Plays movements on a dedicated thread, one job after another, so the caller doesn't have
+/// to wait for them.
+pub struct MovementExecutor {
+    sender: Sender<Job>,
+    pending: Mutex<Vec<MovementHandle>>,
+}
+
+impl MovementExecutor {
+    pub fn new<I: InputSink>(input: Arc<I>, dead_reckoning: Arc<Mutex<DeadReckoning>>) -> Self {
+        let (sender, receiver) = channel::<Job>();
+        thread::spawn(move || {
+            for job in receiver {
+                Self::run(&*input, &dead_reckoning, &job);
+                job.state.finish();
+            }
+        });
+        Self {
+            sender,
+            pending: Mutex::new(vec![]),
+        }
+    }
+
+    fn send_key<I: InputSink>(
+        input: &I,
+        dead_reckoning: &Mutex<DeadReckoning>,
+        key: &str,
+        mode: KeyMode,
+    ) -> Result<(), InputError> {
+        match mode {
+            KeyMode::Hold => dead_reckoning.lock().key_down(key),
+            KeyMode::Release => dead_reckoning.lock().key_up(key),
+            KeyMode::Press => {}
+        }
+        input.send_key(key, mode)
+    }
+
+    fn run<I: InputSink>(input: &I, dead_reckoning: &Mutex<DeadReckoning>, job: &Job) {
+        let mut held_keys = HashSet::new();
+        for step in job.steps.iter() {
+            if job.state.is_cancelled() {
+                break;
+            }
+            let result = match step {
+                Step::Key(key, mode) => {
+                    match mode {
+                        KeyMode::Hold => held_keys.insert(key.clone()),
+                        KeyMode::Release => held_keys.remove(key),
+                        KeyMode::Press => false,
+                    };
+                    Self::send_key(input, dead_reckoning, key, *mode)
+                }
+                Step::Sleep(duration) => {
+                    job.state.sleep(*duration);
+                    Ok(())
+                }
+                Step::Type(text) => input.send_message(text),
+            };
+
+            // Going on after a failed step would play something else than what was asked
+            if let Err(err) = result {
+                *job.state.error.lock() = Some(err);
+                break;
+            }
+        }
+
+        // Don't leave the character walking after a cancellation
+        for key in held_keys {
+            drop(Self::send_key(input, dead_reckoning, &key, KeyMode::Release));
+        }
+    }
+
+    /// Queue steps, they start once previously queued jobs are over
+    pub fn queue(&self, steps: Vec<Step>) -> MovementHandle {
+        let handle = MovementHandle {
+            state: Arc::new(JobState::default()),
+        };
+        let job = Job {
+            steps,
+            state: handle.state.clone(),
+        };
+        if self.sender.send(job).is_err() {
+            handle.state.finish();
+        }
+
+        let mut pending = self.pending.lock();
+        pending.retain(|handle| !handle.is_done());
+        pending.push(handle.clone());
+        handle
+    }
+
+    /// Cancel every queued or running job
+    pub fn cancel_all(&self) {
+        for handle in self.pending.lock().drain(..) {
+            handle.cancel();
+        }
+    }
+}
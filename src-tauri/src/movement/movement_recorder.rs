@@ -0,0 +1,269 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use slog::Logger;
+use tauri::{AppHandle, EventHandler, Manager, Window};
+
+use super::{ActionDuration, Movement, Script};
+use crate::ipc::client_event;
+
+/// Pauses shorter than this are not worth a `Wait`
+const MIN_WAIT: u64 = 20;
+
+/// Why recording is refused while the bot sends input through X11
+const BOT_INPUT_TRUSTED: &str =
+    "the bot is running with X11 input, its keys can't be told apart from yours. Stop it first.";
+
+/// Forwards the keys pressed by the player as `recorded_input` events. Synthetic events sent by
+/// the bot itself through the page are not trusted and therefore ignored, unlike X11 ones.
+const RECORDER_JS: &str = "if(!window.neuzRecorder){const send=(e,pressed)=>{if(!e.isTrusted||e.repeat||['INPUT','TEXTAREA'].includes(e.target.nodeName))return;window.__TAURI_INVOKE__('tauri',{__tauriModule:'Event',message:{cmd:'emit',event:'recorded_input',payload:JSON.stringify({key:e.key,pressed,time:performance.now()})}})};window.neuzRecorder={down:e=>send(e,true),up:e=>send(e,false)};document.addEventListener('keydown',window.neuzRecorder.down,true);document.addEventListener('keyup',window.neuzRecorder.up,true)}";
+const RECORDER_STOP_JS: &str = "if(window.neuzRecorder){document.removeEventListener('keydown',window.neuzRecorder.down,true);document.removeEventListener('keyup',window.neuzRecorder.up,true);delete window.neuzRecorder}";
+
+/// A key going down or up, `time` in milliseconds as measured by the page
+#[derive(Debug, Clone, Deserialize)]
+pub struct InputEvent {
+    pub key: String,
+    pub pressed: bool,
+    pub time: f64,
+}
+
+/// Outcome of a recording, sent to the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingResult {
+    name: String,
+    error: Option<String>,
+}
+
+/// Records the player's keyboard input and saves it as a movement script.
+///
+/// Recording starts with a `record_movement_start` event and ends with a `record_movement_stop`
+/// event carrying the name of the script to write, both scoped to the recorded window.
+pub struct MovementRecorder {
+    events: Arc<Mutex<Option<Vec<InputEvent>>>>,
+    /// Whether the input sent by the bot is trusted by the page, and would be recorded
+    bot_input_trusted: Arc<AtomicBool>,
+    app_handle: AppHandle,
+    window: Window,
+    /// Global listeners, removed when the recorder is dropped
+    handlers: Vec<EventHandler>,
+    /// Listener of the page's input, removed when the recorder is dropped
+    input_handler: EventHandler,
+}
+
+impl MovementRecorder {
+    pub fn new(app_handle: &AppHandle, window: &Window, logger: &Logger) -> Self {
+        // `None` while not recording
+        let events: Arc<Mutex<Option<Vec<InputEvent>>>> = Arc::new(Mutex::new(None));
+        let bot_input_trusted = Arc::new(AtomicBool::new(false));
+
+        let local_events = events.clone();
+        let input_handler = window.listen("recorded_input", move |e| {
+            if let Some(event) = e
+                .payload()
+                .and_then(|payload| serde_json::from_str::<InputEvent>(payload).ok())
+            {
+                if let Some(events) = local_events.lock().as_mut() {
+                    events.push(InputEvent {
+                        key: normalize_key(&event.key),
+                        ..event
+                    });
+                }
+            }
+        });
+
+        let local_events = events.clone();
+        let local_bot_input_trusted = bot_input_trusted.clone();
+        let local_window = window.clone();
+        let local_logger = logger.clone();
+        let local_app_handle = app_handle.clone();
+        let start_event = client_event(window.label(), "record_movement_start");
+        let recorded_event = client_event(window.label(), "movement_recorded_s2c");
+        let start_handler = app_handle.listen_global(start_event, move |_| {
+            if local_bot_input_trusted.load(Ordering::Relaxed) {
+                slog::warn!(local_logger, "Can't record movement"; "error" => BOT_INPUT_TRUSTED);
+                drop(local_app_handle.emit_all(
+                    &recorded_event,
+                    RecordingResult {
+                        name: String::new(),
+                        error: Some(BOT_INPUT_TRUSTED.to_string()),
+                    },
+                ));
+                return;
+            }
+            slog::info!(local_logger, "Recording movement");
+            *local_events.lock() = Some(vec![]);
+            drop(local_window.eval(RECORDER_JS));
+        });
+
+        let local_events = events.clone();
+        let local_window = window.clone();
+        let local_logger = logger.clone();
+        let local_app_handle = app_handle.clone();
+        let stop_event = client_event(window.label(), "record_movement_stop");
+        let recorded_event = client_event(window.label(), "movement_recorded_s2c");
+        let stop_handler = app_handle.listen_global(stop_event, move |e| {
+            drop(local_window.eval(RECORDER_STOP_JS));
+            guard::guard!(let Some(events) = local_events.lock().take() else { return });
+            let name = e
+                .payload()
+                .and_then(|payload| serde_json::from_str::<String>(payload).ok())
+                .unwrap_or_default();
+
+            let movements = record_to_movements(&events);
+            let error = Script::save_named(&name, &movements)
+                .err()
+                .map(|err| err.to_string());
+            match &error {
+                None => {
+                    slog::info!(local_logger, "Movement recorded"; "name" => &name, "movements" => movements.len())
+                }
+                Some(error) => {
+                    slog::warn!(local_logger, "Failed to save recorded movement"; "name" => &name, "error" => error)
+                }
+            }
+            drop(local_app_handle.emit_all(&recorded_event, RecordingResult { name, error }));
+        });
+
+        Self {
+            events,
+            bot_input_trusted,
+            app_handle: app_handle.clone(),
+            window: window.clone(),
+            handlers: vec![start_handler, stop_handler],
+            input_handler,
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.events.lock().is_some()
+    }
+
+    /// Refuse to start recording while the bot sends input the page can't tell from the player's.
+    /// Once recording, the bot stays idle until it ends.
+    pub fn set_bot_input_trusted(&self, trusted: bool) {
+        self.bot_input_trusted.store(trusted, Ordering::Relaxed);
+    }
+}
+
+impl Drop for MovementRecorder {
+    fn drop(&mut self) {
+        for handler in self.handlers.drain(..) {
+            self.app_handle.unlisten(handler);
+        }
+        self.window.unlisten(self.input_handler);
+    }
+}
+
+/// Name the bot uses when sending the key reported by the page
+fn normalize_key(key: &str) -> String {
+    match key {
+        " " => "Space".to_string(),
+        _ if key.starts_with("Arrow") => key.trim_start_matches("Arrow").to_string(),
+        _ if key.chars().count() == 1 => key.to_uppercase(),
+        _ => key.to_string(),
+    }
+}
+
+/// Convert recorded input into movements. A key released before anything else happens becomes a
+/// `HoldKeyFor`, other presses are split into holds and releases, and pauses become `Wait`s.
+pub fn record_to_movements(events: &[InputEvent]) -> Vec<Movement<'_>> {
+    let mut movements: Vec<Movement> = vec![];
+    let mut held_keys: Vec<&str> = vec![];
+    let mut last_time: Option<f64> = None;
+    let mut index = 0;
+
+    while index < events.len() {
+        let event = &events[index];
+        let key = event.key.as_str();
+        index += 1;
+
+        // Ignore releases of keys pressed before the recording started
+        if event.pressed == held_keys.contains(&key) {
+            continue;
+        }
+
+        if let Some(last_time) = last_time {
+            let pause = (event.time - last_time).max(0.0) as u64;
+            if pause >= MIN_WAIT {
+                movements.push(Movement::Wait(ActionDuration::Fixed(pause)));
+            }
+        }
+        last_time = Some(event.time);
+
+        if event.pressed {
+            // Released right after, without anything else in between
+            if let Some(next) = events.get(index).filter(|next| !next.pressed && next.key == key) {
+                let duration = (next.time - event.time).max(0.0) as u64;
+                movements.push(Movement::HoldKeyFor(key, ActionDuration::Fixed(duration)));
+                last_time = Some(next.time);
+                index += 1;
+                continue;
+            }
+            held_keys.push(key);
+            match movements.last_mut() {
+                Some(Movement::HoldKey(previous)) => {
+                    let keys = vec![*previous, key];
+                    *movements.last_mut().unwrap() = Movement::HoldKeys(keys);
+                }
+                Some(Movement::HoldKeys(keys)) => keys.push(key),
+                _ => movements.push(Movement::HoldKey(key)),
+            }
+        } else {
+            held_keys.retain(|held| *held != key);
+            match movements.last_mut() {
+                Some(Movement::ReleaseKey(previous)) => {
+                    let keys = vec![*previous, key];
+                    *movements.last_mut().unwrap() = Movement::ReleaseKeys(keys);
+                }
+                Some(Movement::ReleaseKeys(keys)) => keys.push(key),
+                _ => movements.push(Movement::ReleaseKey(key)),
+            }
+        }
+    }
+
+    // Don't leave keys held at the end of the replay
+    if !held_keys.is_empty() {
+        movements.push(Movement::ReleaseKeys(held_keys));
+    }
+    movements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{record_to_movements, InputEvent};
+    use crate::movement::movement_script::to_source;
+
+    fn event(key: &str, pressed: bool, time: f64) -> InputEvent {
+        InputEvent {
+            key: key.to_string(),
+            pressed,
+            time,
+        }
+    }
+
+    #[test]
+    fn test_record_to_movements() {
+        let events = vec![
+            event("W", false, 0.0),
+            event("W", true, 10.0),
+            event("Space", true, 15.0),
+            event("Space", false, 520.0),
+            event("Left", true, 1520.0),
+            event("Left", false, 1720.4),
+            event("D", true, 1730.0),
+        ];
+        assert_eq!(
+            to_source(&record_to_movements(&events)),
+            "hold W\n\
+             hold Space for 505\n\
+             wait 1000\n\
+             hold Left for 200\n\
+             hold D\n\
+             release W D\n"
+        );
+    }
+}
@@ -0,0 +1,575 @@
+//! Movement scripts, a small line based language compiled into [`Movement`]s.
+//!
+//! ```text
+//! # Look around a few times, then walk somewhere else
+//! sub look
+//!     rotate right 100
+//!     wait 200
+//! end
+//!
+//! repeat 4
+//!     call look
+//! end
+//!
+//! label walk
+//! hold W Space
+//! wait 800..1500
+//! release W Space
+//! ```
+//!
+//! Durations are milliseconds, either fixed (`500`, `500ms`) or random (`100..250`).
+//! Available instructions are `jump`, `move forward|backward|random <duration>`,
+//! `rotate left|right|random <duration>`, `press <key>`, `hold <keys...>`,
+//! `hold <key> for <duration>`, `release <keys...>`, `type <text>`, `wait <duration>`,
+//! `repeat <times>` ... `end`, `sub <name>` ... `end`, `call <name>` and `label <name>`.
+//! Labels are entry points: playing a script from a label skips everything written before it.
+//! Repeat counts go up to 10000, and a script may expand to 100000 steps at most.
+
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use super::{ActionDuration, Movement, MovementDirection, RotationDirection};
+use crate::profiles::is_valid_file_name;
+
+/// Directory where movement scripts are looked up, in the working directory
+pub const SCRIPTS_DIR: &str = "scripts";
+
+/// Deepest `repeat` nesting accepted, expanded scripts grow quickly
+const MAX_DEPTH: usize = 8;
+
+/// Largest `repeat` count accepted
+const MAX_REPEAT: u64 = 10_000;
+
+/// Most steps a script may expand to, they're all queued when it's played
+const MAX_STEPS: u64 = 100_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptError {
+    Io(String),
+    Syntax {
+        line: usize,
+        message: String,
+    },
+    UnknownLabel(String),
+    /// The script expands to more than `MAX_STEPS` steps
+    TooLong,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::Io(message) => write!(f, "{}", message),
+            ScriptError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            ScriptError::UnknownLabel(label) => write!(f, "unknown label `{}`", label),
+            ScriptError::TooLong => {
+                write!(f, "the script expands to more than {} steps", MAX_STEPS)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Instruction {
+    Jump,
+    Move(MovementDirection, ActionDuration),
+    Rotate(RotationDirection, ActionDuration),
+    PressKey(String),
+    HoldKeyFor(String, ActionDuration),
+    HoldKeys(Vec<String>),
+    ReleaseKeys(Vec<String>),
+    Repeat(u64, Vec<Instruction>),
+    Type(String),
+    Wait(ActionDuration),
+    /// Sub-routine name and the line of the call
+    Call(String, usize),
+}
+
+enum Block {
+    Repeat(u64),
+    Sub(String),
+}
+
+/// A parsed movement script
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    body: Vec<Instruction>,
+    labels: HashMap<String, usize>,
+    subs: HashMap<String, Vec<Instruction>>,
+}
+
+impl Script {
+    /// Load a script from a file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScriptError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(|err| ScriptError::Io(format!("{}: {}", path.display(), err)))?;
+        Self::parse(&source)
+    }
+
+    /// Load a script by name from the scripts directory
+    pub fn load_named(name: &str) -> Result<Self, ScriptError> {
+        Self::load(named_path(name)?)
+    }
+
+    /// Write movements as a script in the scripts directory
+    pub fn save_named(name: &str, movements: &[Movement]) -> Result<(), ScriptError> {
+        let path = named_path(name)?;
+        let io_error = |err: std::io::Error| ScriptError::Io(format!("{}: {}", name, err));
+        fs::create_dir_all(SCRIPTS_DIR).map_err(io_error)?;
+        fs::write(path, to_source(movements)).map_err(io_error)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, ScriptError> {
+        let mut script = Self::default();
+        // Open blocks with their opening line and the instructions collected so far
+        let mut blocks: Vec<(Block, usize, Vec<Instruction>)> = vec![];
+
+        for (index, raw_line) in source.lines().enumerate() {
+            let line = index + 1;
+            let error = |message: String| ScriptError::Syntax { line, message };
+            let text = match raw_line.find('#') {
+                Some(start) => &raw_line[..start],
+                None => raw_line,
+            }
+            .trim();
+            if text.is_empty() {
+                continue;
+            }
+
+            let (command, rest) = match text.split_once(char::is_whitespace) {
+                Some((command, rest)) => (command, rest.trim()),
+                None => (text, ""),
+            };
+            let args = rest.split_whitespace().collect::<Vec<_>>();
+
+            let instruction = match command.to_lowercase().as_str() {
+                "jump" => {
+                    expect_args(&args, 0).map_err(error)?;
+                    Instruction::Jump
+                }
+                "move" => {
+                    expect_args(&args, 2).map_err(error)?;
+                    let direction = match args[0].to_lowercase().as_str() {
+                        "forward" => MovementDirection::Forward,
+                        "backward" => MovementDirection::Backward,
+                        "random" => MovementDirection::Random,
+                        other => return Err(error(format!("unknown direction `{}`", other))),
+                    };
+                    Instruction::Move(direction, parse_duration(args[1]).map_err(error)?)
+                }
+                "rotate" => {
+                    expect_args(&args, 2).map_err(error)?;
+                    let direction = match args[0].to_lowercase().as_str() {
+                        "left" => RotationDirection::Left,
+                        "right" => RotationDirection::Right,
+                        "random" => RotationDirection::Random,
+                        other => return Err(error(format!("unknown rotation `{}`", other))),
+                    };
+                    Instruction::Rotate(direction, parse_duration(args[1]).map_err(error)?)
+                }
+                "press" => {
+                    expect_args(&args, 1).map_err(error)?;
+                    Instruction::PressKey(args[0].to_string())
+                }
+                "hold" => match args.as_slice() {
+                    [key, "for", duration] => Instruction::HoldKeyFor(
+                        key.to_string(),
+                        parse_duration(duration).map_err(error)?,
+                    ),
+                    [] => return Err(error("expected at least one key".to_string())),
+                    keys => Instruction::HoldKeys(keys.iter().map(|key| key.to_string()).collect()),
+                },
+                "release" => {
+                    if args.is_empty() {
+                        return Err(error("expected at least one key".to_string()));
+                    }
+                    Instruction::ReleaseKeys(args.iter().map(|key| key.to_string()).collect())
+                }
+                "type" => Instruction::Type(rest.to_string()),
+                "wait" => {
+                    expect_args(&args, 1).map_err(error)?;
+                    Instruction::Wait(parse_duration(args[0]).map_err(error)?)
+                }
+                "call" => {
+                    expect_args(&args, 1).map_err(error)?;
+                    Instruction::Call(args[0].to_string(), line)
+                }
+                "repeat" => {
+                    expect_args(&args, 1).map_err(error)?;
+                    let times = args[0]
+                        .parse::<u64>()
+                        .ok()
+                        .filter(|times| *times <= MAX_REPEAT)
+                        .ok_or_else(|| {
+                            error(format!(
+                                "invalid repeat count `{}`, at most {}",
+                                args[0], MAX_REPEAT
+                            ))
+                        })?;
+                    let depth = blocks
+                        .iter()
+                        .filter(|(block, _, _)| matches!(block, Block::Repeat(_)))
+                        .count();
+                    if depth >= MAX_DEPTH {
+                        return Err(error("too many nested repeats".to_string()));
+                    }
+                    blocks.push((Block::Repeat(times), line, vec![]));
+                    continue;
+                }
+                "sub" => {
+                    expect_args(&args, 1).map_err(error)?;
+                    if !blocks.is_empty() {
+                        return Err(error("sub-routines can't be nested".to_string()));
+                    }
+                    if script.subs.contains_key(args[0]) {
+                        return Err(error(format!("sub-routine `{}` defined twice", args[0])));
+                    }
+                    blocks.push((Block::Sub(args[0].to_string()), line, vec![]));
+                    continue;
+                }
+                "label" => {
+                    expect_args(&args, 1).map_err(error)?;
+                    if !blocks.is_empty() {
+                        return Err(error("labels must be outside of blocks".to_string()));
+                    }
+                    if script
+                        .labels
+                        .insert(args[0].to_string(), script.body.len())
+                        .is_some()
+                    {
+                        return Err(error(format!("label `{}` defined twice", args[0])));
+                    }
+                    continue;
+                }
+                "end" => {
+                    expect_args(&args, 0).map_err(error)?;
+                    match blocks.pop() {
+                        Some((Block::Repeat(times), _, body)) => Instruction::Repeat(times, body),
+                        Some((Block::Sub(name), _, body)) => {
+                            script.subs.insert(name, body);
+                            continue;
+                        }
+                        None => return Err(error("`end` without an open block".to_string())),
+                    }
+                }
+                other => return Err(error(format!("unknown instruction `{}`", other))),
+            };
+
+            match blocks.last_mut() {
+                Some((_, _, body)) => body.push(instruction),
+                None => script.body.push(instruction),
+            }
+        }
+
+        if let Some((_, line, _)) = blocks.last() {
+            return Err(ScriptError::Syntax {
+                line: *line,
+                message: "block is never closed with `end`".to_string(),
+            });
+        }
+
+        script.check_calls(&script.body, &mut vec![])?;
+        let mut names = script.subs.keys().collect::<Vec<_>>();
+        names.sort();
+        for name in names {
+            script.check_calls(&script.subs[name], &mut vec![name.as_str()])?;
+        }
+        // Every label starts in the body, playing it whole is the longest
+        if script.step_count(&script.body) > MAX_STEPS {
+            return Err(ScriptError::TooLong);
+        }
+        Ok(script)
+    }
+
+    /// Steps the instructions are compiled into when played, calls were checked before
+    fn step_count(&self, instructions: &[Instruction]) -> u64 {
+        instructions
+            .iter()
+            .map(|instruction| match instruction {
+                // Held, waited and released
+                Instruction::Jump
+                | Instruction::Move(..)
+                | Instruction::Rotate(..)
+                | Instruction::HoldKeyFor(..) => 3,
+                Instruction::HoldKeys(keys) | Instruction::ReleaseKeys(keys) => keys.len() as u64,
+                Instruction::Repeat(times, body) => times.saturating_mul(self.step_count(body)),
+                Instruction::Call(name, _) => {
+                    self.subs.get(name).map_or(0, |body| self.step_count(body))
+                }
+                Instruction::PressKey(_) | Instruction::Type(_) | Instruction::Wait(_) => 1,
+            })
+            .fold(0, u64::saturating_add)
+    }
+
+    /// Make sure every call targets an existing sub-routine and never loops back
+    fn check_calls<'s>(
+        &'s self,
+        instructions: &'s [Instruction],
+        stack: &mut Vec<&'s str>,
+    ) -> Result<(), ScriptError> {
+        for instruction in instructions {
+            match instruction {
+                Instruction::Repeat(_, body) => self.check_calls(body, stack)?,
+                Instruction::Call(name, line) => {
+                    let error = |message: String| ScriptError::Syntax {
+                        line: *line,
+                        message,
+                    };
+                    let body = self
+                        .subs
+                        .get(name)
+                        .ok_or_else(|| error(format!("unknown sub-routine `{}`", name)))?;
+                    if stack.contains(&name.as_str()) {
+                        return Err(error(format!("sub-routine `{}` calls itself", name)));
+                    }
+                    stack.push(name);
+                    self.check_calls(body, stack)?;
+                    stack.pop();
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Movements of the script, starting at `label` if given
+    pub fn movements(&self, label: Option<&str>) -> Result<Vec<Movement<'_>>, ScriptError> {
+        let start = match label {
+            Some(label) => *self
+                .labels
+                .get(label)
+                .ok_or_else(|| ScriptError::UnknownLabel(label.to_string()))?,
+            None => 0,
+        };
+        let mut movements = vec![];
+        self.expand(&self.body[start..], &mut movements);
+        Ok(movements)
+    }
+
+    fn expand<'s>(&'s self, instructions: &'s [Instruction], movements: &mut Vec<Movement<'s>>) {
+        for instruction in instructions {
+            let movement = match instruction {
+                Instruction::Jump => Movement::Jump,
+                Instruction::Move(direction, duration) => Movement::Move(*direction, duration.clone()),
+                Instruction::Rotate(direction, duration) => {
+                    Movement::Rotate(*direction, duration.clone())
+                }
+                Instruction::PressKey(key) => Movement::PressKey(key),
+                Instruction::HoldKeyFor(key, duration) => {
+                    Movement::HoldKeyFor(key, duration.clone())
+                }
+                Instruction::HoldKeys(keys) if keys.len() == 1 => Movement::HoldKey(&keys[0]),
+                Instruction::HoldKeys(keys) => {
+                    Movement::HoldKeys(keys.iter().map(String::as_str).collect())
+                }
+                Instruction::ReleaseKeys(keys) if keys.len() == 1 => Movement::ReleaseKey(&keys[0]),
+                Instruction::ReleaseKeys(keys) => {
+                    Movement::ReleaseKeys(keys.iter().map(String::as_str).collect())
+                }
+                Instruction::Repeat(times, body) => {
+                    let mut inner = vec![];
+                    self.expand(body, &mut inner);
+                    Movement::Repeat(*times, inner)
+                }
+                Instruction::Type(text) => Movement::Type(text.clone()),
+                Instruction::Wait(duration) => Movement::Wait(duration.clone()),
+                Instruction::Call(name, _) => {
+                    // Calls were checked while parsing
+                    if let Some(body) = self.subs.get(name) {
+                        self.expand(body, movements);
+                    }
+                    continue;
+                }
+            };
+            movements.push(movement);
+        }
+    }
+}
+
+/// Path of the script `name` in the scripts directory, named like profiles so it can't leave it
+fn named_path(name: &str) -> Result<PathBuf, ScriptError> {
+    if !is_valid_file_name(name) {
+        return Err(ScriptError::Io(format!(
+            "invalid script name `{}`, use letters, digits, spaces, - and _ but no device name such as CON",
+            name
+        )));
+    }
+    Ok(Path::new(SCRIPTS_DIR).join(name))
+}
+
+/// Write movements back as script source
+pub fn to_source(movements: &[Movement]) -> String {
+    let mut source = String::new();
+    write_source(movements, 0, &mut source);
+    source
+}
+
+fn write_source(movements: &[Movement], depth: usize, source: &mut String) {
+    for movement in movements {
+        let line = match movement {
+            Movement::Jump => "jump".to_string(),
+            Movement::Move(direction, duration) => {
+                let direction = match direction {
+                    MovementDirection::Forward => "forward",
+                    MovementDirection::Backward => "backward",
+                    MovementDirection::Random => "random",
+                };
+                format!("move {} {}", direction, format_duration(duration))
+            }
+            Movement::Rotate(direction, duration) => {
+                let direction = match direction {
+                    RotationDirection::Left => "left",
+                    RotationDirection::Right => "right",
+                    RotationDirection::Random => "random",
+                };
+                format!("rotate {} {}", direction, format_duration(duration))
+            }
+            Movement::PressKey(key) => format!("press {}", key),
+            Movement::HoldKeyFor(key, duration) => {
+                format!("hold {} for {}", key, format_duration(duration))
+            }
+            Movement::HoldKey(key) => format!("hold {}", key),
+            Movement::HoldKeys(keys) => format!("hold {}", keys.join(" ")),
+            Movement::ReleaseKey(key) => format!("release {}", key),
+            Movement::ReleaseKeys(keys) => format!("release {}", keys.join(" ")),
+            Movement::Repeat(times, movements) => {
+                source.push_str(&format!("{}repeat {}\n", "    ".repeat(depth), times));
+                write_source(movements, depth + 1, source);
+                "end".to_string()
+            }
+            Movement::Type(text) => format!("type {}", text),
+            Movement::Wait(duration) => format!("wait {}", format_duration(duration)),
+        };
+        source.push_str(&"    ".repeat(depth));
+        source.push_str(&line);
+        source.push('\n');
+    }
+}
+
+fn format_duration(duration: &ActionDuration) -> String {
+    match duration {
+        ActionDuration::Fixed(ms) => ms.to_string(),
+        ActionDuration::Random(range) => format!("{}..{}", range.start, range.end),
+    }
+}
+
+fn expect_args(args: &[&str], count: usize) -> Result<(), String> {
+    if args.len() == count {
+        Ok(())
+    } else {
+        Err(format!("expected {} argument(s), got {}", count, args.len()))
+    }
+}
+
+fn parse_duration(text: &str) -> Result<ActionDuration, String> {
+    let parse = |value: &str| {
+        value
+            .trim_end_matches("ms")
+            .parse::<u64>()
+            .map_err(|_| format!("invalid duration `{}`", text))
+    };
+    match text.split_once("..") {
+        Some((start, end)) => {
+            let (start, end) = (parse(start)?, parse(end)?);
+            if start >= end {
+                return Err(format!("empty duration range `{}`", text));
+            }
+            Ok(ActionDuration::Random(start..end))
+        }
+        None => Ok(ActionDuration::Fixed(parse(text)?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Movement, Script, ScriptError};
+
+    #[test]
+    fn test_parse_script() {
+        let script = Script::parse(
+            "# comment\n\
+             sub look\n\
+             \trotate right 100\n\
+             \twait 100..200\n\
+             end\n\
+             repeat 2\n\
+             \tcall look\n\
+             end\n\
+             label walk\n\
+             hold W Space\n\
+             release W\n\
+             type hello there\n",
+        )
+        .unwrap();
+
+        let movements = script.movements(None).unwrap();
+        assert_eq!(movements.len(), 4);
+        match &movements[0] {
+            Movement::Repeat(2, inner) => assert_eq!(inner.len(), 2),
+            other => panic!("unexpected movement {:?}", other),
+        }
+        assert!(matches!(movements[2], Movement::ReleaseKey("W")));
+
+        let movements = script.movements(Some("walk")).unwrap();
+        assert!(matches!(&movements[0], Movement::HoldKeys(keys) if keys == &["W", "Space"]));
+        assert!(matches!(&movements[2], Movement::Type(text) if text == "hello there"));
+        assert_eq!(
+            script.movements(Some("nowhere")).unwrap_err(),
+            ScriptError::UnknownLabel("nowhere".to_string())
+        );
+    }
+
+    #[test]
+    fn test_script_errors() {
+        let line_of = |source: &str| match Script::parse(source) {
+            Err(ScriptError::Syntax { line, .. }) => line,
+            other => panic!("expected a syntax error, got {:?}", other),
+        };
+
+        assert_eq!(line_of("jump\nmove sideways 100"), 2);
+        assert_eq!(line_of("wait 200..100"), 1);
+        assert_eq!(line_of("jump\nrepeat 2\njump\n"), 2);
+        assert_eq!(line_of("end"), 1);
+        assert_eq!(line_of("\n\ncall missing"), 3);
+        assert_eq!(line_of("sub a\ncall b\nend\nsub b\ncall a\nend\ncall a"), 5);
+        assert_eq!(line_of("jump\nrepeat 4294967295\njump\nend"), 2);
+    }
+
+    #[test]
+    fn test_script_size() {
+        assert!(Script::parse("repeat 10000\nwait 1\nend").is_ok());
+        // Nested repeats and calls multiply
+        assert_eq!(
+            Script::parse("repeat 1000\nrepeat 1000\nwait 1\nend\nend").unwrap_err(),
+            ScriptError::TooLong
+        );
+        assert_eq!(
+            Script::parse("sub walk\njump\njump\njump\njump\nend\nrepeat 10000\ncall walk\nend")
+                .unwrap_err(),
+            ScriptError::TooLong
+        );
+    }
+
+    #[test]
+    fn test_named_scripts_stay_in_scripts_dir() {
+        for name in [
+            "",
+            "../bot.json",
+            "..\\bot.json",
+            "/etc/passwd",
+            ".hidden",
+            "C:x",
+            "a:b",
+            "CON",
+            "nul",
+            "NUL.txt",
+            "com1",
+        ] {
+            assert!(matches!(Script::load_named(name), Err(ScriptError::Io(_))));
+            assert!(Script::save_named(name, &[]).is_err());
+        }
+    }
+}
@@ -1,6 +1,21 @@
 pub mod shared;
 pub use self::shared::*;
 
+pub mod input_sink;
+pub use self::input_sink::{InputError, InputSink};
+
+mod js_bridge;
+pub use self::js_bridge::{BridgeCommand, JsBridge};
+
+mod input_router;
+pub use self::input_router::InputRouter;
+
+mod canvas_capture;
+pub use self::canvas_capture::CanvasCapture;
+
+mod coordinate_space;
+pub use self::coordinate_space::{CapturePoint, CoordinateSpace, InputPoint};
+
 //
 // Windows
 //
@@ -9,7 +24,7 @@ pub use self::shared::*;
 mod windows;
 
 #[cfg(target_os = "windows")]
-pub use windows::IGNORE_AREA_TOP;
+pub use windows::{CAPTURE_INCLUDES_DECORATIONS, IGNORE_AREA_TOP};
 
 //
 // macOS
@@ -19,7 +34,7 @@ pub use windows::IGNORE_AREA_TOP;
 mod macos;
 
 #[cfg(target_os = "macos")]
-pub use macos::IGNORE_AREA_TOP;
+pub use macos::{CAPTURE_INCLUDES_DECORATIONS, IGNORE_AREA_TOP};
 
 //
 // Linux
@@ -29,4 +44,10 @@ pub use macos::IGNORE_AREA_TOP;
 mod linux;
 
 #[cfg(target_os = "linux")]
-pub use linux::IGNORE_AREA_TOP;
+pub use linux::{CAPTURE_INCLUDES_DECORATIONS, IGNORE_AREA_TOP};
+
+#[cfg(target_os = "linux")]
+mod x11_input;
+
+#[cfg(target_os = "linux")]
+pub use x11_input::X11Input;
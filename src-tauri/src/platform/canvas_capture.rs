@@ -0,0 +1,145 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{sync_channel, SyncSender},
+        Arc,
+    },
+    time::Duration,
+};
+
+use libscreenshot::ImageBuffer;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use tauri::Window;
+
+/// Time to wait for the page to send a frame
+const FRAME_TIMEOUT: Duration = Duration::from_secs(1);
+
+static NEXT_FRAME_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Copies the game canvas into an opaque 2D canvas at its displayed size, so the frame matches
+/// the page coordinates, and sends the raw RGBA pixels base64 encoded as a `canvas_frame` event.
+/// The copy happens in an animation frame, while the WebGL drawing buffer is still valid.
+const CAPTURE_JS: &str = "if(!window.neuzCapture){const reply=(id,frame)=>window.__TAURI_INVOKE__('tauri',{__tauriModule:'Event',message:{cmd:'emit',event:'canvas_frame',payload:JSON.stringify({id,...frame})}});const buffer=document.createElement('canvas');window.neuzCapture=(id)=>requestAnimationFrame(()=>{try{const canvas=document.querySelector('canvas');if(!canvas)throw new Error('canvas not found');const width=canvas.clientWidth,height=canvas.clientHeight;buffer.width=width;buffer.height=height;const context=buffer.getContext('2d');context.fillStyle='#000';context.fillRect(0,0,width,height);context.drawImage(canvas,0,0,width,height);const pixels=context.getImageData(0,0,width,height).data;let binary='';for(let i=0;i<pixels.length;i+=32768)binary+=String.fromCharCode.apply(null,pixels.subarray(i,i+32768));reply(id,{width,height,data:btoa(binary),error:null})}catch(e){reply(id,{width:0,height:0,data:'',error:String(e)})}})}";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureError {
+    /// The script couldn't be run in the page
+    Eval(String),
+    /// The page couldn't read the canvas
+    Script(String),
+    /// No frame came back in time
+    Timeout,
+    /// The frame doesn't hold the announced pixels
+    Decode(String),
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::Eval(message) => write!(f, "eval failed: {}", message),
+            CaptureError::Script(message) => write!(f, "script error: {}", message),
+            CaptureError::Timeout => write!(f, "no frame from the page"),
+            CaptureError::Decode(message) => write!(f, "invalid frame: {}", message),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CanvasFrame {
+    id: u64,
+    width: u32,
+    height: u32,
+    data: String,
+    error: Option<String>,
+}
+
+impl CanvasFrame {
+    fn into_image(self) -> Result<ImageBuffer, CaptureError> {
+        if let Some(error) = self.error {
+            return Err(CaptureError::Script(error));
+        }
+        let pixels =
+            base64::decode(&self.data).map_err(|err| CaptureError::Decode(err.to_string()))?;
+        ImageBuffer::from_raw(self.width, self.height, pixels).ok_or_else(|| {
+            CaptureError::Decode(format!("not a {}x{} image", self.width, self.height))
+        })
+    }
+}
+
+/// Captures frames from the game canvas itself. Unlike window capture it doesn't depend on the
+/// compositor, leaves the window chrome out and still works when the window is covered.
+#[derive(Debug, Clone)]
+pub struct CanvasCapture {
+    window: Window,
+    pending: Arc<Mutex<HashMap<u64, SyncSender<CanvasFrame>>>>,
+}
+
+impl CanvasCapture {
+    pub fn new(window: Window) -> Self {
+        let pending: Arc<Mutex<HashMap<u64, SyncSender<CanvasFrame>>>> = Arc::default();
+
+        let local_pending = pending.clone();
+        window.listen("canvas_frame", move |e| {
+            if let Some(frame) = e
+                .payload()
+                .and_then(|payload| serde_json::from_str::<CanvasFrame>(payload).ok())
+            {
+                if let Some(sender) = local_pending.lock().remove(&frame.id) {
+                    drop(sender.send(frame));
+                }
+            }
+        });
+
+        Self { window, pending }
+    }
+
+    /// Grab the next frame drawn by the game
+    pub fn capture(&self) -> Result<ImageBuffer, CaptureError> {
+        let id = NEXT_FRAME_ID.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = sync_channel(1);
+        self.pending.lock().insert(id, sender);
+
+        let script = format!("{}window.neuzCapture({})", CAPTURE_JS, id);
+        if let Err(err) = self.window.eval(&script) {
+            self.pending.lock().remove(&id);
+            return Err(CaptureError::Eval(err.to_string()));
+        }
+
+        let frame = receiver.recv_timeout(FRAME_TIMEOUT);
+        self.pending.lock().remove(&id);
+        frame.map_err(|_| CaptureError::Timeout)?.into_image()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CanvasFrame, CaptureError};
+
+    #[test]
+    fn test_frame_into_image() {
+        let frame = CanvasFrame {
+            id: 0,
+            width: 2,
+            height: 1,
+            data: base64::encode([255, 0, 0, 255, 0, 0, 255, 255]),
+            error: None,
+        };
+        let image = frame.into_image().unwrap();
+        assert_eq!(image.get_pixel(1, 0).0, [0, 0, 255, 255]);
+
+        let frame = CanvasFrame {
+            id: 0,
+            width: 2,
+            height: 2,
+            data: base64::encode([255, 0, 0, 255]),
+            error: None,
+        };
+        assert_eq!(
+            frame.into_image().unwrap_err(),
+            CaptureError::Decode("not a 2x2 image".to_string())
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+use serde::Deserialize;
+use tauri::Window;
+
+use super::{BridgeCommand, InputError, JsBridge, CAPTURE_INCLUDES_DECORATIONS};
+use crate::{
+    data::{Bounds, Point},
+    ipc::CaptureBackend,
+};
+
+/// Pixel of a captured frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapturePoint(pub Point);
+
+/// CSS pixel of the page, as used for `clientX` and `clientY`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputPoint(pub Point);
+
+/// Page geometry reported by the bridge
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct PageMetrics {
+    pub device_pixel_ratio: f64,
+    pub canvas_left: f64,
+    pub canvas_top: f64,
+}
+
+/// Everything needed to convert points between the capture and input spaces
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoordinateMapping {
+    /// Physical pixels per CSS pixel
+    pub device_pixel_ratio: f64,
+    /// Position of the game canvas in the page, in CSS pixels
+    pub canvas_offset: (f64, f64),
+    /// Size of the borders and title bar left and above the page in window captures, in
+    /// physical pixels
+    pub decoration: (u32, u32),
+    pub capture_backend: CaptureBackend,
+}
+
+impl Default for CoordinateMapping {
+    fn default() -> Self {
+        Self {
+            device_pixel_ratio: 1.0,
+            canvas_offset: (0.0, 0.0),
+            decoration: (0, 0),
+            capture_backend: CaptureBackend::default(),
+        }
+    }
+}
+
+fn to_pixel(value: f64) -> u32 {
+    value.round().max(0.0) as u32
+}
+
+impl CoordinateMapping {
+    /// Window captures are in physical pixels and may include the decorations, canvas captures
+    /// are in CSS pixels relative to the canvas.
+    pub fn to_input(self, point: CapturePoint) -> InputPoint {
+        let CapturePoint(Point { x, y }) = point;
+        let (x, y) = match self.capture_backend {
+            CaptureBackend::Window => (
+                x.saturating_sub(self.decoration.0) as f64 / self.device_pixel_ratio,
+                y.saturating_sub(self.decoration.1) as f64 / self.device_pixel_ratio,
+            ),
+            CaptureBackend::Canvas => (
+                x as f64 + self.canvas_offset.0,
+                y as f64 + self.canvas_offset.1,
+            ),
+        };
+        InputPoint(Point::new(to_pixel(x), to_pixel(y)))
+    }
+
+    /// Position of `point` in physical pixels of the window client area, for native input
+    pub fn to_window(self, point: InputPoint) -> Point {
+        let InputPoint(Point { x, y }) = point;
+        Point::new(
+            to_pixel(x as f64 * self.device_pixel_ratio),
+            to_pixel(y as f64 * self.device_pixel_ratio),
+        )
+    }
+}
+
+/// Current mapping between capture and input space, shared by the analyzer and the input
+/// backends. Clones share the same mapping.
+#[derive(Debug, Clone, Default)]
+pub struct CoordinateSpace {
+    mapping: Arc<RwLock<CoordinateMapping>>,
+    /// User defined capture areas that never receive clicks, usually open panels
+    exclusion_zones: Arc<RwLock<Vec<Bounds>>>,
+}
+
+impl CoordinateSpace {
+    pub fn mapping(&self) -> CoordinateMapping {
+        *self.mapping.read()
+    }
+
+    pub fn set_capture_backend(&self, backend: CaptureBackend) {
+        self.mapping.write().capture_backend = backend;
+    }
+
+    pub fn set_exclusion_zones(&self, zones: Vec<Bounds>) {
+        *self.exclusion_zones.write() = zones;
+    }
+
+    /// Check whether a point lies inside a user defined exclusion zone
+    pub fn is_excluded(&self, point: CapturePoint) -> bool {
+        self.exclusion_zones
+            .read()
+            .iter()
+            .any(|zone| zone.contains_point(&point.0))
+    }
+
+    /// Query the page and window geometry again, it changes when the window is resized or moved
+    /// to another screen.
+    pub fn refresh(&self, bridge: &JsBridge, window: &Window) -> Result<(), InputError> {
+        let metrics: PageMetrics = bridge.query(&BridgeCommand::PageMetrics)?;
+        let decoration = if CAPTURE_INCLUDES_DECORATIONS {
+            match (window.inner_position(), window.outer_position()) {
+                (Ok(inner), Ok(outer)) => (
+                    (inner.x - outer.x).max(0) as u32,
+                    (inner.y - outer.y).max(0) as u32,
+                ),
+                _ => (0, 0),
+            }
+        } else {
+            (0, 0)
+        };
+
+        let mut mapping = self.mapping.write();
+        if metrics.device_pixel_ratio > 0.0 {
+            mapping.device_pixel_ratio = metrics.device_pixel_ratio;
+        }
+        mapping.canvas_offset = (metrics.canvas_left, metrics.canvas_top);
+        mapping.decoration = decoration;
+        Ok(())
+    }
+
+    /// Convert a captured point for input. Points inside an exclusion zone are never converted,
+    /// so they can't be clicked by accident.
+    pub fn to_input(&self, point: CapturePoint) -> Result<InputPoint, InputError> {
+        if self.is_excluded(point) {
+            return Err(InputError::Excluded(point.0));
+        }
+        Ok(self.mapping().to_input(point))
+    }
+
+    pub fn to_window(&self, point: InputPoint) -> Point {
+        self.mapping().to_window(point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CapturePoint, CoordinateMapping, InputPoint};
+    use crate::{data::Point, ipc::CaptureBackend};
+
+    #[test]
+    fn test_coordinate_mapping() {
+        let mapping = CoordinateMapping {
+            device_pixel_ratio: 2.0,
+            canvas_offset: (10.0, 20.5),
+            decoration: (0, 56),
+            capture_backend: CaptureBackend::Window,
+        };
+        assert_eq!(
+            mapping.to_input(CapturePoint(Point::new(400, 256))),
+            InputPoint(Point::new(200, 100))
+        );
+        // Clicks on the title bar stay at the top of the page
+        assert_eq!(
+            mapping.to_input(CapturePoint(Point::new(3, 10))),
+            InputPoint(Point::new(2, 0))
+        );
+        assert_eq!(
+            mapping.to_window(InputPoint(Point::new(200, 100))),
+            Point::new(400, 200)
+        );
+
+        let mapping = CoordinateMapping {
+            capture_backend: CaptureBackend::Canvas,
+            ..mapping
+        };
+        assert_eq!(
+            mapping.to_input(CapturePoint(Point::new(400, 256))),
+            InputPoint(Point::new(410, 277))
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use std::{fmt, sync::Arc};
+
+use parking_lot::RwLock;
+
+use super::{CoordinateSpace, InputError, InputPoint, InputSink, JsBridge, KeyMode};
+use crate::ipc::InputBackend;
+
+/// Sends input through the backend selected in the config. Clones share the selection.
+///
+/// Logging out always goes through the webview, the native backends can't reload the page.
+#[derive(Clone)]
+pub struct InputRouter {
+    bridge: JsBridge,
+    window_id: u64,
+    coordinates: CoordinateSpace,
+    /// `None` while input goes through the webview
+    native: Arc<RwLock<Option<Box<dyn InputSink>>>>,
+}
+
+impl fmt::Debug for InputRouter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InputRouter")
+            .field("window_id", &self.window_id)
+            .field("native", &self.native.read().is_some())
+            .finish()
+    }
+}
+
+impl InputRouter {
+    pub fn new(bridge: JsBridge, window_id: u64, coordinates: CoordinateSpace) -> Self {
+        Self {
+            bridge,
+            window_id,
+            coordinates,
+            native: Arc::default(),
+        }
+    }
+
+    /// Switch to `backend`. Input keeps going through the webview if the backend can't be used.
+    pub fn set_backend(&self, backend: InputBackend) -> Result<(), InputError> {
+        let mut native = self.native.write();
+        match backend {
+            InputBackend::Webview => *native = None,
+            InputBackend::X11 if native.is_none() => {
+                *native = Some(open_x11_input(self.window_id, &self.coordinates)?)
+            }
+            InputBackend::X11 => {}
+        }
+        Ok(())
+    }
+
+    fn route<T>(&self, func: impl FnOnce(&dyn InputSink) -> T) -> T {
+        match self.native.read().as_deref() {
+            Some(native) => func(native),
+            None => func(&self.bridge),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_x11_input(
+    window_id: u64,
+    coordinates: &CoordinateSpace,
+) -> Result<Box<dyn InputSink>, InputError> {
+    Ok(Box::new(super::X11Input::new(
+        window_id,
+        coordinates.clone(),
+    )?))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_x11_input(
+    _window_id: u64,
+    _coordinates: &CoordinateSpace,
+) -> Result<Box<dyn InputSink>, InputError> {
+    Err(InputError::Backend(
+        "X11 input is only available on Linux".to_string(),
+    ))
+}
+
+impl InputSink for InputRouter {
+    fn send_key(&self, key: &str, mode: KeyMode) -> Result<(), InputError> {
+        self.route(|input| input.send_key(key, mode))
+    }
+
+    fn send_slot(&self, slot_bar_index: usize, slot_index: usize) -> Result<(), InputError> {
+        self.route(|input| input.send_slot(slot_bar_index, slot_index))
+    }
+
+    fn mouse_move(&self, pos: InputPoint) -> Result<(), InputError> {
+        self.route(|input| input.mouse_move(pos))
+    }
+
+    fn click_at(&self, pos: InputPoint) -> Result<(), InputError> {
+        self.route(|input| input.click_at(pos))
+    }
+
+    fn send_message(&self, text: &str) -> Result<(), InputError> {
+        self.route(|input| input.send_message(text))
+    }
+
+    fn logout(&self) -> Result<(), InputError> {
+        self.bridge.logout()
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt;
+#[cfg(test)]
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+use parking_lot::Mutex;
+
+use super::{InputPoint, KeyMode};
+use crate::data::Point;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputError {
+    /// The script couldn't be run in the page
+    Eval(String),
+    /// The page reported an error while running the command
+    Script(String),
+    /// The page never answered
+    Timeout,
+    /// The point lies inside an exclusion zone
+    Excluded(Point),
+    /// The native input backend failed or can't do what was asked
+    Backend(String),
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::Eval(message) => write!(f, "eval failed: {}", message),
+            InputError::Script(message) => write!(f, "script error: {}", message),
+            InputError::Timeout => write!(f, "no answer from the page"),
+            InputError::Excluded(pos) => write!(f, "{:?} is in an exclusion zone", pos),
+            InputError::Backend(message) => write!(f, "input backend error: {}", message),
+        }
+    }
+}
+
+/// Destination of every input the bot sends to the client
+pub trait InputSink: Send + Sync + 'static {
+    fn send_key(&self, key: &str, mode: KeyMode) -> Result<(), InputError>;
+
+    /// Press the slot `slot_index` of the slot bar `slot_bar_index`
+    fn send_slot(&self, slot_bar_index: usize, slot_index: usize) -> Result<(), InputError>;
+
+    fn mouse_move(&self, pos: InputPoint) -> Result<(), InputError>;
+
+    fn click_at(&self, pos: InputPoint) -> Result<(), InputError>;
+
+    /// Fill the chat input with `text`
+    fn send_message(&self, text: &str) -> Result<(), InputError>;
+
+    fn logout(&self) -> Result<(), InputError>;
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SentInput {
+    Key(String, KeyMode),
+    Slot(usize, usize),
+    MouseMove(InputPoint),
+    Click(InputPoint),
+    Message(String),
+    Logout,
+}
+
+/// Keeps every input instead of sending it, along with the time since the sink was created
+#[cfg(test)]
+#[derive(Debug)]
+pub struct RecordingSink {
+    start_time: Instant,
+    inputs: Mutex<Vec<(Duration, SentInput)>>,
+}
+
+#[cfg(test)]
+impl Default for RecordingSink {
+    fn default() -> Self {
+        Self {
+            start_time: Instant::now(),
+            inputs: Mutex::new(vec![]),
+        }
+    }
+}
+
+#[cfg(test)]
+impl RecordingSink {
+    fn record(&self, input: SentInput) {
+        self.inputs
+            .lock()
+            .push((self.start_time.elapsed(), input));
+    }
+
+    /// Take the inputs recorded since the last call
+    pub fn take(&self) -> Vec<(Duration, SentInput)> {
+        self.inputs.lock().drain(..).collect()
+    }
+
+    /// Take the inputs recorded since the last call, without their timestamps
+    pub fn take_inputs(&self) -> Vec<SentInput> {
+        self.take().into_iter().map(|(_, input)| input).collect()
+    }
+}
+
+#[cfg(test)]
+impl InputSink for RecordingSink {
+    fn send_key(&self, key: &str, mode: KeyMode) -> Result<(), InputError> {
+        self.record(SentInput::Key(key.to_string(), mode));
+        Ok(())
+    }
+
+    fn send_slot(&self, slot_bar_index: usize, slot_index: usize) -> Result<(), InputError> {
+        self.record(SentInput::Slot(slot_bar_index, slot_index));
+        Ok(())
+    }
+
+    fn mouse_move(&self, pos: InputPoint) -> Result<(), InputError> {
+        self.record(SentInput::MouseMove(pos));
+        Ok(())
+    }
+
+    fn click_at(&self, pos: InputPoint) -> Result<(), InputError> {
+        self.record(SentInput::Click(pos));
+        Ok(())
+    }
+
+    fn send_message(&self, text: &str) -> Result<(), InputError> {
+        self.record(SentInput::Message(text.to_string()));
+        Ok(())
+    }
+
+    fn logout(&self) -> Result<(), InputError> {
+        self.record(SentInput::Logout);
+        Ok(())
+    }
+}
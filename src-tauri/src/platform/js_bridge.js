@@ -0,0 +1,102 @@
+// Dispatcher injected in the client page by `JsBridge`, see `DISPATCHER_JS`
+if (!window.neuzBridge) {
+    const emit = (event, payload) => window.__TAURI_INVOKE__('tauri', {
+        __tauriModule: 'Event',
+        message: { cmd: 'emit', event, payload: JSON.stringify(payload) },
+    })
+    // Commands sent without an id are only answered when they fail
+    const reply = (id, error, value) => id === null
+        ? error && emit('bridge_error', error)
+        : emit('bridge_result', { id, error, value })
+
+    // Hidden elements, and the ones inside them, have no box
+    const visible = (element) => element.getClientRects().length > 0
+    const shown = (selector) => [...document.querySelectorAll(selector)].filter(visible)
+    const find = (selector, elements = document.querySelectorAll(selector)) => {
+        if (!elements[0]) throw new Error(selector + ' not found')
+        return elements[0]
+    }
+
+    window.neuzBridge = (id, c) => {
+        try {
+            let value = null
+            switch (c.type) {
+                case 'Key': {
+                    const init = { key: c.key, ctrlKey: c.modifiers.ctrl, shiftKey: c.modifiers.shift, altKey: c.modifiers.alt }
+                    const canvas = find('canvas')
+                    if (c.action !== 'Up') canvas.dispatchEvent(new KeyboardEvent('keydown', init))
+                    if (c.action !== 'Down') canvas.dispatchEvent(new KeyboardEvent('keyup', init))
+                    break
+                }
+                case 'Mouse': {
+                    const init = { clientX: c.x, clientY: c.y, button: { Left: 0, Middle: 1, Right: 2 }[c.button] }
+                    const canvas = find('canvas')
+                    for (const type of { Move: ['mousemove'], Click: ['mousedown', 'mouseup'] }[c.action]) {
+                        canvas.dispatchEvent(new MouseEvent(type, init))
+                    }
+                    break
+                }
+                case 'SetInputText': {
+                    const input = find('input')
+                    input.value = c.text
+                    input.select()
+                    break
+                }
+                case 'PageMetrics': {
+                    const rect = find('canvas').getBoundingClientRect()
+                    value = { device_pixel_ratio: window.devicePixelRatio, canvas_left: rect.left, canvas_top: rect.top }
+                    break
+                }
+                case 'PageState': {
+                    value = {
+                        ready: document.readyState === 'complete',
+                        has_canvas: !!document.querySelector('canvas'),
+                        has_password_input: shown('input[type=password]').length > 0,
+                        has_selection_list: shown('[role=listbox]').length > 0,
+                        disconnected: !navigator.onLine || shown('[role=alertdialog],[role=dialog]')
+                            .some(dialog => /disconnected|connection (lost|closed)/i.test(dialog.textContent)),
+                    }
+                    break
+                }
+                case 'Login': {
+                    const password = find('input[type=password]', shown('input[type=password]'))
+                    const form = password.form || document
+                    const username = [...form.querySelectorAll('input')]
+                        .find(input => input !== password && visible(input) && !['hidden', 'checkbox', 'submit'].includes(input.type))
+                    if (!username) throw new Error('username input not found')
+                    for (const [input, text] of [[username, c.username], [password, c.password]]) {
+                        input.value = text
+                        input.dispatchEvent(new Event('input', { bubbles: true }))
+                    }
+                    const submit = form.querySelector('[type=submit]')
+                    if (submit) submit.click()
+                    else if (password.form) password.form.requestSubmit()
+                    else throw new Error('submit button not found')
+                    break
+                }
+                // Lists marked up as listboxes, as in the stand-in page. The real client draws its
+                // server and character lists in the canvas.
+                case 'SelectEntry': {
+                    const list = shown('[role=listbox]')
+                        .find(list => (list.getAttribute('aria-label') || '').toLowerCase().includes(c.list))
+                    if (!list) {
+                        value = false
+                        break
+                    }
+                    const option = list.querySelectorAll('[role=option]')[c.index]
+                    if (!option) throw new Error('no ' + c.list + ' at position ' + c.index)
+                    option.click()
+                    const confirm = list.parentElement.querySelector('button')
+                    if (confirm) confirm.click()
+                    value = true
+                    break
+                }
+                default:
+                    throw new Error('unknown command ' + c.type)
+            }
+            reply(id, null, value)
+        } catch (e) {
+            reply(id, String(e), null)
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{sync_channel, SyncSender},
+        Arc,
+    },
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tauri::Window;
+
+use super::{InputError, InputPoint, InputSink, KeyMode};
+
+/// Time to wait for the page to acknowledge a command
+const RESULT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Ids are unique across bridges so that each one only picks up its own results
+static NEXT_COMMAND_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Runs the commands sent by `JsBridge` and reports the outcome, and the answer of queries, as a
+/// `bridge_result` event. Commands sent without an id are only answered with a `bridge_error`
+/// event when they fail. Commands are passed as JSON values, never spliced into the source.
+///
+/// It's injected along with the first command sent to a page, and defines itself only once.
+const DISPATCHER_JS: &str = include_str!("js_bridge.js");
+
+/// Error of the commands that reached a page without dispatcher, after it was reloaded
+const DISPATCHER_MISSING: &str = "no dispatcher in the page, it was reloaded";
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub enum KeyAction {
+    Down,
+    Up,
+    Press,
+}
+
+impl From<KeyMode> for KeyAction {
+    fn from(mode: KeyMode) -> Self {
+        match mode {
+            KeyMode::Press => KeyAction::Press,
+            KeyMode::Hold => KeyAction::Down,
+            KeyMode::Release => KeyAction::Up,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub enum MouseAction {
+    Move,
+    Click,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, Serialize)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+/// Command understood by the injected dispatcher
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type")]
+pub enum BridgeCommand {
+    Key {
+        key: String,
+        action: KeyAction,
+        modifiers: Modifiers,
+    },
+    Mouse {
+        action: MouseAction,
+        x: u32,
+        y: u32,
+        button: MouseButton,
+    },
+    SetInputText {
+        text: String,
+    },
+    /// Answers with the `PageMetrics` of the page
+    PageMetrics,
+    /// Answers with the `PageState` of the page
+    PageState,
+    /// Fill and submit the login form
+    Login {
+        username: String,
+        password: String,
+    },
+    /// Pick the entry at `index` of the shown list whose label contains `list`, such as the server
+    /// or character list, and confirm. Answers with whether the list is shown.
+    ///
+    /// Only lists marked up as `[role=listbox]`, as in the stand-in page, can be found. The real
+    /// client draws its lists in the canvas.
+    SelectEntry {
+        list: String,
+        index: u32,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct BridgeResult {
+    id: u64,
+    error: Option<String>,
+    #[serde(default)]
+    value: serde_json::Value,
+}
+
+/// Sends input to the client page through a single injected dispatcher function.
+#[derive(Debug, Clone)]
+pub struct JsBridge {
+    window: Window,
+    pending: Arc<Mutex<HashMap<u64, SyncSender<BridgeResult>>>>,
+    /// Whether the dispatcher was injected in the current page
+    injected: Arc<AtomicBool>,
+    /// Error raised in the page by a command sent without waiting, reported by the next one
+    last_error: Arc<Mutex<Option<String>>>,
+}
+
+impl JsBridge {
+    pub fn new(window: Window) -> Self {
+        let pending: Arc<Mutex<HashMap<u64, SyncSender<BridgeResult>>>> = Arc::default();
+        let injected = Arc::new(AtomicBool::new(false));
+        let last_error: Arc<Mutex<Option<String>>> = Arc::default();
+
+        let local_pending = pending.clone();
+        window.listen("bridge_result", move |e| {
+            if let Some(result) = e
+                .payload()
+                .and_then(|payload| serde_json::from_str::<BridgeResult>(payload).ok())
+            {
+                if let Some(sender) = local_pending.lock().remove(&result.id) {
+                    drop(sender.send(result));
+                }
+            }
+        });
+
+        let local_last_error = last_error.clone();
+        window.listen("bridge_error", move |e| {
+            if let Some(error) = e
+                .payload()
+                .and_then(|payload| serde_json::from_str::<String>(payload).ok())
+            {
+                *local_last_error.lock() = Some(error);
+            }
+        });
+
+        // Inject the dispatcher again with the next command, and fail the one that was lost
+        let local_pending = pending.clone();
+        let local_injected = injected.clone();
+        let local_last_error = last_error.clone();
+        window.listen("bridge_missing", move |e| {
+            local_injected.store(false, Ordering::Relaxed);
+            let error = Some(DISPATCHER_MISSING.to_string());
+            match e
+                .payload()
+                .and_then(|payload| serde_json::from_str::<Option<u64>>(payload).ok())
+            {
+                Some(Some(id)) => {
+                    if let Some(sender) = local_pending.lock().remove(&id) {
+                        drop(sender.send(BridgeResult {
+                            id,
+                            error,
+                            value: serde_json::Value::Null,
+                        }));
+                    }
+                }
+                _ => *local_last_error.lock() = error,
+            }
+        });
+
+        Self {
+            window,
+            pending,
+            injected,
+            last_error,
+        }
+    }
+
+    /// Run a command in the page without waiting for it. Errors raised in the page are reported
+    /// by the next command sent this way.
+    pub fn send(&self, command: &BridgeCommand) -> Result<(), InputError> {
+        self.eval(None, command)?;
+        match self.last_error.lock().take() {
+            Some(error) => Err(InputError::Script(error)),
+            None => Ok(()),
+        }
+    }
+
+    /// Run a command in the page and wait for its outcome
+    pub fn send_and_wait(&self, command: &BridgeCommand) -> Result<(), InputError> {
+        self.run(command).map(drop)
+    }
+
+    /// Run a command in the page and deserialize its answer
+    pub fn query<T: DeserializeOwned>(&self, command: &BridgeCommand) -> Result<T, InputError> {
+        serde_json::from_value(self.run(command)?)
+            .map_err(|err| InputError::Script(format!("unexpected answer: {}", err)))
+    }
+
+    fn run(&self, command: &BridgeCommand) -> Result<serde_json::Value, InputError> {
+        let id = NEXT_COMMAND_ID.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = sync_channel(1);
+        self.pending.lock().insert(id, sender);
+
+        if let Err(err) = self.eval(Some(id), command) {
+            self.pending.lock().remove(&id);
+            return Err(err);
+        }
+
+        let result = receiver.recv_timeout(RESULT_TIMEOUT);
+        self.pending.lock().remove(&id);
+        match result {
+            Ok(BridgeResult {
+                error: Some(error), ..
+            }) => Err(InputError::Script(error)),
+            Ok(result) => Ok(result.value),
+            Err(_) => Err(InputError::Timeout),
+        }
+    }
+
+    /// Hand a command to the dispatcher, which answers it if it has an `id`
+    fn eval(&self, id: Option<u64>, command: &BridgeCommand) -> Result<(), InputError> {
+        let payload =
+            serde_json::to_string(command).map_err(|err| InputError::Eval(err.to_string()))?;
+        let id = serde_json::to_string(&id).map_err(|err| InputError::Eval(err.to_string()))?;
+        let inject = !self.injected.swap(true, Ordering::Relaxed);
+        let script = command_script(inject, &id, &payload);
+        self.window.eval(&script).map_err(|err| {
+            self.injected.store(false, Ordering::Relaxed);
+            InputError::Eval(err.to_string())
+        })
+    }
+
+    /// Reload the client page, which drops the game session and returns to the login screen.
+    pub fn reload(&self) -> Result<(), InputError> {
+        self.injected.store(false, Ordering::Relaxed);
+        self.window
+            .eval("window.location.reload()")
+            .map_err(|err| InputError::Eval(err.to_string()))
+    }
+
+    fn key(&self, key: &str, action: KeyAction) -> Result<(), InputError> {
+        self.send(&BridgeCommand::Key {
+            key: key.to_string(),
+            action,
+            modifiers: Modifiers::default(),
+        })
+    }
+
+    fn mouse(&self, action: MouseAction, pos: InputPoint) -> Result<(), InputError> {
+        self.send(&BridgeCommand::Mouse {
+            action,
+            x: pos.0.x,
+            y: pos.0.y,
+            button: MouseButton::Left,
+        })
+    }
+}
+
+/// Script running a command, along with the dispatcher when it has to be injected. Without it, a
+/// page reloaded since the injection tells with a `bridge_missing` event.
+fn command_script(inject: bool, id: &str, payload: &str) -> String {
+    let call = format!("window.neuzBridge({},{})", id, payload);
+    if inject {
+        format!("{}{}", DISPATCHER_JS, call)
+    } else {
+        format!(
+            "if(window.neuzBridge){{{}}}else{{window.__TAURI_INVOKE__('tauri',{{__tauriModule:'Event',message:{{cmd:'emit',event:'bridge_missing',payload:'{}'}}}})}}",
+            call, id
+        )
+    }
+}
+
+impl InputSink for JsBridge {
+    fn send_key(&self, key: &str, mode: KeyMode) -> Result<(), InputError> {
+        self.key(key, mode.into())
+    }
+
+    fn send_slot(&self, slot_bar_index: usize, slot_index: usize) -> Result<(), InputError> {
+        self.key(&format!("F{}", slot_bar_index + 1), KeyAction::Press)?;
+        self.key(&slot_index.to_string(), KeyAction::Press)?;
+        std::thread::sleep(Duration::from_millis(100));
+        Ok(())
+    }
+
+    fn mouse_move(&self, pos: InputPoint) -> Result<(), InputError> {
+        self.mouse(MouseAction::Move, pos)
+    }
+
+    fn click_at(&self, pos: InputPoint) -> Result<(), InputError> {
+        self.mouse(MouseAction::Click, pos)
+    }
+
+    fn send_message(&self, text: &str) -> Result<(), InputError> {
+        // Enter is pressed right after, the text has to be in place
+        self.send_and_wait(&BridgeCommand::SetInputText {
+            text: text.to_string(),
+        })
+    }
+
+    fn logout(&self) -> Result<(), InputError> {
+        self.reload()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{command_script, BridgeCommand, KeyAction, Modifiers, DISPATCHER_JS};
+
+    #[test]
+    fn test_command_payload() {
+        let command = BridgeCommand::SetInputText {
+            text: r#"it's a \ "test"');alert(1)//"#.to_string(),
+        };
+        assert_eq!(
+            serde_json::to_string(&command).unwrap(),
+            r#"{"type":"SetInputText","text":"it's a \\ \"test\"');alert(1)//"}"#
+        );
+
+        let command = BridgeCommand::Key {
+            key: "F1".to_string(),
+            action: KeyAction::Press,
+            modifiers: Modifiers {
+                shift: true,
+                ..Modifiers::default()
+            },
+        };
+        assert_eq!(
+            serde_json::to_string(&command).unwrap(),
+            r#"{"type":"Key","key":"F1","action":"Press","modifiers":{"ctrl":false,"shift":true,"alt":false}}"#
+        );
+    }
+
+    #[test]
+    fn test_command_script() {
+        let payload = r#"{"type":"PageState"}"#;
+        let script = command_script(true, "3", payload);
+        assert!(script.starts_with(DISPATCHER_JS));
+        assert!(script.ends_with(r#"window.neuzBridge(3,{"type":"PageState"})"#));
+
+        // Only the call once injected, the id is reported if the dispatcher is gone
+        let script = command_script(false, "null", payload);
+        assert!(!script.contains(DISPATCHER_JS));
+        assert!(script.starts_with(
+            r#"if(window.neuzBridge){window.neuzBridge(null,{"type":"PageState"})}else{"#
+        ));
+        assert!(script.contains("event:'bridge_missing',payload:'null'"));
+    }
+}
@@ -1 +1,4 @@
 pub const IGNORE_AREA_TOP: u32 = 0;
+
+/// Whether window captures include the title bar and borders
+pub const CAPTURE_INCLUDES_DECORATIONS: bool = false;
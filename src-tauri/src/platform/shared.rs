@@ -1,10 +1,8 @@
 use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
 use tauri::Window;
-use std::time::Duration;
 
-use crate::data::Point;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KeyMode {
     Press,
     Hold,
@@ -34,61 +32,3 @@ pub fn get_window_id(window: &Window) -> Option<u64> {
         _ => Some(0_u64),
     }
 }
-
-pub fn eval_send_key(window: &Window, key: &str, mode: KeyMode) {
-    match mode {
-        KeyMode::Press => {
-            drop(window.eval(format!("
-                document.querySelector('canvas').dispatchEvent(new KeyboardEvent('keydown', {{'key': '{0}'}}))
-                document.querySelector('canvas').dispatchEvent(new KeyboardEvent('keyup', {{'key': '{0}'}}))"
-            , key).as_str()))
-        },
-        KeyMode::Hold => {
-            drop(window.eval(format!("
-                document.querySelector('canvas').dispatchEvent(new KeyboardEvent('keydown', {{'key': '{0}'}}))"
-            , key).as_str()))
-        },
-        KeyMode::Release => {
-            drop(window.eval(format!("
-                document.querySelector('canvas').dispatchEvent(new KeyboardEvent('keyup', {{'key': '{0}'}}))"
-            , key).as_str()))
-        },
-    }
-}
-
-pub fn send_slot_eval(window: &Window, slot_bar_index: usize, k: usize) {
-    eval_send_key(window, format!("F{}", slot_bar_index + 1).to_string().as_str(), KeyMode::Press);
-    eval_send_key(window, k.to_string().as_str(), KeyMode::Press);
-    std::thread::sleep(Duration::from_millis(100));
-}
-
-
-pub fn eval_mouse_click_at_point(window: &Window, pos: Point) {
-    drop(window.eval(format!("
-        document.querySelector('canvas').dispatchEvent(new MouseEvent('mousedown', {{
-            clientX: {0},
-            clientY: {1}
-        }}))
-
-        document.querySelector('canvas').dispatchEvent(new MouseEvent('mouseup', {{
-            clientX: {0},
-            clientY: {1}
-        }}))"
-    , pos.x, pos.y).as_str()));
-}
-
-pub fn eval_mouse_move(window: &Window, pos: Point) {
-    drop(window.eval(format!("
-        document.querySelector('canvas').dispatchEvent(new MouseEvent('mousemove', {{
-            clientX: {0},
-            clientY: {1}
-        }}))"
-    , pos.x, pos.y).as_str()));
-}
-
-pub fn eval_send_message(window: &Window,text: &str) {
-    drop(window.eval(format!("
-    document.querySelector('input').value = '{0}';
-    document.querySelector('input').select();"
-    , text).as_str()));
-}
@@ -0,0 +1,431 @@
+use std::{
+    ffi::{CStr, CString},
+    mem,
+    os::raw::{c_char, c_int, c_uint},
+    ptr,
+    sync::Once,
+    thread,
+    time::Duration,
+};
+
+use parking_lot::{const_mutex, Mutex};
+use x11::{xlib, xtest};
+
+use super::{CoordinateSpace, InputError, InputPoint, InputSink, KeyMode};
+
+/// Errors raised on the connections of `X11Input` since they were last checked, by display. The
+/// default handler would exit the process.
+static ERRORS: Mutex<Vec<(usize, Option<u8>)>> = const_mutex(Vec::new());
+
+/// Handler that was installed before ours, it still gets the errors of the other connections
+static PREVIOUS_HANDLER: Mutex<xlib::XErrorHandler> = const_mutex(None);
+
+static INSTALL_HANDLER: Once = Once::new();
+
+unsafe extern "C" fn on_error(display: *mut xlib::Display, event: *mut xlib::XErrorEvent) -> c_int {
+    if let Some((_, error)) = ERRORS
+        .lock()
+        .iter_mut()
+        .find(|(ours, _)| *ours == display as usize)
+    {
+        error.get_or_insert((*event).error_code);
+        return 0;
+    }
+    let previous = *PREVIOUS_HANDLER.lock();
+    match previous {
+        Some(handler) => handler(display, event),
+        None => 0,
+    }
+}
+
+/// Owned Xlib connection. Xlib isn't thread safe on its own, every call goes through the mutex
+/// around it.
+struct Connection(*mut xlib::Display);
+
+unsafe impl Send for Connection {}
+
+impl Connection {
+    fn open() -> Option<Self> {
+        INSTALL_HANDLER.call_once(|| unsafe {
+            *PREVIOUS_HANDLER.lock() = xlib::XSetErrorHandler(Some(on_error));
+        });
+        let display = unsafe { xlib::XOpenDisplay(ptr::null()) };
+        if display.is_null() {
+            return None;
+        }
+        ERRORS.lock().push((display as usize, None));
+        Some(Self(display))
+    }
+
+    /// Wait for the server to process the requests sent so far and report the first that failed
+    fn sync(&self) -> Result<(), InputError> {
+        unsafe {
+            xlib::XSync(self.0, xlib::False);
+        }
+        let error = ERRORS
+            .lock()
+            .iter_mut()
+            .find(|(ours, _)| *ours == self.0 as usize)
+            .and_then(|(_, error)| error.take());
+        match error {
+            Some(code) => Err(InputError::Backend(self.error_text(code))),
+            None => Ok(()),
+        }
+    }
+
+    fn error_text(&self, code: u8) -> String {
+        let mut buffer = [0 as c_char; 256];
+        unsafe {
+            xlib::XGetErrorText(
+                self.0,
+                code as c_int,
+                buffer.as_mut_ptr(),
+                buffer.len() as c_int,
+            );
+            CStr::from_ptr(buffer.as_ptr())
+                .to_string_lossy()
+                .into_owned()
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        unsafe {
+            xlib::XCloseDisplay(self.0);
+        }
+        ERRORS.lock().retain(|(ours, _)| *ours != self.0 as usize);
+    }
+}
+
+/// Sends input to the client window through the XTest extension, so the game receives it as if
+/// it came from a real keyboard and mouse.
+///
+/// XTest events go to the focused window, the client window is focused before each input if it
+/// lost the focus. Nothing is sent while the window isn't shown.
+pub struct X11Input {
+    connection: Mutex<Connection>,
+    window: xlib::Window,
+    coordinates: CoordinateSpace,
+}
+
+impl X11Input {
+    /// Open the X display for the window `window_id`, as given by `platform::get_window_id`
+    pub fn new(window_id: u64, coordinates: CoordinateSpace) -> Result<Self, InputError> {
+        if window_id == 0 {
+            return Err(InputError::Backend("no X11 window".to_string()));
+        }
+        let connection = Connection::open()
+            .ok_or_else(|| InputError::Backend("can't open the X display".to_string()))?;
+        let display = connection.0;
+        unsafe {
+            let (mut event_base, mut error_base, mut major, mut minor) = (0, 0, 0, 0);
+            if xtest::XTestQueryExtension(
+                display,
+                &mut event_base,
+                &mut error_base,
+                &mut major,
+                &mut minor,
+            ) == 0
+            {
+                return Err(InputError::Backend(
+                    "the X server has no XTest extension".to_string(),
+                ));
+            }
+
+            Ok(Self {
+                connection: Mutex::new(connection),
+                window: window_id as xlib::Window,
+                coordinates,
+            })
+        }
+    }
+
+    /// Run `func` with the display once the client window has the focus, and report the requests
+    /// the server refused
+    fn with_display<T>(
+        &self,
+        func: impl FnOnce(*mut xlib::Display) -> Result<T, InputError>,
+    ) -> Result<T, InputError> {
+        let connection = self.connection.lock();
+        let display = connection.0;
+        unsafe {
+            // A minimized or closed window can't take the focus
+            let mut attributes: xlib::XWindowAttributes = mem::zeroed();
+            if xlib::XGetWindowAttributes(display, self.window, &mut attributes) == 0 {
+                connection.sync()?;
+                return Err(InputError::Backend("client window is gone".to_string()));
+            }
+            if attributes.map_state != xlib::IsViewable {
+                return Err(InputError::Backend("client window isn't shown".to_string()));
+            }
+
+            let (mut focused, mut revert_to) = (0, 0);
+            xlib::XGetInputFocus(display, &mut focused, &mut revert_to);
+            if focused != self.window {
+                xlib::XSetInputFocus(
+                    display,
+                    self.window,
+                    xlib::RevertToParent,
+                    xlib::CurrentTime,
+                );
+            }
+        }
+        let result = func(display);
+        connection.sync()?;
+        result
+    }
+
+    /// Position of `pos` in root window coordinates
+    fn to_root(&self, display: *mut xlib::Display, pos: InputPoint) -> (c_int, c_int) {
+        let pos = self.coordinates.to_window(pos);
+        let (mut x, mut y) = (0, 0);
+        let mut child = 0;
+        unsafe {
+            xlib::XTranslateCoordinates(
+                display,
+                self.window,
+                xlib::XDefaultRootWindow(display),
+                pos.x as c_int,
+                pos.y as c_int,
+                &mut x,
+                &mut y,
+                &mut child,
+            );
+        }
+        (x, y)
+    }
+}
+
+/// Keycode of `keysym` on the current keyboard mapping
+fn keycode(display: *mut xlib::Display, keysym: xlib::KeySym) -> Result<xlib::KeyCode, InputError> {
+    match unsafe { xlib::XKeysymToKeycode(display, keysym) } {
+        0 => Err(InputError::Backend(format!(
+            "no key for keysym {:#x}",
+            keysym
+        ))),
+        keycode => Ok(keycode),
+    }
+}
+
+fn fake_key(display: *mut xlib::Display, keycode: xlib::KeyCode, pressed: bool) {
+    unsafe {
+        xtest::XTestFakeKeyEvent(display, keycode as c_uint, pressed as c_int, 0);
+    }
+}
+
+/// Keysym of a character. Latin-1 keysyms match their code point, the others are offset.
+fn char_keysym(c: char) -> xlib::KeySym {
+    let code = c as xlib::KeySym;
+    if code <= 0xff {
+        code
+    } else {
+        0x0100_0000 + code
+    }
+}
+
+/// Keysym of a key named as in the movements. Single characters are case insensitive, `W` is
+/// the same key as `w`.
+fn keysym(key: &str) -> Result<xlib::KeySym, InputError> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(char_keysym(c.to_ascii_lowercase()));
+    }
+
+    let name = match key {
+        "Space" => "space",
+        "Enter" => "Return",
+        "Backspace" => "BackSpace",
+        "Ctrl" | "Control" => "Control_L",
+        "Shift" => "Shift_L",
+        "Alt" => "Alt_L",
+        _ => key,
+    };
+    let unknown_key = || InputError::Backend(format!("unknown key {}", key));
+    let name = CString::new(name).map_err(|_| unknown_key())?;
+    match unsafe { xlib::XStringToKeysym(name.as_ptr()) } {
+        0 => Err(unknown_key()),
+        keysym => Ok(keysym),
+    }
+}
+
+impl InputSink for X11Input {
+    /// Send a key or a combination such as `Ctrl+1`. Modifiers of a held combination stay down
+    /// until the same combination is released.
+    fn send_key(&self, key: &str, mode: KeyMode) -> Result<(), InputError> {
+        let (modifiers, key) = match key.rsplit_once('+') {
+            Some((modifiers, key)) if !modifiers.is_empty() && !key.is_empty() => {
+                (modifiers.split('+').collect::<Vec<_>>(), key)
+            }
+            _ => (vec![], key),
+        };
+
+        self.with_display(|display| {
+            let modifiers = modifiers
+                .into_iter()
+                .map(|modifier| keysym(modifier).and_then(|keysym| keycode(display, keysym)))
+                .collect::<Result<Vec<_>, _>>()?;
+            let key = keycode(display, keysym(key)?)?;
+
+            if !matches!(mode, KeyMode::Release) {
+                for modifier in modifiers.iter() {
+                    fake_key(display, *modifier, true);
+                }
+                fake_key(display, key, true);
+            }
+            if !matches!(mode, KeyMode::Hold) {
+                fake_key(display, key, false);
+                for modifier in modifiers.iter().rev() {
+                    fake_key(display, *modifier, false);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn send_slot(&self, slot_bar_index: usize, slot_index: usize) -> Result<(), InputError> {
+        self.send_key(&format!("F{}", slot_bar_index + 1), KeyMode::Press)?;
+        self.send_key(&slot_index.to_string(), KeyMode::Press)?;
+        thread::sleep(Duration::from_millis(100));
+        Ok(())
+    }
+
+    fn mouse_move(&self, pos: InputPoint) -> Result<(), InputError> {
+        self.with_display(|display| {
+            let (x, y) = self.to_root(display, pos);
+            unsafe {
+                xtest::XTestFakeMotionEvent(display, xlib::XDefaultScreen(display), x, y, 0);
+            }
+            Ok(())
+        })
+    }
+
+    fn click_at(&self, pos: InputPoint) -> Result<(), InputError> {
+        self.mouse_move(pos)?;
+        self.with_display(|display| {
+            unsafe {
+                xtest::XTestFakeButtonEvent(display, 1, 1, 0);
+                xtest::XTestFakeButtonEvent(display, 1, 0, 0);
+            }
+            Ok(())
+        })
+    }
+
+    /// Type `text` into the focused input, holding shift for the characters that need it
+    fn send_message(&self, text: &str) -> Result<(), InputError> {
+        self.with_display(|display| {
+            let shift = keycode(display, keysym("Shift")?)?;
+            for c in text.chars().filter(|c| !c.is_control()) {
+                let keysym = char_keysym(c);
+                let key = keycode(display, keysym)?;
+                #[allow(deprecated)]
+                let needs_shift = unsafe { xlib::XKeycodeToKeysym(display, key, 0) } != keysym;
+
+                if needs_shift {
+                    fake_key(display, shift, true);
+                }
+                fake_key(display, key, true);
+                fake_key(display, key, false);
+                if needs_shift {
+                    fake_key(display, shift, false);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn logout(&self) -> Result<(), InputError> {
+        Err(InputError::Backend(
+            "logging out needs the webview".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{mem, ptr};
+
+    use x11::xlib;
+
+    use super::X11Input;
+    use crate::{
+        data::Point,
+        platform::{CoordinateSpace, InputPoint, InputSink, KeyMode},
+    };
+
+    /// Needs an X server with the XTest extension: `xvfb-run cargo test -- --ignored x11`
+    #[test]
+    #[ignore]
+    fn test_x11_input() {
+        unsafe {
+            let display = xlib::XOpenDisplay(ptr::null());
+            assert!(!display.is_null(), "no X display");
+            let screen = xlib::XDefaultScreen(display);
+
+            // Dummy client window logging the events it receives
+            let window = xlib::XCreateSimpleWindow(
+                display,
+                xlib::XRootWindow(display, screen),
+                0,
+                0,
+                200,
+                200,
+                0,
+                0,
+                xlib::XWhitePixel(display, screen),
+            );
+            xlib::XSelectInput(
+                display,
+                window,
+                xlib::KeyPressMask
+                    | xlib::KeyReleaseMask
+                    | xlib::ButtonPressMask
+                    | xlib::StructureNotifyMask,
+            );
+            xlib::XMapWindow(display, window);
+            let mut event: xlib::XEvent = mem::zeroed();
+            while event.get_type() != xlib::MapNotify {
+                xlib::XNextEvent(display, &mut event);
+            }
+
+            let input = X11Input::new(window as u64, CoordinateSpace::default()).unwrap();
+            input.send_key("W", KeyMode::Press).unwrap();
+            input.send_key("Ctrl+1", KeyMode::Press).unwrap();
+            input.click_at(InputPoint(Point::new(10, 20))).unwrap();
+
+            let mut received = vec![];
+            while received.len() < 7 {
+                xlib::XNextEvent(display, &mut event);
+                let line = match event.get_type() {
+                    xlib::KeyPress => {
+                        format!("press {:#x}", xlib::XLookupKeysym(&mut event.key, 0))
+                    }
+                    xlib::KeyRelease => {
+                        format!("release {:#x}", xlib::XLookupKeysym(&mut event.key, 0))
+                    }
+                    xlib::ButtonPress => format!(
+                        "button {} at {},{}",
+                        event.button.button, event.button.x, event.button.y
+                    ),
+                    _ => continue,
+                };
+                received.push(line);
+            }
+            assert_eq!(
+                received,
+                vec![
+                    "press 0x77",
+                    "release 0x77",
+                    "press 0xffe3",
+                    "press 0x31",
+                    "release 0x31",
+                    "release 0xffe3",
+                    "button 1 at 10,20",
+                ]
+            );
+
+            xlib::XDestroyWindow(display, window);
+            xlib::XCloseDisplay(display);
+        }
+    }
+}
@@ -0,0 +1,432 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ipc::BotConfig;
+
+/// Profile of the first client window, which gets the historical `.botconfig` on first run
+const DEFAULT_PROFILE: &str = "default";
+
+const MAX_NAME_LEN: usize = 64;
+
+/// Names Windows keeps for devices, whatever the extension
+const RESERVED_NAMES: [&str; 4] = ["CON", "PRN", "AUX", "NUL"];
+
+/// Device names Windows keeps with a digit from 1 to 9 after them
+const RESERVED_PREFIXES: [&str; 2] = ["COM", "LPT"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProfileError {
+    InvalidName(String),
+    NotFound(String),
+    AlreadyExists(String),
+    /// The profile is used by an open client window
+    InUse(String),
+    Io(String),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::InvalidName(name) => write!(
+                f,
+                "invalid profile name \"{}\", use up to {} letters, digits, spaces, - and _ but no device name such as CON",
+                name, MAX_NAME_LEN
+            ),
+            ProfileError::NotFound(name) => write!(f, "no profile named \"{}\"", name),
+            ProfileError::AlreadyExists(name) => {
+                write!(f, "a profile named \"{}\" already exists", name)
+            }
+            ProfileError::InUse(name) => {
+                write!(f, "profile \"{}\" is used by an open client", name)
+            }
+            ProfileError::Io(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+impl From<io::Error> for ProfileError {
+    fn from(err: io::Error) -> Self {
+        ProfileError::Io(err.to_string())
+    }
+}
+
+/// Profiles as shown in the UI for a client window
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileList {
+    pub profiles: Vec<String>,
+    pub active: String,
+}
+
+/// What is remembered between runs
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoreState {
+    /// Last profile used by each client window
+    #[serde(default)]
+    active: HashMap<String, String>,
+}
+
+/// Named bot configs kept in the app data directory, one file per profile, along with the login
+/// of each profile.
+///
+/// Every client window uses one profile, which it keeps using on the next runs.
+#[derive(Debug)]
+pub struct ProfileStore {
+    dir: PathBuf,
+    credentials_dir: PathBuf,
+    state_path: PathBuf,
+    /// Where the configs used before profiles existed are looked up
+    legacy_dir: PathBuf,
+    state: StoreState,
+}
+
+impl ProfileStore {
+    /// Open the store of the app data directory `app_dir`
+    pub fn open(app_dir: &Path) -> Result<Self, ProfileError> {
+        Self::open_with_legacy(app_dir, Path::new(""))
+    }
+
+    /// Open the store of `app_dir`, importing the configs found in `legacy_dir`
+    fn open_with_legacy(app_dir: &Path, legacy_dir: &Path) -> Result<Self, ProfileError> {
+        let dir = app_dir.join("profiles");
+        fs::create_dir_all(&dir)?;
+        let state_path = app_dir.join("profiles.json");
+        let state = File::open(&state_path)
+            .ok()
+            .and_then(|file| serde_json::from_reader(file).ok())
+            .unwrap_or_default();
+        Ok(Self {
+            dir,
+            credentials_dir: app_dir.join("credentials"),
+            state_path,
+            legacy_dir: legacy_dir.to_path_buf(),
+            state,
+        })
+    }
+
+    /// Names of the stored profiles, sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names = fs::read_dir(&self.dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter_map(|entry| {
+                        let path = entry.path();
+                        match path.extension() {
+                            Some(extension) if extension == "json" => path
+                                .file_stem()
+                                .and_then(|stem| stem.to_str())
+                                .map(|stem| stem.to_string()),
+                            _ => None,
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    pub fn list(&self, label: &str) -> ProfileList {
+        ProfileList {
+            profiles: self.names(),
+            active: self.active(label),
+        }
+    }
+
+    /// Profile of the client window `label`
+    pub fn active(&self, label: &str) -> String {
+        self.state
+            .active
+            .get(label)
+            .cloned()
+            .unwrap_or_else(|| default_profile(label))
+    }
+
+    /// Config of the profile of the client window `label`, created on first use
+    pub fn load(&self, label: &str) -> BotConfig {
+        let path = self.path(&self.active(label));
+        if !path.exists() {
+            // Bring over the config used before profiles existed
+            let legacy_path = self.legacy_dir.join(legacy_file_name(label));
+            let config = if legacy_path.exists() {
+                BotConfig::deserialize_or_default(&legacy_path)
+            } else {
+                BotConfig::default()
+            };
+            config.serialize(&path);
+        }
+        BotConfig::deserialize_or_default(&path)
+    }
+
+    /// Write `config` to the profile of the client window `label`
+    pub fn save(&self, label: &str, config: &BotConfig) {
+        config.serialize(&self.path(&self.active(label)));
+    }
+
+    /// File of the login used by the profile of the client window `label`, which may not exist
+    pub fn credentials_path(&self, label: &str) -> PathBuf {
+        self.credentials_dir.join(self.active(label))
+    }
+
+    /// Create a profile with the default config
+    pub fn create(&self, name: &str) -> Result<(), ProfileError> {
+        let path = self.new_path(name)?;
+        BotConfig::default().serialize(&path);
+        Ok(())
+    }
+
+    /// Create a profile with the config of `from`
+    pub fn duplicate(&self, from: &str, to: &str) -> Result<(), ProfileError> {
+        let from_path = self.existing_path(from)?;
+        let to_path = self.new_path(to)?;
+        fs::copy(from_path, to_path)?;
+        Ok(())
+    }
+
+    /// Rename a profile, the client windows using it keep it
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<(), ProfileError> {
+        let from_path = self.existing_path(from)?;
+        let to_path = self.new_path(to)?;
+        fs::rename(from_path, to_path)?;
+        ignore_missing(fs::rename(
+            self.credentials_dir.join(from),
+            self.credentials_dir.join(to),
+        ))?;
+        for name in self.state.active.values_mut() {
+            if name == from {
+                *name = to.to_string();
+            }
+        }
+        self.save_state()
+    }
+
+    /// Delete a profile no open client window uses. `open` holds the labels of the open windows.
+    pub fn delete(&mut self, name: &str, open: &HashSet<String>) -> Result<(), ProfileError> {
+        let path = self.existing_path(name)?;
+        if open.iter().any(|label| self.active(label) == name) {
+            return Err(ProfileError::InUse(name.to_string()));
+        }
+        fs::remove_file(path)?;
+        ignore_missing(fs::remove_file(self.credentials_dir.join(name)))?;
+        self.state.active.retain(|_, active| active != name);
+        self.save_state()
+    }
+
+    /// Make the client window `label` use the profile `name`
+    pub fn switch(&mut self, label: &str, name: &str) -> Result<(), ProfileError> {
+        self.existing_path(name)?;
+        self.state
+            .active
+            .insert(label.to_string(), name.to_string());
+        self.save_state()
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", name))
+    }
+
+    fn existing_path(&self, name: &str) -> Result<PathBuf, ProfileError> {
+        let path = self.path(validate_name(name)?);
+        if !path.exists() {
+            return Err(ProfileError::NotFound(name.to_string()));
+        }
+        Ok(path)
+    }
+
+    fn new_path(&self, name: &str) -> Result<PathBuf, ProfileError> {
+        let path = self.path(validate_name(name)?);
+        if path.exists() {
+            return Err(ProfileError::AlreadyExists(name.to_string()));
+        }
+        Ok(path)
+    }
+
+    fn save_state(&self) -> Result<(), ProfileError> {
+        let file = File::create(&self.state_path)?;
+        serde_json::to_writer(file, &self.state).map_err(|err| ProfileError::Io(err.to_string()))
+    }
+}
+
+/// Treat a missing file as done with
+fn ignore_missing(result: io::Result<()>) -> io::Result<()> {
+    match result {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Profile a client window uses until another is picked
+fn default_profile(label: &str) -> String {
+    match label {
+        "client" => DEFAULT_PROFILE.to_string(),
+        _ => label.to_string(),
+    }
+}
+
+/// File the config of the client window `label` was kept in, in the working directory
+fn legacy_file_name(label: &str) -> String {
+    match label {
+        "client" => ".botconfig".to_string(),
+        _ => format!(".botconfig.{}", label),
+    }
+}
+
+/// Check that `name` can be used as a file name on every platform
+fn validate_name(name: &str) -> Result<&str, ProfileError> {
+    if is_valid_file_name(name) {
+        Ok(name)
+    } else {
+        Err(ProfileError::InvalidName(name.to_string()))
+    }
+}
+
+/// Whether `name` can be used as a file name on every platform and stays in its directory: up to
+/// `MAX_NAME_LEN` letters, digits, spaces, - and _, but no device name
+pub fn is_valid_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name.trim() == name
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+        && !is_reserved(name)
+}
+
+/// Whether Windows refuses `name` as a file name
+fn is_reserved(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    RESERVED_NAMES.contains(&name.as_str())
+        || RESERVED_PREFIXES.iter().any(|prefix| {
+            name.strip_prefix(prefix).map_or(false, |digit| {
+                matches!(digit, "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9")
+            })
+        })
+}
+
+/// Profile asked for on the command line with `--profile <name>` or `--profile=<name>`
+pub fn profile_arg(mut args: impl Iterator<Item = String>) -> Option<String> {
+    while let Some(arg) = args.next() {
+        if arg == "--profile" {
+            return args.next();
+        }
+        if let Some(name) = arg.strip_prefix("--profile=") {
+            return Some(name.to_string());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, fs, path::PathBuf};
+
+    use super::{profile_arg, validate_name, ProfileError, ProfileStore};
+    use crate::ipc::{BotConfig, BotMode};
+
+    /// Empty directory only used by the test `name`
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("neuz-{}-{}", name, std::process::id()));
+        drop(fs::remove_dir_all(&dir));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("default").is_ok());
+        assert!(validate_name("Mercenary 2_alt-1").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name(" padded ").is_err());
+        assert!(validate_name("../escape").is_err());
+        assert!(validate_name("dir/name").is_err());
+        assert!(validate_name(&"a".repeat(65)).is_err());
+        for reserved in ["CON", "nul", "Aux", "PRN", "COM1", "lpt9"] {
+            assert!(validate_name(reserved).is_err(), "{} is reserved", reserved);
+        }
+        assert!(validate_name("COM").is_ok());
+        assert!(validate_name("COM10").is_ok());
+        assert!(validate_name("console").is_ok());
+    }
+
+    #[test]
+    fn test_profiles() {
+        let dir = temp_dir("profiles");
+        let mut store = ProfileStore::open(&dir).unwrap();
+        let open = ["client".to_string()].into_iter().collect::<HashSet<_>>();
+
+        store.create("farm").unwrap();
+        assert_eq!(
+            store.create("farm"),
+            Err(ProfileError::AlreadyExists("farm".to_string()))
+        );
+        store.duplicate("farm", "support").unwrap();
+        assert_eq!(store.names(), vec!["farm", "support"]);
+
+        // Windows use their default profile until another is picked
+        assert_eq!(store.active("client"), "default");
+        assert_eq!(store.active("client_2"), "client_2");
+        store.switch("client", "farm").unwrap();
+        assert_eq!(
+            store.switch("client", "missing"),
+            Err(ProfileError::NotFound("missing".to_string()))
+        );
+
+        // Renaming keeps the windows on the profile
+        store.rename("farm", "grind").unwrap();
+        assert_eq!(store.active("client"), "grind");
+        assert_eq!(store.names(), vec!["grind", "support"]);
+
+        assert_eq!(
+            store.delete("grind", &open),
+            Err(ProfileError::InUse("grind".to_string()))
+        );
+        store.delete("support", &open).unwrap();
+        assert_eq!(store.names(), vec!["grind"]);
+
+        // The choice is kept for the next runs
+        let store = ProfileStore::open(&dir).unwrap();
+        assert_eq!(store.active("client"), "grind");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_legacy_config() {
+        let dir = temp_dir("legacy");
+        let legacy_dir = dir.join("working");
+        fs::create_dir_all(&legacy_dir).unwrap();
+        let mut config = BotConfig::default();
+        config.set_mode(BotMode::Support);
+        config.serialize(&legacy_dir.join(".botconfig"));
+
+        // The first window gets the historical config, the others their own
+        let store = ProfileStore::open_with_legacy(&dir.join("app"), &legacy_dir).unwrap();
+        assert_eq!(store.load("client").mode(), Some(BotMode::Support));
+        assert_eq!(store.load("client_2").mode(), None);
+        assert_eq!(store.names(), vec!["client_2", "default"]);
+
+        // It's only imported once
+        fs::remove_file(legacy_dir.join(".botconfig")).unwrap();
+        assert_eq!(store.load("client").mode(), Some(BotMode::Support));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_profile_arg() {
+        let args = |args: &[&str]| profile_arg(args.iter().map(|arg| arg.to_string()));
+        assert_eq!(args(&["neuz"]), None);
+        assert_eq!(args(&["neuz", "--profile", "alt"]), Some("alt".to_string()));
+        assert_eq!(args(&["neuz", "--profile=alt"]), Some("alt".to_string()));
+        assert_eq!(args(&["neuz", "--profile"]), None);
+    }
+}
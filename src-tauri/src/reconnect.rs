@@ -0,0 +1,5 @@
+mod credential_store;
+mod reconnector;
+
+pub use self::credential_store::{CredentialStore, Credentials};
+pub use self::reconnector::{ReconnectOutcome, Reconnector};
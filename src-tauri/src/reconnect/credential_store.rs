@@ -0,0 +1,250 @@
+use std::{
+    fmt,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use parking_lot::Mutex;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::profiles::ProfileStore;
+
+/// File holding the key the credentials are encrypted with, shared by every profile
+const KEY_FILE: &str = "credentials.key";
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CredentialError {
+    Io(String),
+    /// The credentials were written with another key, or changed since
+    Decrypt,
+    Format(String),
+}
+
+impl fmt::Display for CredentialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CredentialError::Io(message) => write!(f, "{}", message),
+            CredentialError::Decrypt => write!(f, "can't decrypt the stored credentials"),
+            CredentialError::Format(message) => write!(f, "invalid credentials file: {}", message),
+        }
+    }
+}
+
+impl From<io::Error> for CredentialError {
+    fn from(err: io::Error) -> Self {
+        CredentialError::Io(err.to_string())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Whether credentials are stored, as shown in the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct CredentialsStatus {
+    pub stored: bool,
+    pub error: Option<String>,
+}
+
+/// Login of the profile of a client window, encrypted on disk without going through the OS keyring.
+///
+/// The credentials are kept with the profile and the key in another directory, so that copying
+/// the profiles doesn't bring the key along. It keeps the password out of the config and out of
+/// plain sight, it doesn't protect it from someone who can read the user's files.
+#[derive(Debug, Clone)]
+pub struct CredentialStore {
+    profiles: Arc<Mutex<ProfileStore>>,
+    label: String,
+    key_path: PathBuf,
+}
+
+impl CredentialStore {
+    /// Store of the profile the client window `label` uses, following profile switches. The key
+    /// is kept in `key_dir`.
+    pub fn new(profiles: Arc<Mutex<ProfileStore>>, label: &str, key_dir: &Path) -> Self {
+        Self {
+            profiles,
+            label: label.to_string(),
+            key_path: key_dir.join(KEY_FILE),
+        }
+    }
+
+    pub fn save(&self, credentials: &Credentials) -> Result<(), CredentialError> {
+        let plaintext = serde_json::to_vec(credentials)
+            .map_err(|err| CredentialError::Format(err.to_string()))?;
+        let data = encrypt(&self.key()?, &plaintext);
+        write_private(&self.path(), base64::encode(data).as_bytes())
+    }
+
+    /// Stored credentials, `None` if there are none
+    pub fn load(&self) -> Result<Option<Credentials>, CredentialError> {
+        let data = match fs::read_to_string(&self.path()) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let data =
+            base64::decode(data.trim()).map_err(|err| CredentialError::Format(err.to_string()))?;
+        let plaintext = decrypt(&self.key()?, &data)?;
+        serde_json::from_slice(&plaintext)
+            .map(Some)
+            .map_err(|err| CredentialError::Format(err.to_string()))
+    }
+
+    pub fn clear(&self) -> Result<(), CredentialError> {
+        match fs::remove_file(&self.path()) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn status(&self) -> CredentialsStatus {
+        match self.load() {
+            Ok(credentials) => CredentialsStatus {
+                stored: credentials.is_some(),
+                error: None,
+            },
+            Err(err) => CredentialsStatus {
+                stored: false,
+                error: Some(err.to_string()),
+            },
+        }
+    }
+
+    fn path(&self) -> PathBuf {
+        self.profiles.lock().credentials_path(&self.label)
+    }
+
+    /// Read the key, creating it on first use
+    fn key(&self) -> Result<[u8; KEY_LEN], CredentialError> {
+        match fs::read(&self.key_path) {
+            Ok(data) => data.try_into().map_err(|_| {
+                CredentialError::Format(format!("{} isn't a key", self.key_path.display()))
+            }),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                let mut key = [0; KEY_LEN];
+                rand::thread_rng().fill_bytes(&mut key);
+                write_private(&self.key_path, &key)?;
+                Ok(key)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Write a file only the current user can read, creating its directory if needed
+fn write_private(path: &Path, data: &[u8]) -> Result<(), CredentialError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(data)?;
+    Ok(())
+}
+
+/// Encrypt `plaintext` with a random nonce, which is written in front of the ciphertext
+fn encrypt(key: &[u8; KEY_LEN], plaintext: &[u8]) -> Vec<u8> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let mut nonce = [0; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .expect("plaintext too long");
+    [&nonce[..], &ciphertext].concat()
+}
+
+fn decrypt(key: &[u8; KEY_LEN], data: &[u8]) -> Result<Vec<u8>, CredentialError> {
+    if data.len() < NONCE_LEN {
+        return Err(CredentialError::Decrypt);
+    }
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| CredentialError::Decrypt)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, fs, sync::Arc};
+
+    use parking_lot::Mutex;
+
+    use super::{decrypt, encrypt, CredentialError, CredentialStore, Credentials};
+    use crate::profiles::ProfileStore;
+
+    #[test]
+    fn test_encrypt() {
+        let key = [7; 32];
+        let data = encrypt(&key, b"secret");
+        assert!(!data.windows(6).any(|window| window == b"secret"));
+        assert_eq!(decrypt(&key, &data).unwrap(), b"secret");
+        // Every encryption uses another nonce
+        assert_ne!(encrypt(&key, b"secret"), data);
+
+        assert_eq!(decrypt(&[8; 32], &data), Err(CredentialError::Decrypt));
+        let mut tampered = data.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(decrypt(&key, &tampered), Err(CredentialError::Decrypt));
+    }
+
+    #[test]
+    fn test_credentials_follow_profiles() {
+        let app_dir = std::env::temp_dir().join(format!("neuz-credentials-{}", std::process::id()));
+        drop(fs::remove_dir_all(&app_dir));
+        let profiles = Arc::new(Mutex::new(ProfileStore::open(&app_dir).unwrap()));
+        let store = CredentialStore::new(profiles.clone(), "client", &app_dir.join("keys"));
+
+        profiles.lock().create("main").unwrap();
+        profiles.lock().switch("client", "main").unwrap();
+        store
+            .save(&Credentials {
+                username: "player".to_string(),
+                password: "secret".to_string(),
+            })
+            .unwrap();
+        let path = app_dir.join("credentials").join("main");
+        assert!(!fs::read_to_string(&path).unwrap().contains("secret"));
+        assert_eq!(store.load().unwrap().unwrap().username, "player");
+
+        // Another profile has its own login, a renamed one keeps it
+        profiles.lock().create("alt").unwrap();
+        profiles.lock().switch("client", "alt").unwrap();
+        assert!(store.load().unwrap().is_none());
+        profiles.lock().rename("main", "renamed").unwrap();
+        profiles.lock().switch("client", "renamed").unwrap();
+        assert_eq!(store.load().unwrap().unwrap().username, "player");
+
+        profiles.lock().switch("client", "alt").unwrap();
+        profiles.lock().delete("renamed", &HashSet::new()).unwrap();
+        assert!(!app_dir.join("credentials").join("renamed").exists());
+
+        fs::remove_dir_all(&app_dir).unwrap();
+    }
+}
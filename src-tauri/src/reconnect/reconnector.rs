@@ -0,0 +1,308 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use slog::Logger;
+
+use super::CredentialStore;
+use crate::{
+    ipc::{BotConfig, BotMode, ReconnectConfig, ReconnectScreen, SessionState},
+    platform::{BridgeCommand, CapturePoint, CoordinateSpace, InputSink, JsBridge, KeyMode},
+};
+
+/// Time between two steps of an attempt, the page needs a moment to show the next screen
+const STEP_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReconnectOutcome {
+    /// Back in game, with the mode the bot was running before the session dropped
+    Resumed(Option<BotMode>),
+    /// Every attempt failed
+    GaveUp,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Phase {
+    Idle,
+    /// Waiting for the next attempt
+    Waiting {
+        until: Instant,
+    },
+    /// The page was reloaded, going through the screens up to the game
+    Connecting {
+        started: Instant,
+    },
+}
+
+/// Screens an attempt already went through
+#[derive(Debug, Default, Clone, Copy)]
+struct Progress {
+    logged_in: bool,
+    server_selected: bool,
+    character_selected: bool,
+}
+
+/// Gets a client back in game after its session dropped: reloads the page, logs in with the
+/// stored credentials and picks the configured server and character, retrying with a growing
+/// delay.
+///
+/// The game draws the server and character lists in its canvas, they're recognized from their
+/// snapshots and picked with a click at the configured points. Lists shown in the page, as in the
+/// stand-in page, are picked by their position.
+pub struct Reconnector {
+    bridge: JsBridge,
+    input: Arc<dyn InputSink>,
+    coordinates: CoordinateSpace,
+    store: CredentialStore,
+    phase: Phase,
+    attempt: u32,
+    progress: Progress,
+    last_step: Option<Instant>,
+    resume_mode: Option<BotMode>,
+}
+
+impl Reconnector {
+    pub fn new(
+        bridge: JsBridge,
+        input: Arc<dyn InputSink>,
+        coordinates: CoordinateSpace,
+        store: CredentialStore,
+    ) -> Self {
+        Self {
+            bridge,
+            input,
+            coordinates,
+            store,
+            phase: Phase::Idle,
+            attempt: 0,
+            progress: Progress::default(),
+            last_step: None,
+            resume_mode: None,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !matches!(self.phase, Phase::Idle)
+    }
+
+    /// Start reconnecting when the session of a running bot dropped
+    pub fn on_session_change(
+        &mut self,
+        logger: &Logger,
+        config: &BotConfig,
+        from: SessionState,
+        to: SessionState,
+    ) -> Option<ReconnectOutcome> {
+        if self.is_active() || !config.is_running() || !config.reconnect_config().is_enabled() {
+            return None;
+        }
+        if from == SessionState::InGame
+            && matches!(to, SessionState::Disconnected | SessionState::Login)
+        {
+            slog::warn!(logger, "Session lost, reconnecting"; "state" => to.to_string());
+            self.resume_mode = config.mode();
+            self.attempt = 0;
+            return self.schedule(logger, config.reconnect_config());
+        }
+        None
+    }
+
+    pub fn cancel(&mut self, logger: &Logger) {
+        if self.is_active() {
+            slog::info!(logger, "Reconnect cancelled"; "attempt" => self.attempt);
+            self.phase = Phase::Idle;
+        }
+    }
+
+    /// Go on with the reconnection, returns its outcome once it's over
+    pub fn update(
+        &mut self,
+        logger: &Logger,
+        config: &ReconnectConfig,
+        state: SessionState,
+        screen: Option<ReconnectScreen>,
+    ) -> Option<ReconnectOutcome> {
+        match self.phase {
+            Phase::Idle => None,
+            Phase::Waiting { until } => {
+                if Instant::now() >= until {
+                    self.start_attempt(logger);
+                }
+                None
+            }
+            Phase::Connecting { started } => {
+                if state == SessionState::InGame {
+                    slog::info!(logger, "Reconnected"; "attempt" => self.attempt);
+                    self.phase = Phase::Idle;
+                    return Some(ReconnectOutcome::Resumed(self.resume_mode.take()));
+                }
+                if started.elapsed() >= Duration::from_millis(config.attempt_timeout()) {
+                    return self.fail(logger, config, format!("timed out on {}", state));
+                }
+                if self
+                    .last_step
+                    .map_or(false, |time| time.elapsed() < STEP_INTERVAL)
+                {
+                    return None;
+                }
+                self.last_step = Some(Instant::now());
+                match self.step(config, state, screen) {
+                    Ok(()) => None,
+                    Err(reason) => self.fail(logger, config, reason),
+                }
+            }
+        }
+    }
+
+    /// Wait for the next attempt, unless there is none left
+    fn schedule(&mut self, logger: &Logger, config: &ReconnectConfig) -> Option<ReconnectOutcome> {
+        if self.attempt >= config.max_attempts() {
+            slog::error!(logger, "Giving up reconnecting"; "attempts" => self.attempt);
+            self.phase = Phase::Idle;
+            return Some(ReconnectOutcome::GaveUp);
+        }
+        let delay = retry_delay(config, self.attempt + 1);
+        slog::info!(logger, "Reconnect scheduled"; "attempt" => self.attempt + 1, "delay_ms" => delay.as_millis() as u64);
+        self.phase = Phase::Waiting {
+            until: Instant::now() + delay,
+        };
+        None
+    }
+
+    fn start_attempt(&mut self, logger: &Logger) {
+        self.attempt += 1;
+        self.progress = Progress::default();
+        // Leave the page a moment to reload before the first step
+        self.last_step = Some(Instant::now());
+        slog::info!(logger, "Reconnect attempt"; "attempt" => self.attempt);
+        if let Err(err) = self.bridge.reload() {
+            slog::warn!(logger, "Failed to reload the client"; "error" => err.to_string());
+        }
+        self.phase = Phase::Connecting {
+            started: Instant::now(),
+        };
+    }
+
+    fn fail(
+        &mut self,
+        logger: &Logger,
+        config: &ReconnectConfig,
+        reason: String,
+    ) -> Option<ReconnectOutcome> {
+        slog::warn!(logger, "Reconnect attempt failed"; "attempt" => self.attempt, "reason" => reason);
+        self.schedule(logger, config)
+    }
+
+    /// Go through the screen currently shown, `screen` being the one recognized in the canvas.
+    /// Loading screens are waited out.
+    fn step(
+        &mut self,
+        config: &ReconnectConfig,
+        state: SessionState,
+        screen: Option<ReconnectScreen>,
+    ) -> Result<(), String> {
+        match state {
+            SessionState::Login if !self.progress.logged_in => {
+                let credentials = self
+                    .store
+                    .load()
+                    .map_err(|err| err.to_string())?
+                    .ok_or_else(|| "no stored credentials".to_string())?;
+                self.bridge
+                    .send_and_wait(&BridgeCommand::Login {
+                        username: credentials.username,
+                        password: credentials.password,
+                    })
+                    .map_err(|err| format!("login failed: {}", err))?;
+                self.progress.logged_in = true;
+            }
+            SessionState::CharacterSelect => match screen {
+                Some(ReconnectScreen::Server) if !self.progress.server_selected => {
+                    self.choose(config, ReconnectScreen::Server)?;
+                    self.progress.server_selected = true;
+                }
+                Some(ReconnectScreen::Character) if !self.progress.character_selected => {
+                    self.choose(config, ReconnectScreen::Character)?;
+                    self.progress.character_selected = true;
+                }
+                Some(_) => {}
+                None => self.select_in_page(config)?,
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Click the configured point of a screen drawn in the canvas, then confirm
+    fn choose(&self, config: &ReconnectConfig, screen: ReconnectScreen) -> Result<(), String> {
+        let canvas_screen = config.screen(screen);
+        let point = canvas_screen
+            .and_then(|screen| screen.click_point())
+            .ok_or_else(|| format!("no point to click on the {} screen", screen))?;
+        let fail = |err| format!("{} failed: {}", screen, err);
+        let pos = self
+            .coordinates
+            .to_input(CapturePoint(point))
+            .map_err(fail)?;
+        self.input.click_at(pos).map_err(fail)?;
+        if let Some(key) = canvas_screen.and_then(|screen| screen.confirm_key()) {
+            self.input.send_key(key, KeyMode::Press).map_err(fail)?;
+        }
+        Ok(())
+    }
+
+    /// Pick the server then the character in lists shown in the page
+    fn select_in_page(&mut self, config: &ReconnectConfig) -> Result<(), String> {
+        if !self.progress.server_selected && self.select("server", config.server_index())? {
+            self.progress.server_selected = true;
+        } else if !self.progress.character_selected
+            && self.select("character", config.character_index())?
+        {
+            self.progress.character_selected = true;
+        }
+        Ok(())
+    }
+
+    /// Pick an entry of a list, returns whether the list is shown
+    fn select(&self, list: &str, index: u32) -> Result<bool, String> {
+        self.bridge
+            .query(&BridgeCommand::SelectEntry {
+                list: list.to_string(),
+                index,
+            })
+            .map_err(|err| format!("{} selection failed: {}", list, err))
+    }
+}
+
+/// Time to wait before the attempt number `attempt`, counted from 1
+fn retry_delay(config: &ReconnectConfig, attempt: u32) -> Duration {
+    let delay = config.initial_delay() as f64
+        * (config.backoff_factor() as f64).powi(attempt.saturating_sub(1) as i32);
+    Duration::from_millis(delay.min(config.max_delay() as f64) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::retry_delay;
+    use crate::ipc::ReconnectConfig;
+
+    #[test]
+    fn test_retry_delay() {
+        let config: ReconnectConfig =
+            serde_json::from_str(r#"{"initial_delay":1000,"max_delay":5000,"backoff_factor":2.0}"#)
+                .unwrap();
+        let delays = (1..=5)
+            .map(|attempt| retry_delay(&config, attempt))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [1000, 2000, 4000, 5000, 5000].map(Duration::from_millis)
+        );
+
+        let config = ReconnectConfig::default();
+        assert_eq!(retry_delay(&config, 1), Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,252 @@
+use std::time::{Duration, Instant};
+
+use crate::ipc::{FrontendInfo, SessionConfig, SlotType, StopCondition, StopTrigger};
+
+/// Keeps track of the current bot session and checks configured stop conditions.
+pub struct SessionMonitor {
+    start_time: Instant,
+    kills_at_start: Option<u32>,
+    missclick_resets_at_start: Option<u32>,
+    consecutive_deaths: u32,
+    kills_at_last_death: Option<u32>,
+    was_alive: bool,
+    exhausted_slot_types: Vec<SlotType>,
+    fired_conditions: Vec<usize>,
+}
+
+impl Default for SessionMonitor {
+    fn default() -> Self {
+        Self {
+            start_time: Instant::now(),
+            kills_at_start: None,
+            missclick_resets_at_start: None,
+            consecutive_deaths: 0,
+            kills_at_last_death: None,
+            was_alive: true,
+            exhausted_slot_types: vec![],
+            fired_conditions: vec![],
+        }
+    }
+}
+
+impl SessionMonitor {
+    /// Start a new session
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Time since the session started
+    pub fn elapsed(&self) -> Duration {
+        self.start_time.elapsed()
+    }
+
+    /// Update session counters from the latest frontend info
+    pub fn update(&mut self, info: &FrontendInfo, is_alive: bool) {
+        let kills = info.kill_count();
+        self.kills_at_start.get_or_insert(kills);
+        self.missclick_resets_at_start
+            .get_or_insert(info.missclick_reset_count());
+
+        // Count deaths without any kill in between
+        if self.was_alive && !is_alive {
+            if self.kills_at_last_death == Some(kills) {
+                self.consecutive_deaths += 1;
+            } else {
+                self.consecutive_deaths = 1;
+            }
+            self.kills_at_last_death = Some(kills);
+        }
+        self.was_alive = is_alive;
+
+        if let Some(slot_type) = info.exhausted_slot_type() {
+            if !self.exhausted_slot_types.contains(&slot_type) {
+                self.exhausted_slot_types.push(slot_type);
+            }
+        }
+    }
+
+    fn is_reached(&self, trigger: &StopTrigger, info: &FrontendInfo) -> bool {
+        match trigger {
+            StopTrigger::ElapsedTime(minutes) => {
+                self.start_time.elapsed().as_secs() >= minutes * 60
+            }
+            StopTrigger::KillCount(count) => {
+                info.kill_count()
+                    .saturating_sub(self.kills_at_start.unwrap_or(0))
+                    >= *count
+            }
+            StopTrigger::SlotTypeExhausted(slot_type) => {
+                self.exhausted_slot_types.contains(slot_type)
+            }
+            StopTrigger::ConsecutiveDeaths(count) => self.consecutive_deaths >= *count,
+            StopTrigger::MissclickResets(count) => {
+                info.missclick_reset_count()
+                    .saturating_sub(self.missclick_resets_at_start.unwrap_or(0))
+                    >= *count
+            }
+        }
+    }
+
+    /// Get the first stop condition that was reached, each condition fires once per session
+    pub fn check(&mut self, config: &SessionConfig, info: &FrontendInfo) -> Option<StopCondition> {
+        let conditions = config.stop_conditions();
+        let index = (0..conditions.len()).find(|index| {
+            let condition = &conditions[*index];
+            condition.is_enabled()
+                && !self.fired_conditions.contains(index)
+                && self.is_reached(condition.trigger(), info)
+        })?;
+        self.fired_conditions.push(index);
+        Some(conditions[index].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::SessionMonitor;
+    use crate::ipc::{BotMode, FrontendInfo, SessionConfig, SlotType, StopAction, StopTrigger};
+
+    fn config(conditions: &str) -> SessionConfig {
+        serde_json::from_str(&format!(r#"{{"stop_conditions":{}}}"#, conditions)).unwrap()
+    }
+
+    fn info(kills: u32, missclick_resets: u32) -> FrontendInfo {
+        let mut info = FrontendInfo::default();
+        info.set_kill_count(kills);
+        info.set_missclick_reset_count(missclick_resets);
+        info
+    }
+
+    /// Trigger of the condition reached after `update`, if any
+    fn reached(
+        monitor: &mut SessionMonitor,
+        config: &SessionConfig,
+        info: &FrontendInfo,
+    ) -> Option<StopTrigger> {
+        monitor.update(info, true);
+        monitor
+            .check(config, info)
+            .map(|condition| condition.trigger().clone())
+    }
+
+    #[test]
+    fn test_kill_count() {
+        let config = config(r#"[{"trigger":{"KillCount":3},"action":"Stop"}]"#);
+        let mut monitor = SessionMonitor::default();
+
+        // Kills are counted from the start of the session
+        assert_eq!(reached(&mut monitor, &config, &info(10, 0)), None);
+        assert_eq!(reached(&mut monitor, &config, &info(12, 0)), None);
+        // Reached on the threshold itself, not after it
+        assert_eq!(
+            reached(&mut monitor, &config, &info(13, 0)),
+            Some(StopTrigger::KillCount(3))
+        );
+        // Each condition fires once per session
+        assert_eq!(reached(&mut monitor, &config, &info(14, 0)), None);
+
+        monitor.reset();
+        assert_eq!(reached(&mut monitor, &config, &info(14, 0)), None);
+        assert_eq!(
+            reached(&mut monitor, &config, &info(17, 0)),
+            Some(StopTrigger::KillCount(3))
+        );
+    }
+
+    #[test]
+    fn test_elapsed_time() {
+        let config = config(r#"[{"trigger":{"ElapsedTime":10},"action":"Stop"}]"#);
+        let mut monitor = SessionMonitor::default();
+        assert_eq!(reached(&mut monitor, &config, &info(0, 0)), None);
+
+        monitor.start_time = Instant::now() - Duration::from_secs(10 * 60 - 1);
+        assert_eq!(reached(&mut monitor, &config, &info(0, 0)), None);
+        monitor.start_time = Instant::now() - Duration::from_secs(10 * 60);
+        assert_eq!(
+            reached(&mut monitor, &config, &info(0, 0)),
+            Some(StopTrigger::ElapsedTime(10))
+        );
+    }
+
+    #[test]
+    fn test_missclick_resets() {
+        let config = config(r#"[{"trigger":{"MissclickResets":2},"action":"Stop"}]"#);
+        let mut monitor = SessionMonitor::default();
+        assert_eq!(reached(&mut monitor, &config, &info(0, 5)), None);
+        assert_eq!(reached(&mut monitor, &config, &info(0, 6)), None);
+        assert_eq!(
+            reached(&mut monitor, &config, &info(0, 7)),
+            Some(StopTrigger::MissclickResets(2))
+        );
+    }
+
+    #[test]
+    fn test_consecutive_deaths() {
+        let config = config(r#"[{"trigger":{"ConsecutiveDeaths":2},"action":"Logout"}]"#);
+        let mut monitor = SessionMonitor::default();
+        let die = |monitor: &mut SessionMonitor, info: &FrontendInfo| {
+            monitor.update(info, true);
+            monitor.update(info, false);
+            monitor
+                .check(&config, info)
+                .map(|condition| condition.action().clone())
+        };
+
+        assert_eq!(die(&mut monitor, &info(1, 0)), None);
+        // A kill in between starts counting again
+        assert_eq!(die(&mut monitor, &info(2, 0)), None);
+        // Staying dead is a single death
+        monitor.update(&info(2, 0), false);
+        assert!(monitor.check(&config, &info(2, 0)).is_none());
+        assert_eq!(die(&mut monitor, &info(2, 0)), Some(StopAction::Logout));
+    }
+
+    #[test]
+    fn test_slot_type_exhausted() {
+        let config = config(r#"[{"trigger":{"SlotTypeExhausted":"Pill"},"action":"Stop"}]"#);
+        let mut monitor = SessionMonitor::default();
+        let mut exhausted = info(0, 0);
+        exhausted.set_exhausted_slot_type(Some(SlotType::Food));
+        assert_eq!(reached(&mut monitor, &config, &exhausted), None);
+
+        exhausted.set_exhausted_slot_type(Some(SlotType::Pill));
+        assert_eq!(
+            reached(&mut monitor, &config, &exhausted),
+            Some(StopTrigger::SlotTypeExhausted(SlotType::Pill))
+        );
+    }
+
+    #[test]
+    fn test_first_reached_condition() {
+        let config = config(
+            r#"[
+                {"trigger":{"KillCount":1},"action":"Stop","enabled":false},
+                {"trigger":{"KillCount":5},"action":"Stop"},
+                {"trigger":{"KillCount":2},"action":{"SwitchMode":"Support"}},
+                {"trigger":{"KillCount":1},"action":"Logout"}
+            ]"#,
+        );
+        let mut monitor = SessionMonitor::default();
+        monitor.update(&info(0, 0), true);
+
+        // The first reached condition in the list wins, disabled ones are skipped
+        let actions = (0..3)
+            .map(|_| {
+                monitor
+                    .check(&config, &info(5, 0))
+                    .map(|condition| condition.action().clone())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            [
+                Some(StopAction::Stop),
+                Some(StopAction::SwitchMode(BotMode::Support)),
+                Some(StopAction::Logout),
+            ]
+        );
+        assert!(monitor.check(&config, &info(5, 0)).is_none());
+    }
+}
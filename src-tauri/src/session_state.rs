@@ -0,0 +1,510 @@
+use std::{
+    mem,
+    time::{Duration, Instant},
+};
+
+use libscreenshot::ImageBuffer;
+use serde::Deserialize;
+
+use crate::{
+    image_analyzer::ImageAnalyzer,
+    ipc::{ReconnectConfig, ReconnectScreen, SessionState},
+    platform::{BridgeCommand, JsBridge},
+};
+
+/// Frames a new state has to be seen in before it's reported, so a single odd frame doesn't pause
+/// the bot
+const CONFIRMATION_FRAMES: u32 = 3;
+
+/// Time between two queries of the page state
+const PAGE_STATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Share of the sampled pixels that must have the same color for a frame to count as blank
+const BLANK_RATIO: f32 = 0.95;
+
+/// Page state reported by the bridge
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct PageState {
+    /// The page finished loading
+    pub ready: bool,
+    pub has_canvas: bool,
+    /// The login form is shown
+    pub has_password_input: bool,
+    /// A server or character list is shown. The stand-in page draws them in the page, the real
+    /// client in its canvas where they're recognized from their snapshots.
+    #[serde(default)]
+    pub has_selection_list: bool,
+    /// The browser is offline or a dialog tells that the connection is gone
+    pub disconnected: bool,
+}
+
+/// Columns and rows of the grid of pixels a screen signature is made of
+const SIGNATURE_GRID: (u32, u32) = (16, 12);
+
+/// Largest difference of a color channel for a pixel to match its signature
+const SIGNATURE_TOLERANCE: u8 = 24;
+
+/// Share of the pixels of a signature that must match for a frame to show its screen, some
+/// parts of the screens are animated
+const SIGNATURE_RATIO: f32 = 0.85;
+
+/// What the captured frame tells about the session
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScreenFeatures {
+    /// One of the HP, MP or FP bars can be read
+    pub stat_tray_visible: bool,
+    /// The frame is almost a single color, as between two screens
+    pub blank: bool,
+    /// Screen drawn in the canvas that matches its snapshot from the reconnect config
+    pub canvas_screen: Option<ReconnectScreen>,
+}
+
+impl ScreenFeatures {
+    pub fn detect(image: &ImageAnalyzer, config: &ReconnectConfig) -> Self {
+        let signature = image.image().map(screen_signature);
+        Self {
+            stat_tray_visible: image.client_stats.is_stat_tray_visible(),
+            blank: image.image().map_or(false, is_blank),
+            canvas_screen: signature.and_then(|signature| {
+                [
+                    ReconnectScreen::Disconnected,
+                    ReconnectScreen::Server,
+                    ReconnectScreen::Character,
+                ]
+                .into_iter()
+                .find(|screen| {
+                    config
+                        .screen(*screen)
+                        .and_then(|screen| screen.signature())
+                        .map_or(false, |expected| matches_signature(&signature, expected))
+                })
+            }),
+        }
+    }
+}
+
+/// Colors of a grid of pixels spread over the frame, the same cells for any frame size
+pub fn screen_signature(image: &ImageBuffer) -> Vec<[u8; 3]> {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return vec![];
+    }
+    let (columns, rows) = SIGNATURE_GRID;
+    (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .map(|(column, row)| {
+            let pixel = image.get_pixel(
+                (2 * column + 1) * width / (2 * columns),
+                (2 * row + 1) * height / (2 * rows),
+            );
+            [pixel[0], pixel[1], pixel[2]]
+        })
+        .collect()
+}
+
+fn matches_signature(signature: &[[u8; 3]], expected: &[[u8; 3]]) -> bool {
+    if expected.is_empty() || signature.len() != expected.len() {
+        return false;
+    }
+    let matching = signature
+        .iter()
+        .zip(expected)
+        .filter(|(pixel, expected)| {
+            pixel
+                .iter()
+                .zip(expected.iter())
+                .all(|(a, b)| a.abs_diff(*b) <= SIGNATURE_TOLERANCE)
+        })
+        .count();
+    matching as f32 >= expected.len() as f32 * SIGNATURE_RATIO
+}
+
+/// Check whether most pixels have the color of the center of the frame
+fn is_blank(image: &ImageBuffer) -> bool {
+    const STEP: usize = 16;
+    const TOLERANCE: u8 = 8;
+
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return true;
+    }
+    let reference = image.get_pixel(width / 2, height / 2).0;
+    let (mut total, mut matching) = (0, 0);
+    for y in (0..height).step_by(STEP) {
+        for x in (0..width).step_by(STEP) {
+            let pixel = image.get_pixel(x, y).0;
+            total += 1;
+            if pixel
+                .iter()
+                .zip(reference.iter())
+                .take(3)
+                .all(|(a, b)| a.abs_diff(*b) <= TOLERANCE)
+            {
+                matching += 1;
+            }
+        }
+    }
+    matching as f32 >= total as f32 * BLANK_RATIO
+}
+
+/// Tell the screen shown from a single frame.
+///
+/// The login form is only visible in the page. The server and character lists and the
+/// disconnection are shown in the page by the stand-in page, and drawn in the canvas by the game
+/// where they're recognized from their snapshots. A game with a closed stat tray can't be told
+/// apart from the other screens, so an unknown screen keeps counting as in game once the game
+/// was entered.
+pub fn classify(
+    previous: SessionState,
+    page: Option<&PageState>,
+    screen: ScreenFeatures,
+) -> SessionState {
+    if let Some(page) = page {
+        if page.disconnected {
+            return SessionState::Disconnected;
+        }
+        if !page.ready || !page.has_canvas {
+            return SessionState::Loading;
+        }
+        if page.has_password_input {
+            return SessionState::Login;
+        }
+    }
+    match screen.canvas_screen {
+        Some(ReconnectScreen::Disconnected) => return SessionState::Disconnected,
+        Some(ReconnectScreen::Server | ReconnectScreen::Character) => {
+            return SessionState::CharacterSelect
+        }
+        None => {}
+    }
+    if page.map_or(false, |page| page.has_selection_list) {
+        return SessionState::CharacterSelect;
+    }
+    if screen.stat_tray_visible {
+        return SessionState::InGame;
+    }
+    if screen.blank {
+        return SessionState::Loading;
+    }
+    match previous {
+        SessionState::InGame => SessionState::InGame,
+        _ => SessionState::Unknown,
+    }
+}
+
+/// Whether a client on an unknown screen may be in game with a closed stat tray, the only screen
+/// where the stat tray can be opened from
+pub fn may_be_in_game(state: SessionState, page: Option<&PageState>) -> bool {
+    state == SessionState::Unknown
+        && page.map_or(false, |page| {
+            page.ready
+                && page.has_canvas
+                && !page.has_password_input
+                && !page.has_selection_list
+                && !page.disconnected
+        })
+}
+
+/// Follows the session state of a client from its page and captured frames
+pub struct SessionTracker {
+    bridge: JsBridge,
+    page: Option<PageState>,
+    page_time: Option<Instant>,
+    screen: ScreenFeatures,
+    state: SessionState,
+    candidate: SessionState,
+    candidate_frames: u32,
+}
+
+impl SessionTracker {
+    pub fn new(bridge: JsBridge) -> Self {
+        Self {
+            bridge,
+            page: None,
+            page_time: None,
+            screen: ScreenFeatures::default(),
+            state: SessionState::default(),
+            candidate: SessionState::default(),
+            candidate_frames: 0,
+        }
+    }
+
+    pub fn state(&self) -> SessionState {
+        self.state
+    }
+
+    /// What the last frame showed
+    pub fn screen(&self) -> ScreenFeatures {
+        self.screen
+    }
+
+    /// See [`may_be_in_game`]
+    pub fn may_be_in_game(&self) -> bool {
+        may_be_in_game(self.state, self.page.as_ref())
+    }
+
+    /// Classify the current frame, returns the previous state when the state changed
+    pub fn update(
+        &mut self,
+        image: &ImageAnalyzer,
+        config: &ReconnectConfig,
+    ) -> Option<SessionState> {
+        if self
+            .page_time
+            .map_or(true, |time| time.elapsed() >= PAGE_STATE_INTERVAL)
+        {
+            // The page doesn't answer while it reloads
+            self.page = self.bridge.query(&BridgeCommand::PageState).ok();
+            self.page_time = Some(Instant::now());
+        }
+
+        self.screen = ScreenFeatures::detect(image, config);
+        let observed = classify(self.state, self.page.as_ref(), self.screen);
+        self.confirm(observed)
+    }
+
+    fn confirm(&mut self, observed: SessionState) -> Option<SessionState> {
+        if observed == self.state {
+            self.candidate_frames = 0;
+            return None;
+        }
+        if observed != self.candidate {
+            self.candidate = observed;
+            self.candidate_frames = 0;
+        }
+        self.candidate_frames += 1;
+        if self.candidate_frames < CONFIRMATION_FRAMES {
+            return None;
+        }
+        self.candidate_frames = 0;
+        Some(mem::replace(&mut self.state, observed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libscreenshot::ImageBuffer;
+
+    use super::{
+        classify, is_blank, matches_signature, may_be_in_game, screen_signature, PageState,
+        ScreenFeatures,
+    };
+    use crate::ipc::{ReconnectScreen, SessionState};
+
+    #[test]
+    fn test_classify() {
+        let page = PageState {
+            ready: true,
+            has_canvas: true,
+            ..PageState::default()
+        };
+        let game = ScreenFeatures {
+            stat_tray_visible: true,
+            ..ScreenFeatures::default()
+        };
+        let other = ScreenFeatures::default();
+
+        assert_eq!(
+            classify(SessionState::Loading, Some(&page), game),
+            SessionState::InGame
+        );
+        // Nothing tells a screen without stat tray is character select
+        assert_eq!(
+            classify(SessionState::Loading, Some(&page), other),
+            SessionState::Unknown
+        );
+        assert_eq!(
+            classify(
+                SessionState::Login,
+                Some(&PageState {
+                    has_selection_list: true,
+                    ..page
+                }),
+                other
+            ),
+            SessionState::CharacterSelect
+        );
+        // A closed stat tray doesn't leave the game
+        assert_eq!(
+            classify(SessionState::InGame, Some(&page), other),
+            SessionState::InGame
+        );
+        assert_eq!(
+            classify(
+                SessionState::InGame,
+                Some(&page),
+                ScreenFeatures {
+                    blank: true,
+                    ..other
+                }
+            ),
+            SessionState::Loading
+        );
+        assert_eq!(
+            classify(
+                SessionState::Loading,
+                Some(&PageState {
+                    has_password_input: true,
+                    ..page
+                }),
+                other
+            ),
+            SessionState::Login
+        );
+        assert_eq!(
+            classify(
+                SessionState::InGame,
+                Some(&PageState {
+                    disconnected: true,
+                    ..page
+                }),
+                game
+            ),
+            SessionState::Disconnected
+        );
+        assert_eq!(
+            classify(SessionState::InGame, Some(&PageState::default()), game),
+            SessionState::Loading
+        );
+        assert_eq!(
+            classify(SessionState::Login, None, game),
+            SessionState::InGame
+        );
+    }
+
+    #[test]
+    fn test_started_in_game_with_closed_stat_tray() {
+        let page = PageState {
+            ready: true,
+            has_canvas: true,
+            ..PageState::default()
+        };
+        let closed = ScreenFeatures::default();
+        let opened = ScreenFeatures {
+            stat_tray_visible: true,
+            ..ScreenFeatures::default()
+        };
+
+        // Nothing tells the game from the frame, but the stat tray may be opened
+        let state = classify(SessionState::Loading, Some(&page), closed);
+        assert_eq!(state, SessionState::Unknown);
+        assert!(may_be_in_game(state, Some(&page)));
+        // Once opened the game is recognized
+        assert_eq!(classify(state, Some(&page), opened), SessionState::InGame);
+
+        // Never from the login form, the lists, a disconnection or without page
+        for other in [
+            PageState {
+                has_password_input: true,
+                ..page
+            },
+            PageState {
+                has_selection_list: true,
+                ..page
+            },
+            PageState {
+                disconnected: true,
+                ..page
+            },
+            PageState {
+                has_canvas: false,
+                ..page
+            },
+        ] {
+            assert!(!may_be_in_game(SessionState::Unknown, Some(&other)));
+        }
+        assert!(!may_be_in_game(SessionState::Unknown, None));
+        assert!(!may_be_in_game(SessionState::Login, Some(&page)));
+    }
+
+    #[test]
+    fn test_canvas_screens() {
+        let page = PageState {
+            ready: true,
+            has_canvas: true,
+            ..PageState::default()
+        };
+        let screen = |screen| ScreenFeatures {
+            canvas_screen: Some(screen),
+            ..ScreenFeatures::default()
+        };
+
+        for list in [ReconnectScreen::Server, ReconnectScreen::Character] {
+            assert_eq!(
+                classify(SessionState::Loading, Some(&page), screen(list)),
+                SessionState::CharacterSelect
+            );
+        }
+        assert_eq!(
+            classify(
+                SessionState::InGame,
+                Some(&page),
+                screen(ReconnectScreen::Disconnected)
+            ),
+            SessionState::Disconnected
+        );
+        // The page still tells the login form and loading screens
+        assert_eq!(
+            classify(
+                SessionState::InGame,
+                Some(&PageState {
+                    has_password_input: true,
+                    ..page
+                }),
+                screen(ReconnectScreen::Disconnected)
+            ),
+            SessionState::Login
+        );
+        assert_eq!(
+            classify(SessionState::Login, None, screen(ReconnectScreen::Server)),
+            SessionState::CharacterSelect
+        );
+    }
+
+    #[test]
+    fn test_screen_signature() {
+        let mut image = ImageBuffer::from_pixel(320, 240, image::Rgba([20, 40, 60, 255]));
+        for x in 0..160 {
+            for y in 0..240 {
+                image.put_pixel(x, y, image::Rgba([200, 180, 40, 255]));
+            }
+        }
+        let signature = screen_signature(&image);
+        assert_eq!(signature.len(), 16 * 12);
+        assert!(matches_signature(&signature, &signature));
+
+        // Slightly other colors and a few changed cells, as in an animated screen
+        let mut animated = image.clone();
+        for pixel in animated.pixels_mut() {
+            pixel[0] = pixel[0].saturating_add(10);
+        }
+        for x in 0..60 {
+            for y in 0..40 {
+                animated.put_pixel(x, y, image::Rgba([0, 0, 0, 255]));
+            }
+        }
+        assert!(matches_signature(&screen_signature(&animated), &signature));
+
+        // The same screen captured at another size
+        let resized = image::imageops::resize(&image, 160, 120, image::imageops::Nearest);
+        assert!(matches_signature(&screen_signature(&resized), &signature));
+
+        // Another screen
+        let other = ImageBuffer::from_pixel(320, 240, image::Rgba([20, 40, 60, 255]));
+        assert!(!matches_signature(&screen_signature(&other), &signature));
+        assert!(!matches_signature(&signature, &[]));
+    }
+
+    #[test]
+    fn test_is_blank() {
+        let mut image = ImageBuffer::from_pixel(64, 64, image::Rgba([0, 0, 0, 255]));
+        assert!(is_blank(&image));
+
+        for x in 0..32 {
+            for y in 0..64 {
+                image.put_pixel(x, y, image::Rgba([200, 100, 0, 255]));
+            }
+        }
+        assert!(!is_blank(&image));
+    }
+}
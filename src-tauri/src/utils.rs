@@ -1,5 +1,7 @@
 mod datetime;
+mod template;
 mod timer;
 
 pub use self::datetime::DateTime;
+pub use self::template::{render_template, TemplateContext};
 pub use self::timer::Timer;
@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use rand::{prelude::SliceRandom, Rng};
+
+use super::DateTime;
+
+/// Values available to message templates
+pub struct TemplateContext {
+    pub elapsed: Duration,
+}
+
+/// Render a message template.
+///
+/// Supported placeholders are `{time}` (local time), `{elapsed}` (time since the bot started)
+/// and `{a|b|c}` (random pick among alternatives). Unknown placeholders are kept as is.
+pub fn render_template<R: Rng>(template: &str, context: &TemplateContext, rng: &mut R) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        // Keep unterminated braces as they are
+        let end = match after.find('}') {
+            Some(end) => end,
+            None => {
+                result.push_str(&rest[start..]);
+                return result;
+            }
+        };
+
+        let placeholder = &after[..end];
+        match placeholder {
+            "time" => result.push_str(&chrono::Local::now().format("%H:%M").to_string()),
+            "elapsed" => result.push_str(&DateTime::format_time(context.elapsed)),
+            _ if placeholder.contains('|') => {
+                let alternatives = placeholder.split('|').collect::<Vec<_>>();
+                result.push_str(alternatives.choose(rng).unwrap_or(&""));
+            }
+            _ => {
+                result.push('{');
+                result.push_str(placeholder);
+                result.push('}');
+            }
+        }
+        rest = &after[end + 1..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::{render_template, TemplateContext};
+
+    #[test]
+    fn test_render_template() {
+        let context = TemplateContext {
+            elapsed: Duration::from_secs(3723),
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+
+        assert_eq!(render_template("plain text", &context, &mut rng), "plain text");
+        assert_eq!(
+            render_template("up for {elapsed}", &context, &mut rng),
+            "up for 01:02:03"
+        );
+        assert_eq!(render_template("{unknown} {", &context, &mut rng), "{unknown} {");
+
+        let rendered = render_template("S> {swords|shields}!", &context, &mut rng);
+        assert!(rendered == "S> swords!" || rendered == "S> shields!");
+    }
+}
@@ -10,6 +10,7 @@ import LauncherBackground2 from './assets/launcher_background.png'
 import MissionControl from './MissionControl'
 import { randomNumberInRange } from './components/utils/RandomInt'
 import useModal from './components/utils/UseModal'
+import { CLIENT_URL } from './components/utils/ClientUrl'
 import { getVersion } from '@tauri-apps/api/app'
 import YesNoModal from './components/YesNoModal'
 
@@ -70,7 +71,7 @@ const Launcher = ({ className }: Props) => {
     const launch = () => {
         const webview = new WebviewWindow(`client`, {
             title: 'Flyff Universe',
-            url: 'https://universe.flyff.com/play',
+            url: CLIENT_URL,
             center: true,
             resizable: false,
         })
@@ -81,7 +82,7 @@ const Launcher = ({ className }: Props) => {
 
             if (!hasEnteredMainLoop) {
                 enterMainLoop()
-                invoke('start_bot')
+                invoke('start_bot', { label: 'client' })
             }
         })
 
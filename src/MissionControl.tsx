@@ -1,5 +1,8 @@
 import styled from "styled-components"
+import { invoke } from '@tauri-apps/api'
 import { listen, emit } from '@tauri-apps/api/event'
+import { WebviewWindow } from '@tauri-apps/api/window'
+import { sendNotification } from '@tauri-apps/api/notification'
 import { useEffect, useState } from "react"
 import { isNil } from 'lodash'
 
@@ -12,15 +15,30 @@ import ImageFarm from './assets/btn_leveling.png'
 import ImageSupport from './assets/btn_full_support.png'
 import ImageShout from './assets/btn_shout.png'
 
-import { BotConfigModel, ModeModel } from './models/BotConfig'
+import { BotConfigModel, CanvasScreenModel, ChatMessageModel, ModeModel, ReconnectScreenModel } from './models/BotConfig'
 import FarmingConfig from "./components/behaviors/FarmingConfig"
 import SupportConfig from "./components/behaviors/SupportConfig"
 import ShoutConfig from "./components/behaviors/ShoutConfig"
 import Footer from "./components/Footer"
-import { FrontendInfoModel } from "./models/FrontendInfo"
+import { CaptureBenchmarkModel, CredentialsStatusModel, FrontendInfoModel, ProfileListModel, RecordingResultModel, StateTransitionModel } from "./models/FrontendInfo"
+import Modal from "./components/Modal"
+import useModal from "./components/utils/UseModal"
+import ConfigTable from "./components/config/ConfigTable"
+import ConfigTableRow from "./components/config/ConfigTableRow"
+import ConfigLabel from "./components/config/ConfigLabel"
+import StopConditionList from "./components/config/StopConditionList"
+import ChatRuleList from "./components/config/ChatRuleList"
+import ExclusionZoneList from "./components/config/ExclusionZoneList"
+import BooleanSlider from "./components/config/BooleanSlider"
+import TextInput from "./components/config/TextInput"
+import NumericInput from "./components/config/NumericInput"
+import { clientEvent } from "./components/utils/ClientEvent"
+import { CLIENT_URL } from "./components/utils/ClientUrl"
 
 type Bounds = {x: number, y: number, w: number, h: number}
 
+const MAX_STATE_TRANSITIONS = 20
+
 type Props = {
     className?: string,
     currentVersion?: string,
@@ -28,50 +46,198 @@ type Props = {
 }
 
 const MissionControl = ({ className, lastVersion, currentVersion }: Props) => {
+    // Labels of the client windows, the first one is opened by the launcher
+    const [clients, setClients] = useState(['client'])
+    const [client, setClient] = useState('client')
     const [imageData, setImageData] = useState({ data: '', width: 0, height: 0 })
     const [info, setInfo] = useState<FrontendInfoModel | null>(null);
     const [config, setConfig] = useState<BotConfigModel | null>(null);
+    const [transitions, setTransitions] = useState<StateTransitionModel[]>([])
+    const sessionModal = useModal()
+    const chatModal = useModal()
+    const busModal = useModal()
+    const stateLogModal = useModal()
+    const exclusionModal = useModal()
+    const recorderModal = useModal()
+    const inputModal = useModal()
+    const reconnectModal = useModal()
+    const profilesModal = useModal()
+    const [profiles, setProfiles] = useState<ProfileListModel | null>(null)
+    const [profileName, setProfileName] = useState("")
+    const [profileError, setProfileError] = useState<string | null>(null)
+    const [username, setUsername] = useState("")
+    const [password, setPassword] = useState("")
+    const [credentialsStatus, setCredentialsStatus] = useState<CredentialsStatusModel | null>(null)
+    const [recordingName, setRecordingName] = useState("")
+    const [isRecording, setIsRecording] = useState(false)
+    const [recordingResult, setRecordingResult] = useState<RecordingResultModel | null>(null)
+    const [captureBenchmark, setCaptureBenchmark] = useState<CaptureBenchmarkModel[] | null>(null)
 
     useEffect(() => {
-        listen<string>('bot_visualizer_update', event => {
-            const payload = event.payload as unknown as string[]
-            const data = payload[0]
-            const width = Number(payload[1])
-            const height = Number(payload[2])
-            setImageData({ data, width, height })
+        const unlisteners = [
+            listen<string>('bot_visualizer_update', event => {
+                const payload = event.payload as unknown as string[]
+                const data = payload[0]
+                const width = Number(payload[1])
+                const height = Number(payload[2])
+                setImageData({ data, width, height })
+            }),
+
+            listen(clientEvent(client, 'bot_info_s2c'), event => {
+                const payload = event.payload as FrontendInfoModel
+                setInfo(payload)
+            }),
+
+            listen(clientEvent(client, 'bot_config_s2c'), event => {
+                const payload = event.payload as BotConfigModel
+                setConfig(payload)
+            }),
+
+            listen(clientEvent(client, 'chat_alert_s2c'), event => {
+                const message = event.payload as ChatMessageModel
+                sendNotification({ title: `Neuz | ${client} | ${message.channel} message`, body: message.sender ? `${message.sender}: ${message.text}` : message.text })
+            }),
+
+            listen(clientEvent(client, 'movement_recorded_s2c'), event => {
+                setRecordingResult(event.payload as RecordingResultModel)
+                setIsRecording(false)
+            }),
+
+            listen(clientEvent(client, 'capture_benchmark_s2c'), event => {
+                setCaptureBenchmark(event.payload as CaptureBenchmarkModel[])
+            }),
+
+            listen(clientEvent(client, 'credentials_s2c'), event => {
+                setCredentialsStatus(event.payload as CredentialsStatusModel)
+            }),
+
+            listen(clientEvent(client, 'state_transitions_s2c'), event => {
+                const payload = event.payload as StateTransitionModel[]
+                setTransitions(transitions => [...transitions, ...payload].slice(-MAX_STATE_TRANSITIONS))
+            }),
+        ]
+
+        // Forget the state of the previous client and ask the bot for the current one
+        setInfo(null)
+        setConfig(null)
+        setTransitions([])
+        setRecordingResult(null)
+        setIsRecording(false)
+        setCaptureBenchmark(null)
+        setCredentialsStatus(null)
+        setProfiles(null)
+        setProfileError(null)
+        invoke<ProfileListModel>('list_profiles', { label: client }).then(setProfiles)
+        Promise.all(unlisteners).then(() => emit(clientEvent(client, 'bot_state_request')))
+
+        return () => {
+            unlisteners.forEach(unlisten => unlisten.then(f => f()))
+        }
+    }, [client])
+
+    const addClient = () => {
+        // Same label as before for the same slot, so the client keeps its config file
+        let index = 2
+        while (clients.includes(`client-${index}`)) index++
+        const label = `client-${index}`
+        const webview = new WebviewWindow(label, {
+            title: `Flyff Universe (${index})`,
+            url: CLIENT_URL,
+            center: true,
+            resizable: false,
         })
 
-        listen('bot_info_s2c', event => {
-            const payload = event.payload as FrontendInfoModel
-            setInfo(payload)
+        webview.once('tauri://created', function () {
+            webview.show()
+            setClients(clients => [...clients, label])
+            setClient(label)
+            invoke('start_bot', { label })
         })
 
-        listen('bot_config_s2c', event => {
-            console.log(event.payload)
-            const payload = event.payload as BotConfigModel
-            setConfig(payload)
+        webview.once('tauri://close-requested', function () {
+            webview.close()
+            setClients(clients => clients.filter(other => other !== label))
+            setClient(current => current === label ? 'client' : current)
         })
-    }, [])
+    }
 
     const handleToggle = () => {
         if (!config) return
         const newConfig = { ...config, is_running: !config.is_running }
-        emit('bot_config_c2s', newConfig)
+        emit(clientEvent(client, 'bot_config_c2s'), newConfig)
     }
 
     const handleTabSelect = (mode: ModeModel) => {
         const newConfig = { ...config, mode }
-        emit('bot_config_c2s', newConfig)
+        emit(clientEvent(client, 'bot_config_c2s'), newConfig)
+    }
+
+    const handleRecording = () => {
+        if (isRecording) {
+            emit(clientEvent(client, 'record_movement_stop'), recordingName)
+        } else {
+            setRecordingResult(null)
+            emit(clientEvent(client, 'record_movement_start'))
+        }
+        setIsRecording(!isRecording)
+    }
+
+    const saveCredentials = () => {
+        emit(clientEvent(client, 'save_credentials_c2s'), { username, password })
+        setPassword("")
+    }
+
+    const runProfileCommand = (command: string, args: Record<string, string>) => {
+        invoke<ProfileListModel>(command, { label: client, ...args })
+            .then(list => {
+                setProfiles(list)
+                setProfileName("")
+                setProfileError(null)
+            })
+            .catch(error => setProfileError(String(error)))
     }
 
     const makeConfigUpdater = (key: string) => <T,>(patchedConfig: T) => {
         const newConfig = { ...config, [key]: patchedConfig }
-        emit('bot_config_c2s', newConfig)
+        emit(clientEvent(client, 'bot_config_c2s'), newConfig)
+    }
+
+    // Rows of a reconnect screen the game draws in its canvas
+    const canvasScreenRows = (screen: ReconnectScreenModel, name: string, clicked: boolean) => {
+        const key = `${screen.toLowerCase()}_screen`
+        const canvasScreen: CanvasScreenModel = config.reconnect_config?.[key] ?? {}
+        const updateScreen = (patch: CanvasScreenModel) => makeConfigUpdater('reconnect_config')({ ...config.reconnect_config, [key]: { ...canvasScreen, ...patch } })
+        return <>
+            <ConfigTableRow
+                label={<ConfigLabel name={`${name} snapshot`} helpText={`Open the ${name.toLowerCase()} screen in the game, then take a snapshot so the bot recognizes it. ${canvasScreen.signature ? 'A snapshot is stored.' : 'No snapshot yet.'}`} />}
+                item={<button onClick={() => emit(clientEvent(client, 'snapshot_reconnect_screen'), screen)}>📸</button>}
+            />
+            {clicked && <>
+                <ConfigTableRow
+                    label={<ConfigLabel name={`${name} click X`} helpText="Where to click on this screen to pick the entry, in pixels of the captured frame from its left edge." />}
+                    item={<NumericInput min={0} unit="px" value={canvasScreen.click_x} onChange={value => updateScreen({ click_x: value })} />}
+                />
+                <ConfigTableRow
+                    label={<ConfigLabel name={`${name} click Y`} helpText="Where to click on this screen to pick the entry, in pixels of the captured frame from its top edge." />}
+                    item={<NumericInput min={0} unit="px" value={canvasScreen.click_y} onChange={value => updateScreen({ click_y: value })} />}
+                />
+                <ConfigTableRow
+                    label={<ConfigLabel name={`${name} confirm key`} helpText="Key pressed after the click to confirm the choice, like Enter. Leave empty to only click." />}
+                    item={<TextInput value={canvasScreen.confirm_key ?? ''} onChange={value => updateScreen({ confirm_key: value })} />}
+                />
+            </>}
+        </>
     }
 
     return (
         <div className={className}>
             <div className="vstack">
+                <div className="clients">
+                    {clients.map((label, index) => (
+                        <button key={label} className={`btn sm ${label === client ? 'active' : ''}`} onClick={() => setClient(label)}>Client {index + 1}</button>
+                    ))}
+                    <button className="btn sm" onClick={addClient}>Add client ➕</button>
+                </div>
                 {config && (
                     <>
                         <TabControl activeMode={config.mode} onSelect={handleTabSelect}>
@@ -83,10 +249,219 @@ const MissionControl = ({ className, lastVersion, currentVersion }: Props) => {
                             {config?.mode === 'Farming' && (<FarmingConfig running={config.is_running} isCurrentMode={config.mode == "Farming"} info={info} config={config.farming_config} onChange={makeConfigUpdater('farming_config')} />)}
                             {config?.mode === 'Support' && (<SupportConfig running={config.is_running} isCurrentMode={config.mode == "Support"} info={info} config={config.support_config} onChange={makeConfigUpdater('support_config')} />)}
                             {config?.mode === 'AutoShout' && (<ShoutConfig config={config.shout_config} onChange={makeConfigUpdater('shout_config')} />)}
+                            <Modal isShowing={sessionModal.isShown} hide={sessionModal.close} title={<h4>Session goals</h4>} body={
+                                <ConfigTable>
+                                    <ConfigTableRow
+                                        layout="v"
+                                        label={<ConfigLabel name="Stop conditions" helpText="The first condition reached during a session triggers its action: stop the bot, switch to another mode or logout." />}
+                                        item={<StopConditionList conditions={config.session_config?.stop_conditions ?? []} onChange={value => makeConfigUpdater('session_config')({ ...config.session_config, stop_conditions: value })} />}
+                                    />
+                                </ConfigTable>
+                            }/>
+                            <Modal isShowing={chatModal.isShown} hide={chatModal.close} title={<h4>Chat rules</h4>} body={
+                                <ConfigTable>
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Enabled" helpText="Read the chat while the bot is running and apply the rules below to incoming messages." />}
+                                        item={<BooleanSlider value={config.chat_config?.enabled ?? false} onChange={value => makeConfigUpdater('chat_config')({ ...config.chat_config, enabled: value })} />}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Character name" helpText="Your character name. Your own messages never trigger rules." />}
+                                        item={<TextInput value={config.chat_config?.character_name} onChange={value => makeConfigUpdater('chat_config')({ ...config.chat_config, character_name: value })} />}
+                                    />
+                                    <ConfigTableRow
+                                        layout="v"
+                                        label={<ConfigLabel name="Rules" helpText="Every rule whose keyword (or regex) matches an incoming message triggers its action: reply on the same channel, pause the bot or show an alert. Replies support {sender}, {time}, {elapsed} and {a|b|c}. A rule triggers at most once every 30 seconds." />}
+                                        item={<ChatRuleList rules={config.chat_config?.rules ?? []} onChange={value => makeConfigUpdater('chat_config')({ ...config.chat_config, rules: value })} />}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Pause duration" helpText="How long a pausing rule keeps the bot idle. 0 waits for you to resume it. Default : 300000" />}
+                                        item={<NumericInput unit="ms" value={config.chat_config?.pause_duration} onChange={value => makeConfigUpdater('chat_config')({ ...config.chat_config, pause_duration: value })} />}
+                                    />
+                                    {info?.chat_panel_found === false && <div>No chat panel found in the page, the client may draw its chat in the game view. Rules can't see any message.</div>}
+                                </ConfigTable>
+                            }/>
+                            <Modal isShowing={busModal.isShown} hide={busModal.close} title={<h4>Instances</h4>} body={
+                                <ConfigTable>
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Enabled" helpText="Exchange events with the other neuz instances running on this computer." />}
+                                        item={<BooleanSlider value={config.bus_config?.enabled ?? false} onChange={value => makeConfigUpdater('bus_config')({ ...config.bus_config, enabled: value })} />}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Leader" helpText="The leader shares its HP, target and death with the other instances, so supporters heal based on the leader's real HP." />}
+                                        item={<BooleanSlider value={config.bus_config?.role === 'Leader'} onChange={value => makeConfigUpdater('bus_config')({ ...config.bus_config, role: value ? 'Leader' : 'Follower' })} />}
+                                    />
+                                    {config.bus_config?.role === 'Leader' && (<>
+                                        <ConfigTableRow
+                                            label={<ConfigLabel name="Low HP warning" helpText="Warn the other instances when HP drops below this value. Default : 50" />}
+                                            item={<NumericInput unit="%" value={config.bus_config?.hp_low_threshold} onChange={value => makeConfigUpdater('bus_config')({ ...config.bus_config, hp_low_threshold: value })} />}
+                                        />
+                                        <ConfigTableRow
+                                            label={<ConfigLabel name="Buff request interval" helpText="Ask supporters for buffs regularly. 0 to disable." />}
+                                            item={<NumericInput unit="ms" value={config.bus_config?.buff_request_interval} onChange={value => makeConfigUpdater('bus_config')({ ...config.bus_config, buff_request_interval: value })} />}
+                                        />
+                                    </>)}
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Pause all" helpText="Stop this instance and every connected one." />}
+                                        item={<button onClick={() => emit('pause_all')}>⏸️</button>}
+                                    />
+                                </ConfigTable>
+                            }/>
+                            <Modal isShowing={exclusionModal.isShown} hide={exclusionModal.close} title={<h4>Exclusion zones</h4>} body={
+                                <ConfigTable>
+                                    <ConfigTableRow
+                                        layout="v"
+                                        label={<ConfigLabel name="Zones" helpText="Screen areas in pixels where monsters are never detected nor clicked. Use them for open panels like the inventory, quest tracker or chat." />}
+                                        item={<ExclusionZoneList zones={config.exclusion_zones ?? []} onChange={value => emit(clientEvent(client, 'bot_config_c2s'), { ...config, exclusion_zones: value })} />}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Snapshot open panels" helpText="Rotates the camera a little and adds every area that didn't move with the scene, which means the panels currently open." />}
+                                        item={<button onClick={() => emit(clientEvent(client, 'snapshot_exclusion_zones'))}>📸</button>}
+                                    />
+                                </ConfigTable>
+                            }/>
+                            <Modal isShowing={inputModal.isShown} hide={inputModal.close} title={<h4>Input and capture</h4>} body={
+                                <ConfigTable>
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Native input (X11)" helpText="Linux only. Sends keys and clicks to the game window through XTest instead of the page, as a real keyboard and mouse would. The game window takes the focus while the bot runs." />}
+                                        item={<BooleanSlider value={config.input_backend === 'X11'} onChange={value => emit(clientEvent(client, 'bot_config_c2s'), { ...config, input_backend: value ? 'X11' : 'Webview' })} />}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Canvas capture" helpText="Reads the frames from the game canvas instead of taking screenshots of the window. Works when the window is covered and doesn't include the window borders." />}
+                                        item={<BooleanSlider value={config.capture_backend === 'Canvas'} onChange={value => emit(clientEvent(client, 'bot_config_c2s'), { ...config, capture_backend: value ? 'Canvas' : 'Window' })} />}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Benchmark capture" helpText="Takes a few frames with each capture method and shows how long they took on average." />}
+                                        item={<button onClick={() => emit(clientEvent(client, 'benchmark_capture'))}>⏱️</button>}
+                                    />
+                                    {captureBenchmark && captureBenchmark.map(result => (
+                                        <div key={result.backend}>{result.backend}: {isNil(result.average_ms) ? 'unavailable' : `${result.average_ms.toFixed(1)} ms`}{result.failures > 0 && ` (${result.failures} failed)`}</div>
+                                    ))}
+                                </ConfigTable>
+                            }/>
+                            <Modal isShowing={reconnectModal.isShown} hide={reconnectModal.close} title={<h4>Reconnect</h4>} body={
+                                <ConfigTable>
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Enabled" helpText="When the session drops while the bot runs, reload the game, log in with the credentials below, pick the server and character and resume the current mode. The server, character and disconnected screens are drawn by the game: take a snapshot of each and set where to click, they can't be recognized otherwise." />}
+                                        item={<BooleanSlider value={config.reconnect_config?.enabled ?? false} onChange={value => makeConfigUpdater('reconnect_config')({ ...config.reconnect_config, enabled: value })} />}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Attempts" helpText="Attempts before giving up and stopping the bot. Default : 5" />}
+                                        item={<NumericInput min={1} value={config.reconnect_config?.max_attempts} onChange={value => makeConfigUpdater('reconnect_config')({ ...config.reconnect_config, max_attempts: value })} />}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="First delay" helpText="Time before the first attempt. Default : 10000" />}
+                                        item={<NumericInput unit="ms" value={config.reconnect_config?.initial_delay} onChange={value => makeConfigUpdater('reconnect_config')({ ...config.reconnect_config, initial_delay: value })} />}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Backoff factor" helpText="The delay is multiplied by this factor after each failed attempt. Default : 2" />}
+                                        item={<NumericInput min={1} unit="x" value={config.reconnect_config?.backoff_factor} onChange={value => makeConfigUpdater('reconnect_config')({ ...config.reconnect_config, backoff_factor: value })} />}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Max delay" helpText="Longest time between two attempts. Default : 300000" />}
+                                        item={<NumericInput unit="ms" value={config.reconnect_config?.max_delay} onChange={value => makeConfigUpdater('reconnect_config')({ ...config.reconnect_config, max_delay: value })} />}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Attempt timeout" helpText="Time an attempt may take to get back in game. Default : 120000" />}
+                                        item={<NumericInput unit="ms" value={config.reconnect_config?.attempt_timeout} onChange={value => makeConfigUpdater('reconnect_config')({ ...config.reconnect_config, attempt_timeout: value })} />}
+                                    />
+                                    {canvasScreenRows('Server', 'Server', true)}
+                                    {canvasScreenRows('Character', 'Character', true)}
+                                    {canvasScreenRows('Disconnected', 'Disconnected', false)}
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Server list position" helpText="Position of the server in a server list shown in the page, like in the stand-in page, the first one is 0. The game draws its list in the canvas, use the server snapshot and click instead." />}
+                                        item={<NumericInput min={0} value={config.reconnect_config?.server_index} onChange={value => makeConfigUpdater('reconnect_config')({ ...config.reconnect_config, server_index: value })} />}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Character list position" helpText="Position of the character in a character list shown in the page, like in the stand-in page, the first one is 0. The game draws its list in the canvas, use the character snapshot and click instead." />}
+                                        item={<NumericInput min={0} value={config.reconnect_config?.character_index} onChange={value => makeConfigUpdater('reconnect_config')({ ...config.reconnect_config, character_index: value })} />}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Username" helpText="Account of the current profile, stored encrypted in the app data directory." />}
+                                        item={<TextInput value={username} onChange={setUsername} />}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Password" helpText="Stored encrypted on this computer, never in the config. Save an empty password to forget the credentials." />}
+                                        item={<TextInput type="password" value={password} onChange={setPassword} />}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Save credentials" helpText="Replaces the stored credentials of this client." />}
+                                        item={<button disabled={!username && !!password} onClick={saveCredentials}>💾</button>}
+                                    />
+                                    {credentialsStatus && <div>{credentialsStatus.error ? credentialsStatus.error : credentialsStatus.stored ? 'Credentials stored' : 'No credentials stored'}</div>}
+                                </ConfigTable>
+                            }/>
+                            <Modal isShowing={recorderModal.isShown} hide={recorderModal.close} title={<h4>Movement recorder</h4>} body={
+                                <ConfigTable>
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Name" helpText="File name of the script written to the scripts folder, made of letters, digits, spaces, - and _. Use it as search script or in your own scripts." />}
+                                        item={<TextInput value={recordingName} onChange={setRecordingName} />}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name={isRecording ? "Stop and save" : "Record"} helpText="Records the keys you press in the game window while the bot stays idle. Pauses are kept as waits. Refused while the bot runs with X11 input, the page can't tell its keys from yours." />}
+                                        item={<button disabled={isRecording && !recordingName} onClick={handleRecording}>{isRecording ? '⏹️' : '⏺️'}</button>}
+                                    />
+                                    {recordingResult && <div>{recordingResult.error ? recordingResult.name ? `Failed to save ${recordingResult.name}: ${recordingResult.error}` : `Can't record: ${recordingResult.error}` : `Saved ${recordingResult.name}`}</div>}
+                                </ConfigTable>
+                            }/>
+                            <Modal isShowing={profilesModal.isShown} hide={profilesModal.close} title={<h4>Profiles</h4>} body={
+                                <ConfigTable>
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Profile" helpText="Config used by this client, kept for the next runs. Switching stops the bot and loads the config of the profile." />}
+                                        item={
+                                            <div className="clients">
+                                                {profiles?.profiles.map(name => (
+                                                    <button key={name} className={`btn sm ${name === profiles.active ? 'active' : ''}`} onClick={() => runProfileCommand('switch_profile', { name })}>{name}</button>
+                                                ))}
+                                            </div>
+                                        }
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Name" helpText="Letters, digits, spaces, - and _. Used by the actions below." />}
+                                        item={<TextInput value={profileName} onChange={setProfileName} />}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="New" helpText="Creates a profile with the default config." />}
+                                        item={<button disabled={!profileName} onClick={() => runProfileCommand('create_profile', { name: profileName })}>➕</button>}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Duplicate" helpText="Creates a profile with the config of the current profile." />}
+                                        item={<button disabled={!profileName || !profiles} onClick={() => runProfileCommand('duplicate_profile', { from: profiles?.active ?? '', to: profileName })}>📄</button>}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Rename" helpText="Gives the current profile this name." />}
+                                        item={<button disabled={!profileName || !profiles} onClick={() => runProfileCommand('rename_profile', { from: profiles?.active ?? '', to: profileName })}>✏️</button>}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Delete" helpText="Deletes the profile with this name. Profiles used by an open client can't be deleted." />}
+                                        item={<button disabled={!profileName} onClick={() => runProfileCommand('delete_profile', { name: profileName })}>🗑️</button>}
+                                    />
+                                    {profileError && <div>{profileError}</div>}
+                                </ConfigTable>
+                            }/>
+                            <Modal isShowing={stateLogModal.isShown} hide={stateLogModal.close} title={<h4>State log</h4>} body={
+                                <div className="state-log">
+                                    {transitions.length === 0 && <div>No state change yet</div>}
+                                    {[...transitions].reverse().map((transition, index) => (
+                                        <div key={index}>
+                                            {new Date(transition.timestamp).toLocaleTimeString()} {transition.from} → {transition.to}{transition.reason && ` (${transition.reason})`}
+                                        </div>
+                                    ))}
+                                </div>
+                            }/>
+                            <button className="btn sm" onClick={sessionModal.open}>Session goals ⏱️</button>
+                            <button className="btn sm" onClick={chatModal.open}>Chat rules 💬</button>
+                            <button className="btn sm" onClick={busModal.open}>Instances 🔗</button>
+                            <button className="btn sm" onClick={exclusionModal.open}>Exclusion zones 🚫</button>
+                            <button className="btn sm" onClick={recorderModal.open}>Recorder ⏺️</button>
+                            <button className="btn sm" onClick={inputModal.open}>Input & capture ⌨️</button>
+                            <button className="btn sm" onClick={reconnectModal.open}>Reconnect 🔌</button>
+                            <button className="btn sm" onClick={profilesModal.open}>Profile: {profiles?.active ?? '…'} 🗂️</button>
+                            <button className="btn sm" onClick={stateLogModal.open}>State log 📜</button>
                         </div>
                     </>
                 )}
                 <div className="footer">
+                    {info?.is_paused && config?.is_running && <div className="btn" onClick={() => emit(clientEvent(client, 'resume_bot'))}>Resume</div>}
                     {!isNil(config?.mode) && <div className="btn" onClick={handleToggle}>{config?.is_running ? 'Disengage' : 'Engage'}</div>}
                 </div>
             </div>
@@ -164,6 +539,24 @@ export default styled(MissionControl)`
         }
     }
 
+    & .clients {
+        display: flex;
+        gap: .5rem;
+
+        & .active {
+            outline: 1px solid white;
+        }
+    }
+
+    & .state-log {
+        color: white;
+        display: flex;
+        flex-direction: column;
+        gap: .25rem;
+        max-height: 60vh;
+        overflow-y: auto;
+    }
+
     & .container {
         overflow: hidden;
         position: relative;
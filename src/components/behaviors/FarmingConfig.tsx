@@ -6,12 +6,16 @@ import ConfigPanel from '../config/ConfigPanel'
 import ConfigTable from '../config/ConfigTable'
 import ConfigTableRow from '../config/ConfigTableRow'
 import NumericInput from '../config/NumericInput'
+import TextInput from '../config/TextInput'
 import ColorSelector from '../config/ColorSelector'
+import HuntingSpotList from '../config/HuntingSpotList'
+import TargetFilterList from '../config/TargetFilterList'
+import Select from 'react-select'
 
 import SlotBar from '../SlotBar'
-import { createSlotBars, FarmingConfigModel } from '../../models/BotConfig'
+import { createSlotBars, FarmingConfigModel, ScoringWeightsModel, scoringPresets } from '../../models/BotConfig'
 import { useRef } from 'react'
-import { FrontendInfoModel } from '../../models/FrontendInfo'
+import { FrontendInfoModel, sessionStateLabels } from '../../models/FrontendInfo'
 import Modal from '../Modal'
 import useModal from '../utils/UseModal'
 import YesNoModal from '../YesNoModal'
@@ -27,6 +31,18 @@ type Props = {
     isCurrentMode: boolean,
 }
 
+const presetOptions = scoringPresets.map(preset => ({ value: preset, label: preset }))
+
+const defaultWeights: ScoringWeightsModel = { distance: 1, aggressive: 10, name_width: 0, seen_time: 0, avoid_proximity: -1 }
+
+const weightNames: [keyof ScoringWeightsModel, string, string][] = [
+    ['distance', 'Distance weight', 'Closer mobs score higher.'],
+    ['aggressive', 'Aggressive weight', 'Aggressive mobs score higher.'],
+    ['name_width', 'Name width weight', 'Wider names score higher, usually higher level or bigger mobs. Use a negative value to prefer small mobs.'],
+    ['seen_time', 'Seen time weight', 'Mobs that stayed in view for a while score higher.'],
+    ['avoid_proximity', 'Avoid proximity weight', 'Mobs close to avoided areas score higher. Use a negative value to keep away from them.'],
+]
+
 const FarmingConfig = ({ className, info, config, onChange, running, isCurrentMode }: Props) => {
     const statsModal = useModal()
     const debugModal = useModal()
@@ -34,6 +50,7 @@ const FarmingConfig = ({ className, info, config, onChange, running, isCurrentMo
     const mobsColorsDebugModal = useModal(mobsNameDebugModal)
     const resetSlotYesNo = useModal(debugModal)
     const obstacleAvoidanceDebugModal = useModal(debugModal)
+    const targetSelectionDebugModal = useModal(debugModal)
 
     const selectedMobType = useRef(0)
 
@@ -48,6 +65,17 @@ const FarmingConfig = ({ className, info, config, onChange, running, isCurrentMo
         'max_mobs_name_width': 180,
         'circle_pattern_rotation_duration': 30,
         'min_hp_attack': 30,
+        'leash_radius': 5000,
+        'aoe_pull_count': 3,
+        'aoe_cluster_radius': 150,
+        'aoe_gather_timeout': 6000,
+        'kiting_min_distance': 120,
+        'kiting_retreat_duration': 600,
+        'kiting_cooldown': 2000,
+        'stuck_duration': 1500,
+        'attack_timeout': 120000,
+        'flying_altitude': 1500,
+        'spot_dwell_time': 600000,
     }
 
     DefaultValuesChecker(config, defaultValues, onChange)
@@ -57,7 +85,7 @@ const FarmingConfig = ({ className, info, config, onChange, running, isCurrentMo
         () => onChange({...config, ...{aggressive_mobs_colors: defaultValues['aggressive_mobs_colors'], aggressive_tolerence: defaultValues['aggressive_tolerence']} })
     ]
 
-    let botState = running? info?.is_running? !info?.is_alive? "dead" : config.is_stop_fighting? "manual" : info.is_attacking? "fighting" : "searching" : "ready" : "idle"
+    let botState = running? info?.session_state && info.session_state !== "InGame"? sessionStateLabels[info.session_state] : info?.is_running? !info?.is_alive? "dead" : config.is_stop_fighting? "manual" : info.is_attacking? "fighting" : "searching" : "ready" : "idle"
 
     // StopWatchs
     let botStopWatch = useStopWatch(), searchMobStopWatch = useStopWatch(), fightStopWatch = useStopWatch()
@@ -84,6 +112,10 @@ const FarmingConfig = ({ className, info, config, onChange, running, isCurrentMo
                         label={<ConfigLabel name="Obstacle avoidance settings" helpText="" />}
                         item={<button onClick={obstacleAvoidanceDebugModal.open}>⚙️</button>}
                     />
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Target selection settings" helpText="" />}
+                        item={<button onClick={targetSelectionDebugModal.open}>⚙️</button>}
+                    />
                     <ConfigTableRow
                         label={<ConfigLabel name="Reset all slots" helpText="" />}
                         item={<button onClick={resetSlotYesNo.open}>⚙️</button>}
@@ -108,6 +140,26 @@ const FarmingConfig = ({ className, info, config, onChange, running, isCurrentMo
                     />
                 </ConfigTable>
             }/>
+            <Modal isShowing={targetSelectionDebugModal.isShown} hide={targetSelectionDebugModal.close} title={<h4>Target selection settings</h4>} body={
+                <ConfigTable>
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Preset" helpText="How visible mobs are scored, the best scored one is attacked. AggressiveFirst is the historical behavior." />}
+                        item={<div style={{ minWidth: '10rem', color: 'black' }}><Select options={presetOptions} value={presetOptions.find(x => x.value === (config.scoring_preset ?? 'AggressiveFirst'))}
+                            onChange={option => option && onChange?.({ ...config, scoring_preset: option.value })} /></div>}
+                    />
+                    {config.scoring_preset === 'Custom' && weightNames.map(([key, name, helpText]) => (
+                        <ConfigTableRow key={key}
+                            label={<ConfigLabel name={name} helpText={helpText} />}
+                            item={<NumericInput value={(config.scoring_weights ?? defaultWeights)[key]} onChange={value => onChange?.({ ...config, scoring_weights: { ...(config.scoring_weights ?? defaultWeights), [key]: value ?? 0 } })} />}
+                        />
+                    ))}
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Filters" helpText="Mobs not matching every filter are never attacked. Mobs in avoided areas (last kill, missclicks) are always skipped." />}
+                        item={<TargetFilterList filters={config.target_filters ?? ['NotAvoided', 'PassiveMinHp']} onChange={value => onChange?.({ ...config, target_filters: value })} />}
+                    />
+                </ConfigTable>
+            }/>
             <Modal isShowing={obstacleAvoidanceDebugModal.isShown} hide={obstacleAvoidanceDebugModal.close} title={<h4>Obstacle avoidance settings</h4>} body={
                 <ConfigTable>
                     <ConfigTableRow
@@ -128,6 +180,15 @@ const FarmingConfig = ({ className, info, config, onChange, running, isCurrentMo
                         label={<ConfigLabel name="Obstacle avoidance max try" helpText="After this number of try it'll abort attack and search for another target" />}
                         item={<NumericInput unit='#' value={config.obstacle_avoidance_max_try} onChange={value => onChange({...config, obstacle_avoidance_max_try: value})} />}
                     />
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Visual stuck detection" helpText="Compare consecutive frames to notice when the character stops moving while walking back, searching or running to a monster, and get it free earlier. Also used by the support assist." />}
+                        item={<BooleanSlider value={config.stuck_detection_enabled ?? false} onChange={value => onChange?.({ ...config, stuck_detection_enabled: value })} />}
+                    />
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Stuck duration" helpText="Time the scene has to stay still before we consider being stuck. Default : 1500" />}
+                        item={<NumericInput unit='ms' value={config.stuck_duration} onChange={value => onChange({...config, stuck_duration: value})} />}
+                    />
                 </ConfigTable>
             }/>
             <Modal isShowing={mobsNameDebugModal.isShown} hide={mobsNameDebugModal.close} title={<h4>Mobs detection</h4>} body={
@@ -169,10 +230,81 @@ const FarmingConfig = ({ className, info, config, onChange, running, isCurrentMo
                         label={<ConfigLabel name="Circle pattern duration" helpText="The bot will try to move in a circle pattern to find target. Value of 0 will stay in place. Lower value to increase circle. Default : 30" />}
                         item={<NumericInput value={config.circle_pattern_rotation_duration} onChange={value => onChange?.({ ...config, circle_pattern_rotation_duration: value })} />}
                     />
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Search script" helpText="Name of a movement script from the scripts folder (letters, digits, spaces, - and _), played instead of the circle pattern when no monster is around. Leave empty to use the circle pattern" />}
+                        item={<TextInput value={config.search_script} onChange={value => onChange?.({ ...config, search_script: value })} />}
+                    />
                     <ConfigTableRow
                         label={<ConfigLabel name="Min HP percent to attack" helpText="Minimum required HP value to attack a monster (only for passive ones)" />}
                         item={<NumericInput unit='%' value={config.min_hp_attack} onChange={value => onChange({...config, min_hp_attack: value})} />}
                     />
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Attack timeout" helpText="Give up on a monster after fighting it this long. 0 to never give up. Not used while fighting is stopped. Default : 120000" />}
+                        item={<NumericInput unit='ms' value={config.attack_timeout} onChange={value => onChange?.({ ...config, attack_timeout: value })} />}
+                    />
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Stay in area" helpText="Remember where farming started and walk back when the character drifts too far away." />}
+                        item={<BooleanSlider value={config.leash_enabled ?? false} onChange={value => onChange?.({ ...config, leash_enabled: value })} />}
+                    />
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Leash radius" helpText="Max distance from the starting point, in milliseconds of walking. The circle pattern stays within half of it. Default : 5000" />}
+                        item={<NumericInput unit='ms' value={config.leash_radius} onChange={value => onChange?.({ ...config, leash_radius: value })} />}
+                    />
+                    <ConfigTableRow
+                        label={<ConfigLabel name="AoE pull & burst" helpText="Tag several monsters with your attack skill, wait for them to gather around you and kill them with your AoE skills." />}
+                        item={<BooleanSlider value={config.farming_strategy === 'AoePull'} onChange={value => onChange?.({ ...config, farming_strategy: value ? 'AoePull' : 'SingleTarget' })} />}
+                    />
+                    {config.farming_strategy === 'AoePull' && (<>
+                        <ConfigTableRow
+                            label={<ConfigLabel name="Pull count" helpText="Amount of monsters to tag before using AoE skills. Default : 3" />}
+                            item={<NumericInput unit='#' value={config.aoe_pull_count} onChange={value => onChange?.({ ...config, aoe_pull_count: value })} />}
+                        />
+                        <ConfigTableRow
+                            label={<ConfigLabel name="AoE radius" helpText="Distance around your character in which monsters are considered in range of AoE skills. Default : 150" />}
+                            item={<NumericInput unit='px' value={config.aoe_cluster_radius} onChange={value => onChange?.({ ...config, aoe_cluster_radius: value })} />}
+                        />
+                        <ConfigTableRow
+                            label={<ConfigLabel name="Gather timeout" helpText="Max time to wait for tagged monsters to come to you. Default : 6000" />}
+                            item={<NumericInput unit='ms' value={config.aoe_gather_timeout} onChange={value => onChange?.({ ...config, aoe_gather_timeout: value })} />}
+                        />
+                    </>)}
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Kiting" helpText="For ranged classes: back off or sidestep when an aggressive monster gets too close, then resume attacking." />}
+                        item={<BooleanSlider value={config.kiting_enabled ?? false} onChange={value => onChange?.({ ...config, kiting_enabled: value })} />}
+                    />
+                    {config.kiting_enabled && (<>
+                        <ConfigTableRow
+                            label={<ConfigLabel name="Kiting distance" helpText="Distance between your character and an aggressive monster's name under which the bot steps away. Default : 120" />}
+                            item={<NumericInput unit='px' value={config.kiting_min_distance} onChange={value => onChange?.({ ...config, kiting_min_distance: value })} />}
+                        />
+                        <ConfigTableRow
+                            label={<ConfigLabel name="Retreat duration" helpText="How long to walk away. Default : 600" />}
+                            item={<NumericInput unit='ms' value={config.kiting_retreat_duration} onChange={value => onChange?.({ ...config, kiting_retreat_duration: value })} />}
+                        />
+                        <ConfigTableRow
+                            label={<ConfigLabel name="Kiting cooldown" helpText="Minimum time between two retreats. Default : 2000" />}
+                            item={<NumericInput unit='ms' value={config.kiting_cooldown} onChange={value => onChange?.({ ...config, kiting_cooldown: value })} />}
+                        />
+                    </>)}
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Fly between spots" helpText="Farm each hunting spot for a while, then mount the board from the flying slot and fly to the next one. Needs a Board slot." />}
+                        item={<BooleanSlider value={config.flying_enabled ?? false} onChange={value => onChange?.({ ...config, flying_enabled: value })} />}
+                    />
+                    {config.flying_enabled && (<>
+                        <ConfigTableRow
+                            label={<ConfigLabel name="Time per spot" helpText="How long to farm a hunting spot before flying to the next one. Default : 600000" />}
+                            item={<NumericInput unit='ms' value={config.spot_dwell_time} onChange={value => onChange?.({ ...config, spot_dwell_time: value })} />}
+                        />
+                        <ConfigTableRow
+                            label={<ConfigLabel name="Flying altitude" helpText="How long to climb (Space) after taking off. The same time is used to descend (C) before landing. Default : 1500" />}
+                            item={<NumericInput unit='ms' value={config.flying_altitude} onChange={value => onChange?.({ ...config, flying_altitude: value })} />}
+                        />
+                        <ConfigTableRow
+                            layout="v"
+                            label={<ConfigLabel name="Hunting spots" helpText="Route flown in a loop. Each spot is reached from the previous one by flying forward (ms) in a heading (degrees, positive to the right) taken from the direction faced on arrival at the previous spot, or when the bot started for the first one." />}
+                            item={<HuntingSpotList spots={config.hunting_spots ?? []} onChange={value => onChange?.({ ...config, hunting_spots: value })} />}
+                        />
+                    </>)}
                     <ConfigTableRow
                         label={<ConfigLabel name="Stop mob detection" helpText="Stop mob searching but keeps benefit of using the bot like item pickup, buffs, restoration, etc..." />}
                         item={<BooleanSlider value={config.is_stop_fighting ?? false} onChange={value => onChange?.({ ...config, is_stop_fighting: value })} />}
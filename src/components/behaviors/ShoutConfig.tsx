@@ -3,6 +3,8 @@ import ConfigPanel from '../config/ConfigPanel'
 
 import NumericInput from '../config/NumericInput'
 import StringList from '../config/StringList'
+import ShoutMessageList from '../config/ShoutMessageList'
+import BooleanSlider from '../config/BooleanSlider'
 
 import { ShoutConfigModel } from '../../models/BotConfig'
 import ConfigTable from '../config/ConfigTable'
@@ -28,7 +30,7 @@ const ShoutConfig = ({ config, onChange }: Props) => {
                 <ConfigTable>
                     <ConfigTableRow
                         layout="v"
-                        label={<ConfigLabel name="Messages" helpText="Add messages to the list to shout multiple messages. One message per line." />}
+                        label={<ConfigLabel name="Messages" helpText="Add messages to the list to shout multiple messages. One message per line. They are shouted as written, placeholders only work in scheduled messages." />}
                         item={<StringList messages={config.shout_messages ?? []} onChange={value => onChange?.({ ...config, shout_messages: value })} />}
                     />
                     <ConfigTableRow
@@ -36,6 +38,23 @@ const ShoutConfig = ({ config, onChange }: Props) => {
                         label={<ConfigLabel name="Interval" helpText="Interval between shouts in milliseconds." />}
                         item={<NumericInput unit="ms" value={config.shout_interval} onChange={value => onChange?.({ ...config, shout_interval: value })} />}
                     />
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Scheduled messages" helpText="Messages with their own interval, random jitter and chat channel. Supports {time}, {elapsed} and {a|b|c} (random pick) placeholders." />}
+                        item={<ShoutMessageList messages={config.scheduled_messages ?? []} showWeight={config.shout_selection === 'WeightedRandom'} onChange={value => onChange?.({ ...config, scheduled_messages: value })} />}
+                    />
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Weighted random" helpText="Pick among due messages randomly using their weight instead of shouting them in order." />}
+                        item={<BooleanSlider value={config.shout_selection === 'WeightedRandom'} onChange={value => onChange?.({ ...config, shout_selection: value ? 'WeightedRandom' : 'RoundRobin' })} />}
+                    />
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Quiet hours start" helpText="Local hour (0-23) from which nothing is shouted. Leave empty to disable." />}
+                        item={<NumericInput unit="h" min={0} max={23} value={config.quiet_hours_start} onChange={value => onChange?.({ ...config, quiet_hours_start: value ?? undefined })} />}
+                    />
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Quiet hours end" helpText="Local hour (0-23) at which shouting resumes." />}
+                        item={<NumericInput unit="h" min={0} max={23} value={config.quiet_hours_end} onChange={value => onChange?.({ ...config, quiet_hours_end: value ?? undefined })} />}
+                    />
                 </ConfigTable>
             </ConfigPanel>
         </>
@@ -1,5 +1,5 @@
 import { createSlotBars, SupportConfigModel } from "../../models/BotConfig"
-import { FrontendInfoModel } from "../../models/FrontendInfo"
+import { FrontendInfoModel, sessionStateLabels } from "../../models/FrontendInfo"
 
 import Modal from '../Modal'
 import useModal from '../utils/UseModal'
@@ -15,6 +15,7 @@ import ConfigTableRow from '../config/ConfigTableRow'
 import styled from "styled-components"
 import { DefaultValuesChecker } from "../utils/DefaultValuesChecker"
 import NumericInput from "../config/NumericInput"
+import TextInput from "../config/TextInput"
 
 type Props = {
     className?: string,
@@ -31,11 +32,20 @@ const SupportConfig = ({ className, info, config, onChange, running, isCurrentMo
 
     const defaultValues = {
         'jump_cooldown': 5000,
+        'assist_enabled': false,
+        'leader_target_command': '/target {leader}',
+        'follow_key': 'Z',
+        'follow_interval': 3000,
+        'reacquire_interval': 2000,
+        'assist_radius': 200,
+        'assist_timeout': 30000,
+        'mount_with_leader': true,
+        'mount_on_leader_lost': true,
     }
 
     DefaultValuesChecker(config, defaultValues, onChange)
 
-    let botState = running? info?.is_running? !info?.is_alive? "dead" : "healing" : "ready" : "idle"
+    let botState = running? info?.session_state && info.session_state !== "InGame"? sessionStateLabels[info.session_state] : info?.is_running? !info?.is_alive? "dead" : "healing" : "ready" : "idle"
 
     let botStopWatch = useStopWatch()
 
@@ -67,6 +77,48 @@ const SupportConfig = ({ className, info, config, onChange, running, isCurrentMo
                         label={<ConfigLabel name="Jump cooldown" helpText="Time between two jumps If set to 0 the character will never jump." />}
                         item={<NumericInput unit='ms' value={config.jump_cooldown} onChange={value => onChange?.({...config, jump_cooldown: value})} />}
                     />
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Assist leader" helpText="Follow the leader, attack its target and select the leader again after every interruption. A farming leader instance on the bus tells where its target stands, the monster at the same place around you is attacked. Other leaders' targets can't be read, the monsters attacked are then a guess: aggressive ones around you, and any one right after a leader instance reported a new target. Uses attack skill and board/mount slots." />}
+                        item={<BooleanSlider value={config.assist_enabled ?? false} onChange={value => onChange?.({...config, assist_enabled: value})} />}
+                    />
+                    {config.assist_enabled && (<>
+                        <ConfigTableRow
+                            label={<ConfigLabel name="Leader name" helpText="Name of the character to follow." />}
+                            item={<TextInput value={config.leader_name} onChange={value => onChange?.({...config, leader_name: value})} />}
+                        />
+                        <ConfigTableRow
+                            label={<ConfigLabel name="Target command" helpText="Chat command used to select the leader, {leader} is replaced by its name. Leave empty to select the leader by hand. When it doesn't select anyone 3 times in a row it's tried 10 times less often. Default : /target {leader}" />}
+                            item={<TextInput value={config.leader_target_command} onChange={value => onChange?.({...config, leader_target_command: value})} />}
+                        />
+                        <ConfigTableRow
+                            label={<ConfigLabel name="Follow key" helpText="Key following the selected target. Default : Z" />}
+                            item={<TextInput value={config.follow_key} onChange={value => onChange?.({...config, follow_key: value})} />}
+                        />
+                        <ConfigTableRow
+                            label={<ConfigLabel name="Follow interval" helpText="Time between two follow key presses, so following resumes after interruptions. Default : 3000" />}
+                            item={<NumericInput unit='ms' value={config.follow_interval} onChange={value => onChange?.({...config, follow_interval: value})} />}
+                        />
+                        <ConfigTableRow
+                            label={<ConfigLabel name="Reacquire interval" helpText="Minimum time between two attempts to select the leader. Default : 2000" />}
+                            item={<NumericInput unit='ms' value={config.reacquire_interval} onChange={value => onChange?.({...config, reacquire_interval: value})} />}
+                        />
+                        <ConfigTableRow
+                            label={<ConfigLabel name="Assist radius" helpText="Distance from the place the leader's target was reported at, or around your character when guessing, in which monsters are attacked. Default : 200" />}
+                            item={<NumericInput unit='px' value={config.assist_radius} onChange={value => onChange?.({...config, assist_radius: value})} />}
+                        />
+                        <ConfigTableRow
+                            label={<ConfigLabel name="Assist timeout" helpText="Give up on a monster and go back to the leader after this time. Default : 30000" />}
+                            item={<NumericInput unit='ms' value={config.assist_timeout} onChange={value => onChange?.({...config, assist_timeout: value})} />}
+                        />
+                        <ConfigTableRow
+                            label={<ConfigLabel name="Mount with leader" helpText="Use the board/mount slot when a leader instance on the bus mounts or lands." />}
+                            item={<BooleanSlider value={config.mount_with_leader ?? true} onChange={value => onChange?.({...config, mount_with_leader: value})} />}
+                        />
+                        <ConfigTableRow
+                            label={<ConfigLabel name="Mount when leader is lost (heuristic)" helpText="Use the board/mount slot when the leader runs out of range. Only for leaders that aren't instances on the bus, their mount can't be seen and losing them is taken as a sign they mounted." />}
+                            item={<BooleanSlider value={config.mount_on_leader_lost ?? true} onChange={value => onChange?.({...config, mount_on_leader_lost: value})} />}
+                        />
+                    </>)}
                 </ConfigTable>
             </ConfigPanel>
 
@@ -0,0 +1,117 @@
+import styled from "styled-components"
+import Select from 'react-select'
+
+import { ChatChannelModel, ChatRuleActionModel, ChatRuleModel, chatChannels } from "../../models/BotConfig"
+import BooleanSlider from "./BooleanSlider"
+
+type Props = {
+    className?: string,
+    rules: ChatRuleModel[],
+    onChange: (value: ChatRuleModel[]) => void,
+}
+
+const channelOptions = chatChannels.map(channel => ({ value: channel, label: channel }))
+const actionOptions = [
+    { value: "Reply", label: "Reply" },
+    { value: "PauseBot", label: "Pause bot" },
+    { value: "Alert", label: "Alert" },
+]
+
+const actionValue = (action: ChatRuleActionModel) => typeof action === "string" ? action : "Reply"
+
+const ChatRuleList = ({ className, rules, onChange }: Props) => {
+    const update = (index: number, rule: ChatRuleModel) => {
+        const newRules = [...rules]
+        newRules[index] = rule
+        onChange(newRules)
+    }
+
+    const remove = (index: number) => onChange(rules.filter((_, i) => i !== index))
+
+    const add = () => onChange([...rules, { pattern: "", channels: ["Whisper"], action: "Alert", enabled: true }])
+
+    return (
+        <div className={className}>
+            {rules.map((rule, index) => (
+                <div className="rule" key={index}>
+                    <div className="row">
+                        <BooleanSlider value={rule.enabled ?? true} onChange={enabled => update(index, { ...rule, enabled })} />
+                        <input className="text" type="text" value={rule.pattern} placeholder="Keyword"
+                            onChange={e => update(index, { ...rule, pattern: e.target.value })} />
+                        <label className="regex" title="Treat the pattern as a regular expression">
+                            <input type="checkbox" checked={rule.is_regex ?? false} onChange={e => update(index, { ...rule, is_regex: e.target.checked })} />
+                            regex
+                        </label>
+                        <button onClick={() => remove(index)}>&times;</button>
+                    </div>
+                    <div className="row">
+                        <div className="select channels">
+                            <Select isMulti placeholder="All channels" options={channelOptions} value={channelOptions.filter(x => rule.channels?.includes(x.value))}
+                                onChange={options => update(index, { ...rule, channels: options.map(x => x.value as ChatChannelModel) })} />
+                        </div>
+                        <div className="select">
+                            <Select options={actionOptions} value={actionOptions.find(x => x.value === actionValue(rule.action))}
+                                onChange={option => option && update(index, { ...rule, action: option.value === "Reply" ? { Reply: "" } : option.value as ChatRuleActionModel })} />
+                        </div>
+                    </div>
+                    {typeof rule.action !== "string" && (
+                        <input className="text" type="text" value={rule.action.Reply} placeholder="Reply, e.g. sorry {sender}, afk"
+                            onChange={e => update(index, { ...rule, action: { Reply: e.target.value } })} />
+                    )}
+                </div>
+            ))}
+            <div className="btn" onClick={add}>Add rule</div>
+        </div>
+    )
+}
+
+export default styled(ChatRuleList)`
+    display: flex;
+    flex-direction: column;
+    gap: .75rem;
+    width: 100%;
+
+    & .rule {
+        display: flex;
+        flex-direction: column;
+        gap: .25rem;
+    }
+
+    & .row {
+        display: flex;
+        align-items: center;
+        gap: .5rem;
+    }
+
+    & .text {
+        flex-grow: 1;
+        background: hsla(0,0%,0%,.75);
+        color: white;
+        border: 2px solid hsla(0,0%,0%,.75);
+        border-radius: .25rem;
+        padding: .1rem .25rem;
+    }
+
+    & .regex {
+        display: flex;
+        align-items: center;
+        gap: .25rem;
+        color: white;
+    }
+
+    & .select {
+        min-width: 8rem;
+        color: black;
+    }
+
+    & .channels {
+        flex-grow: 1;
+    }
+
+    & .btn {
+        width: 100% !important;
+        font-size: 1rem !important;
+        height: 2rem !important;
+        padding: .25rem 1rem !important;
+    }
+`
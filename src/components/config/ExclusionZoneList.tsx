@@ -0,0 +1,58 @@
+import styled from "styled-components"
+
+import { BoundsModel } from "../../models/BotConfig"
+import NumericInput from "./NumericInput"
+
+type Props = {
+    className?: string,
+    zones: BoundsModel[],
+    onChange: (value: BoundsModel[]) => void,
+}
+
+const fields: (keyof BoundsModel)[] = ["x", "y", "w", "h"]
+
+const ExclusionZoneList = ({ className, zones, onChange }: Props) => {
+    const update = (index: number, zone: BoundsModel) => {
+        const newZones = [...zones]
+        newZones[index] = zone
+        onChange(newZones)
+    }
+
+    const remove = (index: number) => onChange(zones.filter((_, i) => i !== index))
+
+    const add = () => onChange([...zones, { x: 0, y: 0, w: 200, h: 200 }])
+
+    return (
+        <div className={className}>
+            {zones.map((zone, index) => (
+                <div className="zone" key={index}>
+                    {fields.map(field => (
+                        <NumericInput key={field} unit={field} min={0} value={zone[field]} onChange={value => update(index, { ...zone, [field]: value ?? 0 })} />
+                    ))}
+                    <button onClick={() => remove(index)}>&times;</button>
+                </div>
+            ))}
+            <div className="btn" onClick={add}>Add zone</div>
+        </div>
+    )
+}
+
+export default styled(ExclusionZoneList)`
+    display: flex;
+    flex-direction: column;
+    gap: .5rem;
+    width: 100%;
+
+    & .zone {
+        display: flex;
+        align-items: center;
+        gap: .5rem;
+    }
+
+    & .btn {
+        width: 100% !important;
+        font-size: 1rem !important;
+        height: 2rem !important;
+        padding: .25rem 1rem !important;
+    }
+`
@@ -0,0 +1,66 @@
+import styled from "styled-components"
+
+import { HuntingSpotModel } from "../../models/BotConfig"
+import NumericInput from "./NumericInput"
+
+type Props = {
+    className?: string,
+    spots: HuntingSpotModel[],
+    onChange: (value: HuntingSpotModel[]) => void,
+}
+
+const HuntingSpotList = ({ className, spots, onChange }: Props) => {
+    const update = (index: number, spot: HuntingSpotModel) => {
+        const newSpots = [...spots]
+        newSpots[index] = spot
+        onChange(newSpots)
+    }
+
+    const remove = (index: number) => onChange(spots.filter((_, i) => i !== index))
+
+    const add = () => onChange([...spots, { name: `Spot ${spots.length + 1}`, turn: 0, flight_duration: 10000 }])
+
+    return (
+        <div className={className}>
+            {spots.map((spot, index) => (
+                <div className="spot" key={index}>
+                    <input className="text" type="text" value={spot.name ?? ""} placeholder="Name"
+                        onChange={e => update(index, { ...spot, name: e.target.value })} />
+                    <NumericInput unit="°" min={-180} max={180} value={spot.turn} onChange={value => update(index, { ...spot, turn: value ?? undefined })} />
+                    <NumericInput unit="ms" min={0} value={spot.flight_duration} onChange={value => update(index, { ...spot, flight_duration: value ?? undefined })} />
+                    <button onClick={() => remove(index)}>&times;</button>
+                </div>
+            ))}
+            <div className="btn" onClick={add}>Add hunting spot</div>
+        </div>
+    )
+}
+
+export default styled(HuntingSpotList)`
+    display: flex;
+    flex-direction: column;
+    gap: .5rem;
+    width: 100%;
+
+    & .spot {
+        display: flex;
+        align-items: center;
+        gap: .5rem;
+    }
+
+    & .text {
+        min-width: 6rem;
+        background: hsla(0,0%,0%,.75);
+        color: white;
+        border: 2px solid hsla(0,0%,0%,.75);
+        border-radius: .25rem;
+        padding: .1rem .25rem;
+    }
+
+    & .btn {
+        width: 100% !important;
+        font-size: 1rem !important;
+        height: 2rem !important;
+        padding: .25rem 1rem !important;
+    }
+`
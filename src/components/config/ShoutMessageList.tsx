@@ -0,0 +1,89 @@
+import styled from "styled-components"
+import Select from 'react-select'
+
+import { ShoutChannelModel, ShoutMessageModel } from "../../models/BotConfig"
+import NumericInput from "./NumericInput"
+
+type Props = {
+    className?: string,
+    messages: ShoutMessageModel[],
+    showWeight?: boolean,
+    onChange: (value: ShoutMessageModel[]) => void,
+}
+
+const channels: ShoutChannelModel[] = ["Normal", "Shout", "Party", "Guild"]
+const channelOptions = channels.map(channel => ({ value: channel, label: channel }))
+
+const ShoutMessageList = ({ className, messages, showWeight, onChange }: Props) => {
+    const update = (index: number, message: ShoutMessageModel) => {
+        const newMessages = [...messages]
+        newMessages[index] = message
+        onChange(newMessages)
+    }
+
+    const remove = (index: number) => onChange(messages.filter((_, i) => i !== index))
+
+    const add = () => onChange([...messages, { text: "", interval: 60000, jitter: 0, weight: 1, channel: "Normal" }])
+
+    return (
+        <div className={className}>
+            {messages.map((message, index) => (
+                <div className="message" key={index}>
+                    <input className="text" type="text" value={message.text} placeholder="Message, e.g. Selling {swords|shields} ({time})"
+                        onChange={e => update(index, { ...message, text: e.target.value })} />
+                    <div className="row">
+                        <NumericInput unit="ms" value={message.interval} onChange={value => update(index, { ...message, interval: value ?? undefined })} />
+                        <NumericInput unit="± ms" min={0} value={message.jitter} onChange={value => update(index, { ...message, jitter: value ?? undefined })} />
+                        {showWeight && <NumericInput unit="weight" min={1} value={message.weight} onChange={value => update(index, { ...message, weight: value ?? undefined })} />}
+                        <div className="select">
+                            <Select options={channelOptions} value={channelOptions.find(x => x.value === (message.channel ?? "Normal"))}
+                                onChange={option => option && update(index, { ...message, channel: option.value })} />
+                        </div>
+                        <button onClick={() => remove(index)}>&times;</button>
+                    </div>
+                </div>
+            ))}
+            <div className="btn" onClick={add}>Add message</div>
+        </div>
+    )
+}
+
+export default styled(ShoutMessageList)`
+    display: flex;
+    flex-direction: column;
+    gap: .5rem;
+    width: 100%;
+
+    & .message {
+        display: flex;
+        flex-direction: column;
+        gap: .25rem;
+    }
+
+    & .text {
+        width: 100%;
+        background: hsla(0,0%,0%,.75);
+        color: white;
+        border: 2px solid hsla(0,0%,0%,.75);
+        border-radius: .25rem;
+        padding: .1rem .25rem;
+    }
+
+    & .row {
+        display: flex;
+        align-items: center;
+        gap: .5rem;
+    }
+
+    & .select {
+        min-width: 8rem;
+        color: black;
+    }
+
+    & .btn {
+        width: 100% !important;
+        font-size: 1rem !important;
+        height: 2rem !important;
+        padding: .25rem 1rem !important;
+    }
+`
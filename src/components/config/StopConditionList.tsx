@@ -0,0 +1,93 @@
+import styled from "styled-components"
+import Select from 'react-select'
+
+import { ModeModel, SlotType, StopActionModel, StopConditionModel, StopTriggerType, stopTriggerTypes, thresholdSlotTypes, translateDesc, translateStopTrigger } from "../../models/BotConfig"
+import BooleanSlider from "./BooleanSlider"
+import NumericInput from "./NumericInput"
+
+type Props = {
+    className?: string,
+    conditions: StopConditionModel[],
+    onChange: (value: StopConditionModel[]) => void,
+}
+
+const modes: ModeModel[] = ["Farming", "Support", "AutoShout"]
+
+const triggerOptions = stopTriggerTypes.map(type => ({ value: type, label: translateStopTrigger(type)[0] }))
+const slotTypeOptions = thresholdSlotTypes.map(type => ({ value: type, label: translateDesc(type as SlotType)[1] }))
+const actionOptions = [
+    { value: "Stop", label: "Stop" },
+    { value: "Logout", label: "Logout" },
+    ...modes.map(mode => ({ value: mode, label: `Switch to ${mode}` })),
+]
+
+const triggerType = (condition: StopConditionModel) => Object.keys(condition.trigger)[0] as StopTriggerType
+const actionValue = (action: StopActionModel) => typeof action === "string" ? action : action.SwitchMode
+
+const StopConditionList = ({ className, conditions, onChange }: Props) => {
+    const update = (index: number, condition: StopConditionModel) => {
+        const newConditions = [...conditions]
+        newConditions[index] = condition
+        onChange(newConditions)
+    }
+
+    const remove = (index: number) => onChange(conditions.filter((_, i) => i !== index))
+
+    const add = () => onChange([...conditions, { trigger: { ElapsedTime: 60 }, action: "Stop", enabled: true }])
+
+    return (
+        <div className={className}>
+            {conditions.map((condition, index) => {
+                const type = triggerType(condition)
+                const value = condition.trigger[type]
+                return (
+                    <div className="condition" key={index}>
+                        <BooleanSlider value={condition.enabled ?? true} onChange={enabled => update(index, { ...condition, enabled })} />
+                        <div className="select">
+                            <Select options={triggerOptions} value={triggerOptions.find(x => x.value === type)}
+                                onChange={option => option && update(index, { ...condition, trigger: { [option.value]: option.value === "SlotTypeExhausted" ? "Pill" : 1 } })} />
+                        </div>
+                        {type === "SlotTypeExhausted"
+                            ? <div className="select">
+                                <Select options={slotTypeOptions} value={slotTypeOptions.find(x => x.value === value)}
+                                    onChange={option => option && update(index, { ...condition, trigger: { SlotTypeExhausted: option.value as SlotType } })} />
+                            </div>
+                            : <NumericInput unit={translateStopTrigger(type)[1]} value={value as number} onChange={value => update(index, { ...condition, trigger: { [type]: value } })} />
+                        }
+                        <div className="select">
+                            <Select options={actionOptions} value={actionOptions.find(x => x.value === actionValue(condition.action))}
+                                onChange={option => option && update(index, { ...condition, action: option.value === "Stop" || option.value === "Logout" ? option.value : { SwitchMode: option.value as ModeModel } })} />
+                        </div>
+                        <button onClick={() => remove(index)}>&times;</button>
+                    </div>
+                )
+            })}
+            <div className="btn" onClick={add}>Add condition</div>
+        </div>
+    )
+}
+
+export default styled(StopConditionList)`
+    display: flex;
+    flex-direction: column;
+    gap: .5rem;
+    width: 100%;
+
+    & .condition {
+        display: flex;
+        align-items: center;
+        gap: .5rem;
+    }
+
+    & .select {
+        min-width: 10rem;
+        color: black;
+    }
+
+    & .btn {
+        width: 100% !important;
+        font-size: 1rem !important;
+        height: 2rem !important;
+        padding: .25rem 1rem !important;
+    }
+`
@@ -0,0 +1,84 @@
+import styled from "styled-components"
+import Select from 'react-select'
+
+import { TargetFilterModel, TargetFilterType, targetFilterTypes, translateTargetFilter } from "../../models/BotConfig"
+import NumericInput from "./NumericInput"
+
+type Props = {
+    className?: string,
+    filters: TargetFilterModel[],
+    onChange: (value: TargetFilterModel[]) => void,
+}
+
+const filterOptions = targetFilterTypes.map(type => ({ value: type, label: translateTargetFilter(type)[0] }))
+
+const filterType = (filter: TargetFilterModel) => (typeof filter === "string" ? filter : Object.keys(filter)[0]) as TargetFilterType
+const filterValue = (filter: TargetFilterModel) => typeof filter === "string" ? undefined : Object.values(filter)[0]
+
+const makeFilter = (type: TargetFilterType, value?: number): TargetFilterModel => {
+    switch (type) {
+        case 'MaxDistance':
+        case 'MinNameWidth':
+        case 'MaxNameWidth':
+            return { [type]: value ?? 0 } as TargetFilterModel
+        default:
+            return type
+    }
+}
+
+const TargetFilterList = ({ className, filters, onChange }: Props) => {
+    const update = (index: number, filter: TargetFilterModel) => {
+        const newFilters = [...filters]
+        newFilters[index] = filter
+        onChange(newFilters)
+    }
+
+    const remove = (index: number) => onChange(filters.filter((_, i) => i !== index))
+
+    const add = () => onChange([...filters, "NotAvoided"])
+
+    return (
+        <div className={className}>
+            {filters.map((filter, index) => {
+                const type = filterType(filter)
+                const unit = translateTargetFilter(type)[1]
+                return (
+                    <div className="filter" key={index}>
+                        <div className="select">
+                            <Select options={filterOptions} value={filterOptions.find(x => x.value === type)}
+                                onChange={option => option && update(index, makeFilter(option.value, 100))} />
+                        </div>
+                        {unit && <NumericInput unit={unit} min={0} value={filterValue(filter)} onChange={value => update(index, makeFilter(type, value ?? 0))} />}
+                        <button onClick={() => remove(index)}>&times;</button>
+                    </div>
+                )
+            })}
+            <div className="btn" onClick={add}>Add filter</div>
+        </div>
+    )
+}
+
+export default styled(TargetFilterList)`
+    display: flex;
+    flex-direction: column;
+    gap: .5rem;
+    width: 100%;
+
+    & .filter {
+        display: flex;
+        align-items: center;
+        gap: .5rem;
+    }
+
+    & .select {
+        min-width: 12rem;
+        color: black;
+    }
+
+    & .btn {
+        width: 100% !important;
+        font-size: 1rem !important;
+        height: 2rem !important;
+        padding: .25rem 1rem !important;
+    }
+`
@@ -0,0 +1,37 @@
+import styled from "styled-components"
+
+type Props = {
+    className?: string,
+    value: string | undefined,
+    placeholder?: string,
+    type?: "text" | "password",
+    onChange: (value: string) => void,
+}
+
+const TextInput = ({ className, value, placeholder, type, onChange }: Props) => {
+    return (
+        <div className={className}>
+            <input type={type ?? "text"} value={value ?? ""} placeholder={placeholder} onChange={(e) => onChange(e.target.value)} />
+        </div>
+    )
+}
+
+export default styled(TextInput)`
+    display: flex;
+    align-items: center;
+    background: hsla(0,0%,0%,.75);
+    width: 100%;
+    flex-grow: 1;
+    border-radius: .25rem;
+    position: relative;
+    border: 2px solid hsla(0,0%,0%,.75);
+    padding: .1rem .25rem;
+    color: white;
+
+    & input {
+        all: unset;
+        display: flex;
+        flex-grow: 1;
+        height: 100%;
+    }
+`
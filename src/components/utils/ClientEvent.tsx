@@ -0,0 +1,4 @@
+// Events of a bot are scoped to the label of its client window, see `ipc::client_event`
+export function clientEvent(client: string, event: string) {
+    return `${client}/${event}`;
+}
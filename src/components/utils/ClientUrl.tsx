@@ -0,0 +1,3 @@
+// Page loaded in the client windows. Point REACT_APP_CLIENT_URL to `stand-in/reconnect.html` to
+// try the bot against a local page mimicking the game screens.
+export const CLIENT_URL = process.env.REACT_APP_CLIENT_URL || 'https://universe.flyff.com/play';
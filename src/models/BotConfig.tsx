@@ -5,11 +5,11 @@ import HealSkill from '../assets/heal_spell.png'
 
 export type FixedArray<TItem, TLength extends number> = [TItem, ...TItem[]] & { length: TLength }
 
-export const slotTypes = ["Unused", "Food", "Pill", "HealSkill", "MpRestorer", "FpRestorer", "PickupPet", "PickupMotion", "AttackSkill", "BuffSkill", "Flying"] as const;
+export const slotTypes = ["Unused", "Food", "Pill", "HealSkill", "MpRestorer", "FpRestorer", "PickupPet", "PickupMotion", "AttackSkill", "BuffSkill", "Flying", "AoeSkill"] as const;
 export const thresholdSlotTypes = ["Food", "Pill", "HealSkill", "MpRestorer", "FpRestorer"];
-export const cooldownSlotTypes = ["Food", "Pill", "HealSkill", "AttackSkill", "BuffSkill", "MpRestorer", "FpRestorer", "PickupPet"];
-export const farmingSlotsBlacklist = ["HealSkill", "Flying"]
-export const supportSlotsBlacklist = ["PickupPet", "PickupMotion", "AttackSkill"]
+export const cooldownSlotTypes = ["Food", "Pill", "HealSkill", "AttackSkill", "BuffSkill", "MpRestorer", "FpRestorer", "PickupPet", "AoeSkill"];
+export const farmingSlotsBlacklist = ["HealSkill"]
+export const supportSlotsBlacklist = ["PickupPet", "PickupMotion", "AoeSkill"]
 
 export type SlotType = typeof slotTypes[number];
 
@@ -32,6 +32,7 @@ export const translateType = (type: SlotType) => {
         case 'AttackSkill': return '🗡️'
         case 'BuffSkill': return '🪄'
         case 'Flying': return '✈️'
+        case 'AoeSkill': return '💥'
     }
 }
 
@@ -48,6 +49,7 @@ export const translateDesc = (type: SlotType, defaultUnused: string = '') => {
         case 'AttackSkill': return ['Attack', 'Attack skill']
         case 'BuffSkill': return ['Buff', 'Buff skill']
         case 'Flying': return ['Board', 'Board']
+        case 'AoeSkill': return ['AoE', 'AoE skill']
     }
 }
 export type SlotModel = {
@@ -65,6 +67,42 @@ export type SlotBars = FixedArray<SlotBarHolder, 9>
 
 export type ModeModel = "Farming" | "Support" | "AutoShout"
 
+export type FarmingStrategyModel = "SingleTarget" | "AoePull"
+
+export const scoringPresets = ["AggressiveFirst", "Closest", "BigMobs", "Steady", "Custom"] as const;
+export type ScoringPresetModel = typeof scoringPresets[number];
+
+export type ScoringWeightsModel = {
+    distance: number,
+    aggressive: number,
+    name_width: number,
+    seen_time: number,
+    avoid_proximity: number,
+}
+
+export const targetFilterTypes = ["MaxDistance", "MinNameWidth", "MaxNameWidth", "OnlyAggressive", "OnlyPassive", "NotAvoided", "PassiveMinHp"] as const;
+export type TargetFilterType = typeof targetFilterTypes[number];
+export type TargetFilterModel = "OnlyAggressive" | "OnlyPassive" | "NotAvoided" | "PassiveMinHp"
+    | { MaxDistance: number } | { MinNameWidth: number } | { MaxNameWidth: number }
+
+export const translateTargetFilter = (type: TargetFilterType) => {
+    switch (type) {
+        case 'MaxDistance': return ['Max distance', 'px']
+        case 'MinNameWidth': return ['Min name width', 'px']
+        case 'MaxNameWidth': return ['Max name width', 'px']
+        case 'OnlyAggressive': return ['Only aggressive', '']
+        case 'OnlyPassive': return ['Only passive', '']
+        case 'NotAvoided': return ['Skip avoided areas', '']
+        case 'PassiveMinHp': return ['Passive needs min HP', '']
+    }
+}
+
+export type HuntingSpotModel = {
+    name?: string,
+    turn?: number,
+    flight_duration?: number,
+}
+
 export type FarmingConfigModel = Partial<{
     [key: string]: any;
     on_demand_pet: boolean,
@@ -72,6 +110,7 @@ export type FarmingConfigModel = Partial<{
     stay_in_area: boolean,
     slot_bars: SlotBars,
     circle_pattern_rotation_duration: number,
+    search_script: string,
 
     passive_mobs_colors: number[];
     passive_tolerence: number;
@@ -91,21 +130,172 @@ export type FarmingConfigModel = Partial<{
 
     min_hp_attack: number,
 
+    leash_enabled: boolean,
+    leash_radius: number,
+
+    farming_strategy: FarmingStrategyModel,
+    aoe_pull_count: number,
+    aoe_cluster_radius: number,
+    aoe_gather_timeout: number,
+
+    kiting_enabled: boolean,
+    kiting_min_distance: number,
+    kiting_retreat_duration: number,
+    kiting_cooldown: number,
+
+    stuck_detection_enabled: boolean,
+    stuck_duration: number,
 
+    attack_timeout: number,
+
+    flying_enabled: boolean,
+    flying_altitude: number,
+    hunting_spots: HuntingSpotModel[],
+    spot_dwell_time: number,
+
+    scoring_preset: ScoringPresetModel,
+    scoring_weights: ScoringWeightsModel,
+    target_filters: TargetFilterModel[],
 }>
 
 export type SupportConfigModel = Partial<{
     [key: string]: any;
     slot_bars: SlotBars,
     jump_cooldown: number,
+
+    assist_enabled: boolean,
+    leader_name: string,
+    leader_target_command: string,
+    follow_key: string,
+    follow_interval: number,
+    reacquire_interval: number,
+    assist_radius: number,
+    assist_timeout: number,
+    mount_with_leader: boolean,
+    mount_on_leader_lost: boolean,
 }>
 
+export type ShoutChannelModel = "Normal" | "Shout" | "Party" | "Guild"
+export type ShoutSelectionModel = "RoundRobin" | "WeightedRandom"
+
+export type ShoutMessageModel = {
+    text: string,
+    interval?: number,
+    jitter?: number,
+    weight?: number,
+    channel?: ShoutChannelModel,
+}
+
 export type ShoutConfigModel = Partial<{
     [key: string]: any;
     shout_interval: number,
     shout_messages: string[],
+
+    scheduled_messages: ShoutMessageModel[],
+    shout_selection: ShoutSelectionModel,
+    quiet_hours_start: number,
+    quiet_hours_end: number,
+}>
+
+export const stopTriggerTypes = ["ElapsedTime", "KillCount", "SlotTypeExhausted", "ConsecutiveDeaths", "MissclickResets"] as const;
+export type StopTriggerType = typeof stopTriggerTypes[number];
+export type StopTriggerModel = { [key in StopTriggerType]?: number | SlotType }
+export type StopActionModel = "Stop" | "Logout" | { SwitchMode: ModeModel }
+
+export type StopConditionModel = {
+    trigger: StopTriggerModel,
+    action: StopActionModel,
+    enabled?: boolean,
+}
+
+export const translateStopTrigger = (type: StopTriggerType) => {
+    switch (type) {
+        case 'ElapsedTime': return ['Elapsed time', 'min']
+        case 'KillCount': return ['Kill count', '#']
+        case 'SlotTypeExhausted': return ['Restoration exhausted', '']
+        case 'ConsecutiveDeaths': return ['Consecutive deaths', '#']
+        case 'MissclickResets': return ['Missclick resets', '#']
+    }
+}
+
+export type SessionConfigModel = Partial<{
+    [key: string]: any;
+    stop_conditions: StopConditionModel[],
+}>
+
+export const chatChannels = ["Normal", "Shout", "Party", "Guild", "Whisper", "System"] as const;
+export type ChatChannelModel = typeof chatChannels[number];
+export type ChatRuleActionModel = "PauseBot" | "Alert" | { Reply: string }
+
+export type ChatRuleModel = {
+    pattern: string,
+    is_regex?: boolean,
+    channels?: ChatChannelModel[],
+    action: ChatRuleActionModel,
+    cooldown?: number,
+    enabled?: boolean,
+}
+
+export type ChatConfigModel = Partial<{
+    [key: string]: any;
+    enabled: boolean,
+    character_name: string,
+    rules: ChatRuleModel[],
+    pause_duration: number,
 }>
 
+export type ChatMessageModel = {
+    channel: ChatChannelModel,
+    sender?: string,
+    text: string,
+}
+
+export type InstanceRoleModel = "Leader" | "Follower"
+
+export type BusConfigModel = Partial<{
+    [key: string]: any;
+    enabled: boolean,
+    role: InstanceRoleModel,
+    hp_low_threshold: number,
+    buff_request_interval: number,
+}>
+
+export type ReconnectScreenModel = 'Server' | 'Character' | 'Disconnected'
+
+export type CanvasScreenModel = Partial<{
+    [key: string]: any;
+    signature: [number, number, number][],
+    click_x: number,
+    click_y: number,
+    confirm_key: string,
+}>
+
+export type ReconnectConfigModel = Partial<{
+    [key: string]: any;
+    enabled: boolean,
+    max_attempts: number,
+    initial_delay: number,
+    max_delay: number,
+    backoff_factor: number,
+    attempt_timeout: number,
+    server_index: number,
+    character_index: number,
+    server_screen: CanvasScreenModel,
+    character_screen: CanvasScreenModel,
+    disconnected_screen: CanvasScreenModel,
+}>
+
+export type BoundsModel = {
+    x: number,
+    y: number,
+    w: number,
+    h: number,
+}
+
+export type InputBackendModel = "Webview" | "X11"
+
+export type CaptureBackendModel = "Window" | "Canvas"
+
 export type BotConfigModel = {
     change_id: number,
     is_running: boolean,
@@ -113,6 +303,13 @@ export type BotConfigModel = {
     farming_config: FarmingConfigModel,
     support_config: SupportConfigModel,
     shout_config: ShoutConfigModel,
+    session_config: SessionConfigModel,
+    chat_config: ChatConfigModel,
+    bus_config: BusConfigModel,
+    reconnect_config?: ReconnectConfigModel,
+    exclusion_zones?: BoundsModel[],
+    input_backend?: InputBackendModel,
+    capture_backend?: CaptureBackendModel,
 }
 
-export type AnyConfig = FarmingConfigModel | SupportConfigModel | ShoutConfigModel
+export type AnyConfig = FarmingConfigModel | SupportConfigModel | ShoutConfigModel | SessionConfigModel | ChatConfigModel | BusConfigModel | ReconnectConfigModel
@@ -1,3 +1,16 @@
+import { CaptureBackendModel, SlotType } from './BotConfig'
+
+export type SessionStateModel = "Loading" | "Login" | "CharacterSelect" | "InGame" | "Disconnected" | "Unknown"
+
+export const sessionStateLabels: Record<SessionStateModel, string> = {
+    Loading: "loading",
+    Login: "login screen",
+    CharacterSelect: "character select",
+    InGame: "in game",
+    Disconnected: "disconnected",
+    Unknown: "unknown screen",
+}
+
 export type FrontendInfoModel = {
     /* enemy_bounds?: Bounds[],
     active_enemy_bounds?: Bounds, */
@@ -7,5 +20,38 @@ export type FrontendInfoModel = {
     is_alive: boolean,
     kill_min_avg: number,
     kill_hour_avg: number,
+    missclick_reset_count: number,
+    exhausted_slot_type?: SlotType,
+    session_state?: SessionStateModel,
+    is_paused?: boolean,
+    chat_panel_found?: boolean,
     version: number[],
 }
+
+export type StateTransitionModel = {
+    from: string,
+    to: string,
+    reason: string,
+    timestamp: number,
+}
+
+export type RecordingResultModel = {
+    name: string,
+    error?: string,
+}
+
+export type CredentialsStatusModel = {
+    stored: boolean,
+    error?: string,
+}
+
+export type ProfileListModel = {
+    profiles: string[],
+    active: string,
+}
+
+export type CaptureBenchmarkModel = {
+    backend: CaptureBackendModel,
+    average_ms?: number,
+    failures: number,
+}
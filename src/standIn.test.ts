@@ -0,0 +1,87 @@
+import fs from 'fs'
+import path from 'path'
+
+// Drives the stand-in page with the dispatcher the bot injects in the client windows, through the
+// screens the bot goes when it reconnects.
+
+const read = (file: string) => fs.readFileSync(path.join(__dirname, '..', file), 'utf8')
+
+type Reply = { id: number, error: string | null, value: any }
+
+const replies: Reply[] = []
+const drawn: [string, number, number][] = []
+let nextId = 0
+
+const run = (command: object) => {
+    const id = nextId++
+    ;(window as any).neuzBridge(id, command)
+    const reply = replies.find(reply => reply.id === id)
+    expect(reply?.error).toBeNull()
+    return reply?.value
+}
+
+const pageState = () => run({ type: 'PageState' })
+
+// Whether the HP, MP and FP bars were drawn where the bot reads them since the last call
+const barsDrawn = () => {
+    const bars = drawn.filter(([, x, y]) => x === 110 && [40, 60, 80].includes(y))
+    drawn.length = 0
+    return bars.length === 3
+}
+
+beforeAll(() => {
+    jest.useFakeTimers()
+
+    // There is no layout here, an element has a box unless it or one of its parents isn't displayed
+    Element.prototype.getClientRects = function (this: Element) {
+        for (let element: Element | null = this; element; element = element.parentElement) {
+            if (window.getComputedStyle(element).display === 'none') return [] as any
+        }
+        return [{}] as any
+    }
+    HTMLCanvasElement.prototype.getContext = function () {
+        const context = {
+            fillStyle: '',
+            fillRect: (x: number, y: number) => drawn.push([context.fillStyle, x, y]),
+            createLinearGradient: () => ({ addColorStop: () => {} }),
+        }
+        return context
+    } as any
+    ;(window as any).__TAURI_INVOKE__ = (_: string, { message }: any) => {
+        if (message.event === 'bridge_result') replies.push(JSON.parse(message.payload))
+    }
+
+    const page = read('public/stand-in/reconnect.html')
+    document.documentElement.innerHTML = page.replace(/<!DOCTYPE html>/i, '')
+    new Function(document.querySelector('body script')!.textContent!)()
+    new Function(read('src-tauri/src/platform/js_bridge.js'))()
+})
+
+test('reconnects through the stand-in screens', () => {
+    // Login
+    expect(pageState()).toMatchObject({ has_canvas: true, has_password_input: true, has_selection_list: false, disconnected: false })
+    expect(run({ type: 'SelectEntry', list: 'server', index: 1 })).toBe(false)
+    run({ type: 'Login', username: 'player', password: 'secret' })
+    expect(pageState()).toMatchObject({ has_password_input: false, has_selection_list: false })
+    jest.advanceTimersByTime(1500)
+
+    // Server select, the hidden login form doesn't count anymore
+    expect(pageState()).toMatchObject({ has_password_input: false, has_selection_list: true })
+    expect(run({ type: 'SelectEntry', list: 'character', index: 2 })).toBe(false)
+    expect(run({ type: 'SelectEntry', list: 'server', index: 1 })).toBe(true)
+    jest.advanceTimersByTime(1500)
+
+    // Character select
+    expect(run({ type: 'SelectEntry', list: 'character', index: 2 })).toBe(true)
+    drawn.length = 0
+    jest.advanceTimersByTime(1500)
+
+    // In game
+    expect(pageState()).toMatchObject({ has_password_input: false, has_selection_list: false, disconnected: false })
+    expect(barsDrawn()).toBe(true)
+    expect(document.getElementById('summary')?.textContent).toBe('player, server 1, character 2')
+
+    // The disconnection dialog only counts once shown
+    document.getElementById('disconnect')!.click()
+    expect(pageState()).toMatchObject({ disconnected: true })
+})